@@ -0,0 +1,74 @@
+//! Fast boot support. RAM test loops of the original ROMs are executed instantly instead of
+//! running them instruction by instruction, leaving machine in the same state
+use crate::{
+    emulator::Emulator,
+    host::Host,
+    zx::constants::{
+        ADDR_RAM_CHECK_48K, ADDR_RAM_CLEAR_128K, ADDR_RAM_DONE_48K, ADDR_STARTUP_DELAY_128K,
+    },
+};
+use rustzx_z80::{RegName16, Z80Bus, FLAG_CARRY, FLAG_SIGN, FLAG_ZERO};
+
+// Code at the trap addresses, traps are ignored for custom ROMs which differ from the original
+// LD H,D; LD L,E; LD (HL),$02; DEC HL; CP H; JR NZ,RAM-FILL
+const RAM_CHECK_48K_CODE: [u8; 8] = [0x62, 0x6B, 0x36, 0x02, 0x2B, 0xBC, 0x20, 0xFA];
+// DEC BC; LD A,B; OR C; JR NZ,$0004
+const STARTUP_DELAY_128K_CODE: [u8; 5] = [0x0B, 0x78, 0xB1, 0x20, 0xFB];
+// LDIR; EXX; DJNZ $00C9
+const RAM_CLEAR_128K_CODE: [u8; 5] = [0xED, 0xB0, 0xD9, 0x10, 0xDF];
+
+const RAM_START: u16 = 0x4000;
+const LDIR_LENGTH: u16 = 2;
+
+fn code_matches<H: Host>(emulator: &mut Emulator<H>, addr: u16, code: &[u8]) -> bool {
+    code.iter()
+        .enumerate()
+        .all(|(offset, byte)| emulator.controller.read_internal(addr + offset as u16) == *byte)
+}
+
+/// Executes ROM RAM test loop at the current PC at once
+pub fn skip_ram_test<H: Host>(emulator: &mut Emulator<H>) {
+    let pc = emulator.cpu.regs.get_pc();
+    match pc {
+        ADDR_RAM_CHECK_48K if code_matches(emulator, pc, &RAM_CHECK_48K_CODE) => {
+            // RAM is filled with 2 from the top (DE) down to 0x4000 and then each byte is
+            // decremented twice on the way up, which leaves RAM cleared and HL pointing past
+            // the RAM top
+            let top = emulator.cpu.regs.get_reg_16(RegName16::DE);
+            for addr in RAM_START..=top {
+                emulator.controller.write_internal(addr, 0);
+            }
+            emulator
+                .cpu
+                .regs
+                .set_reg_16(RegName16::HL, top.wrapping_add(1));
+            emulator.cpu.regs.set_pc(ADDR_RAM_DONE_48K);
+        }
+        ADDR_STARTUP_DELAY_128K if code_matches(emulator, pc, &STARTUP_DELAY_128K_CODE) => {
+            // Delay loop ends after the next iteration
+            emulator.cpu.regs.set_reg_16(RegName16::BC, 1);
+        }
+        ADDR_RAM_CLEAR_128K if code_matches(emulator, pc, &RAM_CLEAR_128K_CODE) => {
+            let regs = &emulator.cpu.regs;
+            let mut src = regs.get_reg_16(RegName16::HL);
+            let mut dest = regs.get_reg_16(RegName16::DE);
+            let length = regs.get_reg_16(RegName16::BC);
+            // Byte by byte copy, as LDIR does for the overlapping blocks
+            for _ in 0..length {
+                let byte = emulator.controller.read_internal(src);
+                emulator.controller.write_internal(dest, byte);
+                src = src.wrapping_add(1);
+                dest = dest.wrapping_add(1);
+            }
+            let regs = &mut emulator.cpu.regs;
+            regs.set_reg_16(RegName16::HL, src);
+            regs.set_reg_16(RegName16::DE, dest);
+            regs.set_reg_16(RegName16::BC, 0);
+            // LDIR resets H, P/V and N flags
+            let flags = regs.get_flags() & (FLAG_SIGN | FLAG_ZERO | FLAG_CARRY);
+            regs.set_flags(flags);
+            regs.set_pc(pc + LDIR_LENGTH);
+        }
+        _ => {}
+    }
+}
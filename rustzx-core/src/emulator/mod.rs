@@ -1,38 +1,57 @@
 //! Platform-independent high-level Emulator interaction module
+mod fastboot;
 mod fastload;
 pub mod poke;
+mod registers;
 mod screenshot;
 mod snapshot;
+#[cfg(feature = "serde")]
+mod state;
 
 use crate::{
-    error::RomLoadError,
+    error::{EmulationError, RomLoadError},
     host::{
         DataRecorder, Host, LoadableAsset, RomFormat, RomSet, Screen, ScreenAsset, Snapshot,
-        SnapshotAsset, SnapshotRecorder, Stopwatch, Tape,
+        SnapshotAsset, SnapshotRecorder, Stopwatch, Tape, TraceEntry, TraceSink,
     },
     settings::RustzxSettings,
-    utils::EmulationMode,
+    utils::{
+        rng::{Rng, DEFAULT_SEED},
+        EmulationMode,
+    },
     zx::{
         controller::ZXController,
         events::EmulationEvents,
+        input::InputEvent,
         joy::{
+            fuller::FullerKey,
             kempston::KempstonKey,
             sinclair::{SinclairJoyNum, SinclairKey},
+            JoyState, JoystickKind,
         },
-        keys::{CompoundKey, ZXKey},
+        keys::{CompoundKey, KeyMap, ZXKey},
+        machine::ZXMachine,
         mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
-        tape::{Tap, TapeImpl},
-        video::colors::ZXColor,
+        tape::{Csw, LoopbackTape, Pzx, Tap, TapeImpl, ZXTape},
+        typing::{basic, KeyStroke},
+        video::{colors::ZXColor, dirty::DirtyMap},
     },
     Result,
 };
+use alloc::vec::Vec;
 use core::time::Duration;
 use rustzx_z80::Z80;
 
-#[cfg(feature = "sound")]
-use crate::zx::sound::sample::SoundSample;
 #[cfg(feature = "autoload")]
-use crate::{host::BufferCursor, zx::machine::ZXMachine};
+use crate::host::BufferCursor;
+#[cfg(feature = "sound")]
+use crate::zx::sound::sample::{ChannelSamples, SoundSample};
+pub use crate::zx::tape::{TapeTimings, TapeTimingsBuilder, TransportStatus};
+#[cfg(feature = "precise-border")]
+use crate::zx::video::BorderSize;
+pub use registers::Z80Registers;
+#[cfg(feature = "serde")]
+pub use state::MachineState;
 
 /// Represents emulator stop reason
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -41,7 +60,7 @@ pub enum EmulationStopReason {
     Completed,
     /// Emulation time limit has been reached
     Timeout,
-    /// Emulator has reached breakpoint address
+    /// Emulator has reached breakpoint address or accessed port matching I/O breakpoint
     Breakpoint,
 }
 
@@ -53,13 +72,39 @@ pub struct EmulationInfo {
     pub stop_reason: EmulationStopReason,
 }
 
+/// Represents result of the single frame emulation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameResult {
+    /// T-states executed during the frame, including the overshoot of the last instruction
+    pub tstates_run: usize,
+    /// T-states by which the last instruction crossed the frame boundary. These T-states are
+    /// already counted in `tstates_run` and will be skipped in the next frame, so the total
+    /// T-states count does not drift from the ideal one
+    pub overshoot: usize,
+    /// Set if CPU has accepted maskable interrupt during the frame
+    pub interrupt_taken: bool,
+    /// Address of the instruction which accessed port matching the I/O breakpoint. Frame is
+    /// stopped right after this instruction, the next `emulate_frame` call finishes it
+    pub io_breakpoint_pc: Option<u16>,
+}
+
 /// Represents main Emulator structure
 pub struct Emulator<H: Host> {
     settings: RustzxSettings,
     cpu: Z80,
     controller: ZXController<H>,
     mode: EmulationMode,
+    frame_budget: f32,
+    // T-states which should be emulated by `emulate_for`, may be negative after the
+    // last instruction overshoot
+    tstates_budget: f64,
     fast_load: bool,
+    tape_auto_control: bool,
+    tape_pause_scale: f32,
+    tape_instant_mode: bool,
+    tape_join_pauses: bool,
+    tape_timings: TapeTimings,
+    rng: Rng,
     #[cfg(feature = "sound")]
     sound_enabled: bool,
 }
@@ -71,28 +116,102 @@ impl<H: Host> Emulator<H> {
     pub fn new(settings: RustzxSettings, context: H::Context) -> Result<Self> {
         let mode = settings.emulation_mode;
         let fast_load = settings.tape_fastload_enabled;
+        let tape_auto_control = settings.tape_auto_control_enabled;
         #[cfg(feature = "sound")]
         let sound_enabled = settings.sound_enabled;
 
         let cpu = Z80::default();
         let controller = ZXController::<H>::new(&settings, context);
 
-        let this = Self {
+        let mut this = Self {
             settings,
             cpu,
             controller,
             mode,
+            frame_budget: 0.0,
+            tstates_budget: 0.0,
             fast_load,
+            tape_auto_control,
+            tape_pause_scale: 1.0,
+            tape_instant_mode: false,
+            tape_join_pauses: false,
+            tape_timings: TapeTimings::default(),
+            rng: Rng::new(DEFAULT_SEED),
             #[cfg(feature = "sound")]
             sound_enabled,
         };
+        this.fill_power_on_ram();
 
         Ok(this)
     }
 
+    /// Constructs new emulator for the machine model detected from the `snapshot` and loads
+    /// it, so 128K snapshot is never loaded to 48K machine by mistake. Machine from `settings`
+    /// is replaced with the detected one, other settings are kept. Pentagon 128K is kept if
+    /// requested by `settings` for 128K snapshots, as snapshot formats do not distinguish it
+    pub fn from_snapshot(
+        mut settings: RustzxSettings,
+        context: H::Context,
+        snapshot: Snapshot<impl SnapshotAsset>,
+    ) -> Result<Self> {
+        let snapshot = match snapshot {
+            Snapshot::Sna(mut asset) => {
+                let machine = snapshot::sna::detect_machine(&mut asset)?;
+                settings.machine = match (machine, settings.machine) {
+                    (ZXMachine::Sinclair128K, ZXMachine::Pentagon128) => ZXMachine::Pentagon128,
+                    (machine, _) => machine,
+                };
+                Snapshot::Sna(asset)
+            }
+        };
+        let mut this = Self::new(settings, context)?;
+        this.load_snapshot(snapshot)?;
+        Ok(this)
+    }
+
+    /// Returns emulated machine model
+    pub fn machine(&self) -> ZXMachine {
+        self.settings.machine
+    }
+
+    /// Returns current beam position (`line`, `pixel`), see [ZXMachine::raster_position]
+    pub fn raster_position(&self) -> (u16, u16) {
+        self.settings
+            .machine
+            .raster_position(self.controller.raster_clocks())
+    }
+
+    /// Resets the machine as the reset button does. CPU and memory paging are reset, while RAM
+    /// contents are kept. Border turns black until ROM sets its own color. Tape is stopped and
+    /// rewound unless [RustzxSettings::reset_rewinds_tape] is disabled
+    pub fn reset(&mut self) -> Result<()> {
+        self.cpu = Z80::default();
+        self.controller.reset();
+        if self.settings.reset_rewinds_tape {
+            self.controller.tape.stop();
+            self.controller.tape.rewind()?;
+        }
+        Ok(())
+    }
+
+    /// Re-seeds emulator random number generator, which is used for all nondeterministic
+    /// parts of the emulation. RAM is reset to the power-on pattern generated from the new
+    /// seed, therefore seed should be set before loading snapshots or tapes
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.fill_power_on_ram();
+    }
+
+    fn fill_power_on_ram(&mut self) {
+        self.rng.fill_bytes(self.controller.memory.ram_data_mut());
+        self.controller.refresh_memory_dependent_devices();
+    }
+
     /// changes emulation speed
     pub fn set_speed(&mut self, new_speed: EmulationMode) {
         self.mode = new_speed;
+        self.frame_budget = 0.0;
+        self.tstates_budget = 0.0;
     }
 
     /// changes fast loading flag
@@ -100,6 +219,54 @@ impl<H: Host> Emulator<H> {
         self.fast_load = value;
     }
 
+    /// changes tape auto control flag. When enabled, tape starts playing when ROM enters
+    /// tape loading routine and stops when the routine returns. Tape is not started
+    /// automatically while fast loading is enabled, as fast loading requires stopped tape
+    pub fn set_tape_auto_control(&mut self, value: bool) {
+        self.tape_auto_control = value;
+    }
+
+    /// changes multiplier of the pauses between tape blocks, e.g. `0.0` removes pauses
+    /// completely. Applies to the current and all subsequently loaded tapes
+    pub fn set_tape_pause_scale(&mut self, scale: f32) {
+        self.tape_pause_scale = scale;
+        self.controller.tape.set_pause_scale(scale);
+    }
+
+    /// changes tape playback speed multiplier, which is independent of the emulation speed.
+    /// Pilot, sync, data and pause pulses are shortened proportionally while CPU runs at the
+    /// normal speed, e.g. `2.0` plays tape twice faster. Loaders with strict timings may fail
+    /// to load the sped up tape
+    pub fn set_tape_speed(&mut self, speed: f32) {
+        self.controller.set_tape_speed(speed);
+    }
+
+    /// changes instant tape mode flag. When enabled, subsequently loaded tapes are decoded to
+    /// the pulses list at load time and played from memory, trading memory for independence
+    /// from the asset reads. Fast loading is not available for such tapes
+    pub fn set_tape_instant_mode(&mut self, value: bool) {
+        self.tape_instant_mode = value;
+    }
+
+    /// changes joining of the adjacent tape pauses. When enabled, consecutive pause blocks of
+    /// subsequently loaded pzx tapes are played as a single pause, which removes redundant
+    /// gaps of poorly authored tapes. Disabled by default to keep exact tape timings
+    pub fn set_tape_join_pauses(&mut self, value: bool) {
+        self.tape_join_pauses = value;
+    }
+
+    /// Overrides pulse timings of the tap tapes, see [TapeTimings]. This is an advanced escape
+    /// hatch for copy-protected tapes, which could be loaded only with tweaked timings.
+    /// Applies to the current and all subsequently loaded tap tapes. Tap tape which is already
+    /// decoded in the instant mode (see [Emulator::set_tape_instant_mode]) keeps the timings
+    /// it was decoded with, it should be loaded again to apply the new ones
+    pub fn override_tape_timings(&mut self, timings: TapeTimings) {
+        self.tape_timings = timings;
+        if let ZXTape::Tap(tap) = &mut self.controller.tape {
+            tap.override_timings(timings);
+        }
+    }
+
     /// changes sound playback flag
     #[cfg(feature = "sound")]
     pub fn set_sound(&mut self, value: bool) {
@@ -110,10 +277,10 @@ impl<H: Host> Emulator<H> {
     #[cfg(feature = "sound")]
     pub fn have_sound(&self) -> bool {
         // enable sound only if speed is normal
-        if let EmulationMode::FrameCount(1) = self.mode {
-            self.sound_enabled
-        } else {
-            false
+        match self.mode {
+            EmulationMode::FrameCount(1) => self.sound_enabled,
+            EmulationMode::Multiplier(1.0) => self.sound_enabled,
+            _ => false,
         }
     }
 
@@ -132,18 +299,18 @@ impl<H: Host> Emulator<H> {
         }
     }
 
+    /// Loads tape and, if enabled in settings, resets machine to the state which runs the
+    /// tape loader
     pub fn load_tape(&mut self, tape: Tape<H::TapeAsset>) -> Result<()> {
-        match tape {
-            Tape::Tap(asset) => {
-                self.controller.tape = Tap::from_asset(asset)?.into();
-            }
-        }
+        self.insert_tape(tape)?;
 
         #[cfg(feature = "autoload")]
         if self.settings.autoload_enabled {
             let snapshot = match self.settings.machine {
                 ZXMachine::Sinclair48K => &snapshot::autoload::tape::SNAPSHOT_SNA_48K,
-                ZXMachine::Sinclair128K => &snapshot::autoload::tape::SNAPSHOT_SNA_128K,
+                ZXMachine::Sinclair128K | ZXMachine::Pentagon128 => {
+                    &snapshot::autoload::tape::SNAPSHOT_SNA_128K
+                }
             };
 
             self.load_snapshot(Snapshot::Sna(BufferCursor::new(snapshot)))?;
@@ -152,6 +319,39 @@ impl<H: Host> Emulator<H> {
         Ok(())
     }
 
+    /// Replaces current tape with the new one while the machine is running, e.g. to swap
+    /// tapes of the multi-tape game. New tape is stopped at its beginning, CPU and memory
+    /// state is not affected
+    pub fn insert_tape(&mut self, tape: Tape<H::TapeAsset>) -> Result<()> {
+        match tape {
+            Tape::Tap(asset) => {
+                let mut tap = Tap::from_asset(asset, self.settings.machine)?;
+                tap.set_pause_scale(self.tape_pause_scale);
+                tap.override_timings(self.tape_timings);
+                self.controller.tape = if self.tape_instant_mode {
+                    tap.decode()?.into()
+                } else {
+                    tap.into()
+                };
+            }
+            Tape::Pzx(asset) => {
+                // Pzx tape is always played from memory, so instant mode is not applicable
+                let mut pzx = Pzx::from_asset(asset, self.settings.machine)?;
+                pzx.set_pause_scale(self.tape_pause_scale);
+                if self.tape_join_pauses {
+                    pzx.join_pauses();
+                }
+                self.controller.tape = pzx.into();
+            }
+            Tape::Csw(asset) => {
+                // Csw tape is always played from memory and has no separate pauses
+                self.controller.tape = Csw::from_asset(asset, self.settings.machine)?.into();
+            }
+        }
+
+        Ok(())
+    }
+
     fn load_rom_binary_16k_pages(&mut self, mut rom: impl RomSet) -> Result<()> {
         let page_count = self.settings.machine.specs().rom_pages;
 
@@ -178,6 +378,35 @@ impl<H: Host> Emulator<H> {
         Ok(())
     }
 
+    /// Captures full machine state, see [MachineState]
+    #[cfg(feature = "serde")]
+    pub fn machine_state(&self) -> MachineState {
+        state::save(self)
+    }
+
+    /// Restores machine state previously captured via [Emulator::machine_state]. Tape position
+    /// is restored only if the same tape is inserted
+    #[cfg(feature = "serde")]
+    pub fn load_machine_state(&mut self, state: MachineState) -> Result<()> {
+        state::load(self, state)
+    }
+
+    /// Returns currently displayed screen memory (bitmap and attributes) in `*.scr` layout
+    pub fn screenshot_scr(&self) -> [u8; 6912] {
+        screenshot::scr::capture(self)
+    }
+
+    /// Renders currently displayed screen to RGBA pixels using the given `palette` (8 normal
+    /// colors followed by 8 bright colors). Returns `(width, height, pixels)`; when
+    /// `include_border` is set, the canvas is surrounded with the current border color.
+    pub fn screenshot_rgba(
+        &self,
+        palette: &[[u8; 4]; 16],
+        include_border: bool,
+    ) -> (u32, u32, Vec<u8>) {
+        screenshot::rgba::capture(self, palette, include_border)
+    }
+
     pub fn play_tape(&mut self) {
         self.controller.tape.play();
     }
@@ -186,21 +415,81 @@ impl<H: Host> Emulator<H> {
         self.controller.tape.stop();
     }
 
+    /// Returns tape transport state summary, e.g. for the frontend HUD
+    pub fn transport_status(&self) -> TransportStatus {
+        self.controller.tape.transport_status()
+    }
+
+    /// Inserts empty loopback tape, which records MIC output (e.g. produced by SAVE) while
+    /// stopped and plays it back to EAR input when playback is started
+    pub fn insert_loopback_tape(&mut self) {
+        self.controller.tape = LoopbackTape::default().into();
+    }
+
+    /// Removes inserted tape and releases its asset
+    pub fn eject_tape(&mut self) {
+        self.controller.tape.eject();
+    }
+
+    /// Returns true if tape is currently playing
+    pub fn is_tape_playing(&self) -> bool {
+        self.controller.tape.is_playing()
+    }
+
     /// Rewinds tape. May return error if underlying tape asset failed to
     /// perform seek operation to go back to the the beginning of the tape
     pub fn rewind_tape(&mut self) -> Result<()> {
         self.controller.tape.rewind()
     }
 
+    /// Returns count of tape level changes since the tape was rewound
+    pub fn tape_edge_count(&self) -> u64 {
+        self.controller.tape.edge_count()
+    }
+
+    /// Rewinds tape to the beginning of the current block
+    pub fn restart_tape_block(&mut self) -> Result<()> {
+        self.controller.tape.restart_block()
+    }
+
+    /// Moves tape to the beginning of the next block, stays at the last block if there are
+    /// no more blocks. Tape keeps playing if it was playing. Loopback tape can't be skipped
+    /// and returns `EmulationError::TapeNotSeekable`
+    pub fn skip_tape_forward(&mut self) -> Result<()> {
+        let block = self.controller.tape.transport_status().block;
+        self.controller.tape.seek_block(block + 1)
+    }
+
+    /// Moves tape to the beginning of the previous block, stays at the first block if there
+    /// are no previous blocks. Tape keeps playing if it was playing. Loopback tape can't be
+    /// skipped and returns `EmulationError::TapeNotSeekable`
+    pub fn skip_tape_back(&mut self) -> Result<()> {
+        let block = self.controller.tape.transport_status().block;
+        self.controller.tape.seek_block(block.saturating_sub(1))
+    }
+
     pub fn screen_buffer(&self) -> &H::FrameBuffer {
         self.controller.screen.frame_buffer()
     }
 
+    /// Returns canvas cells and border which were changed in the screen buffer since the
+    /// previous frame. Map is updated at the end of each rendered frame
+    pub fn dirty_regions(&self) -> &DirtyMap {
+        self.controller.screen.dirty_regions()
+    }
+
     #[cfg(feature = "precise-border")]
     pub fn border_buffer(&self) -> &H::FrameBuffer {
         self.controller.border.frame_buffer()
     }
 
+    /// Returns border size of the border frame buffer, which determines its dimensions and
+    /// canvas position within it
+    #[cfg(feature = "precise-border")]
+    pub fn border_size(&self) -> BorderSize {
+        self.controller.border.size()
+    }
+
     pub fn set_io_extender(&mut self, extender: H::IoExtender) {
         self.controller.io_extender = Some(extender);
     }
@@ -219,19 +508,177 @@ impl<H: Host> Emulator<H> {
         self.controller.debug_interface.as_mut()
     }
 
+    /// Sets [Host::TapeListener] which is notified about tape playback events
+    pub fn set_tape_listener(&mut self, tape_listener: H::TapeListener) {
+        self.controller.tape_listener = Some(tape_listener);
+    }
+
+    /// Returns current [Host::TapeListener] instance
+    pub fn tape_listener(&mut self) -> Option<&mut H::TapeListener> {
+        self.controller.tape_listener.as_mut()
+    }
+
+    /// Sets handler which receives trace of each executed instruction. Tracing slows down
+    /// emulation and should be used only for debugging
+    pub fn set_trace_sink(&mut self, trace_sink: H::TraceSink) {
+        self.controller.trace_sink = Some(trace_sink);
+    }
+
+    /// Returns current [Host::TraceSink] instance, if tracing is enabled
+    pub fn trace_sink(&mut self) -> Option<&mut H::TraceSink> {
+        self.controller.trace_sink.as_mut()
+    }
+
+    /// Stops instructions tracing, returning the current trace sink
+    pub fn take_trace_sink(&mut self) -> Option<H::TraceSink> {
+        self.controller.trace_sink.take()
+    }
+
+    /// Returns copy of the CPU registers
+    pub fn registers(&self) -> Z80Registers {
+        Z80Registers::capture(&self.cpu)
+    }
+
+    /// Overwrites CPU registers
+    pub fn set_registers(&mut self, registers: Z80Registers) {
+        registers.apply(&mut self.cpu);
+    }
+
+    /// Overrides interrupt mode and interrupt enable flip-flops. This is an escape hatch for
+    /// snapshots which restore inconsistent interrupt state. Interrupt mode values above 2
+    /// are treated as IM 2
+    pub fn force_interrupt_mode(&mut self, im: u8, iff1: bool, iff2: bool) {
+        self.cpu.set_im(im.min(2));
+        self.cpu.regs.set_iff1(iff1);
+        self.cpu.regs.set_iff2(iff2);
+    }
+
     /// Reads byte from memory
     pub fn peek(&self, addr: u16) -> u8 {
         self.controller.memory.read(addr)
     }
 
+    /// Reads byte at `offset` of the 16K RAM `bank` regardless of the memory paging. Banks
+    /// are numbered as in 128K machine, 48K machine has only banks 5, 2 and 0 mapped at
+    /// `0x4000`, `0x8000` and `0xC000`. Returns `0xFF` for the missing banks
+    pub fn read_bank(&self, bank: u8, offset: u16) -> u8 {
+        self.controller.read_bank(bank, offset)
+    }
+
+    /// Writes byte at `offset` of the 16K RAM `bank` regardless of the memory paging, see
+    /// `read_bank` for the banks numbering. Writes to the missing banks are ignored
+    pub fn write_bank(&mut self, bank: u8, offset: u16, value: u8) {
+        self.controller.write_bank(bank, offset, value);
+    }
+
     pub fn border_color(&self) -> ZXColor {
         self.controller.border_color
     }
 
+    /// Changes CPU clock frequency to `hz` for overclocking experiments. Frame rate, tape and
+    /// sound keep their real time speed, so the frame length in T-states is scaled accordingly.
+    /// Interrupt length and contended timings are kept in the machine clocks and are only
+    /// approximate on the changed frequency
+    pub fn set_cpu_hz(&mut self, hz: u32) {
+        self.controller.set_cpu_hz(hz as usize);
+    }
+
+    /// Returns current CPU clock frequency
+    pub fn cpu_hz(&self) -> u32 {
+        self.controller.cpu_hz() as u32
+    }
+
+    /// Returns frame length in T-states for the current CPU clock frequency
+    pub fn frame_tstates(&self) -> u32 {
+        self.controller.clocks_frame() as u32
+    }
+
+    /// Returns T-states left until the next frame interrupt
+    pub fn tstates_to_interrupt(&self) -> u32 {
+        self.controller.clocks_to_interrupt() as u32
+    }
+
+    /// Triggers non-maskable interrupt, as done by the NMI button of the Multiface-like
+    /// interfaces. CPU jumps to `0x0066` before the next instruction (waking up from HALT if
+    /// required) with IFF2 keeping the interrupt enable state
+    pub fn trigger_nmi(&mut self) {
+        self.controller.trigger_nmi();
+    }
+
     pub fn send_key(&mut self, key: ZXKey, pressed: bool) {
         self.controller.send_key(key, pressed);
     }
 
+    /// Sets matrix positions of the keys sent via `send_key` and `send_compound_key`, e.g. to
+    /// remap the keys for the non-QWERTY host layouts. Currently pressed keys are released
+    pub fn set_keymap(&mut self, map: KeyMap) {
+        self.controller.set_keymap(map);
+    }
+
+    /// Renders only one of each `n` frames to reduce host load, while CPU and sound are still
+    /// emulated for every frame. Skipped frames leave the frame buffers unchanged. Value of
+    /// `0` or `1` disables frame skipping
+    pub fn set_frame_skip(&mut self, n: u32) {
+        self.controller.set_frame_skip(n as usize);
+    }
+
+    /// Queues key strokes required to type `text`. Keys are pressed and released with the frame
+    /// cadence and do not affect keys pressed via `send_key`. Uppercase letters are typed with
+    /// Caps Shift and symbols with Symbol Shift, characters which can't be typed with a single
+    /// key stroke are ignored
+    pub fn type_text(&mut self, text: &str) {
+        for stroke in text.chars().filter_map(KeyStroke::from_char) {
+            self.controller.typer.push(stroke);
+        }
+    }
+
+    /// Queues key strokes required to enter BASIC `program` listing line by line. 48K machine
+    /// editor requires keywords to be entered with a single key stroke depending on the
+    /// cursor mode, so keywords are translated to the matching keys. 128K editor expects the
+    /// program to be typed letter by letter, therefore machine should be in 128 BASIC mode.
+    /// Nothing is typed if the listing contains characters which can't be typed
+    pub fn paste_basic(&mut self, program: &str) -> Result<()> {
+        let strokes = match self.settings.machine {
+            ZXMachine::Sinclair48K => basic::keyword_entry_strokes(program)?,
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128 => {
+                basic::letter_entry_strokes(program)?
+            }
+        };
+        for stroke in strokes {
+            self.controller.typer.push(stroke);
+        }
+        Ok(())
+    }
+
+    /// Returns true if all keys queued via `type_text` or `paste_basic` have been typed
+    pub fn is_typing_finished(&self) -> bool {
+        self.controller.typer.is_idle()
+    }
+
+    /// Schedules input `event` to be applied at the start of the `frame`, which allows to
+    /// replay recorded input independently of the host timings. Events scheduled for the
+    /// current or already passed frames are applied immediately
+    pub fn schedule_input(&mut self, frame: u64, event: InputEvent) {
+        self.controller.schedule_input(frame, event);
+    }
+
+    /// Stops emulation after each instruction, which reads or writes port matching
+    /// `port_value` in the bits set in `port_mask`, e.g. mask `0x00FF` with value `0x00FE`
+    /// matches ULA port accesses. `emulate_frame` reports address of such instruction and
+    /// `emulate_frames` stops with [EmulationStopReason::Breakpoint]
+    pub fn set_io_breakpoint(&mut self, port_mask: u16, port_value: u16) {
+        self.controller.set_io_breakpoint(port_mask, port_value);
+    }
+
+    pub fn clear_io_breakpoint(&mut self) {
+        self.controller.clear_io_breakpoint();
+    }
+
+    /// Returns count of frames emulated since the emulator creation
+    pub fn frame_number(&self) -> u64 {
+        self.controller.frame_number()
+    }
+
     pub fn send_compound_key(&mut self, key: CompoundKey, pressed: bool) {
         self.controller.send_compound_key(key, pressed);
     }
@@ -242,10 +689,28 @@ impl<H: Host> Emulator<H> {
         }
     }
 
+    pub fn send_fuller_key(&mut self, key: FullerKey, pressed: bool) {
+        if let Some(joy) = &mut self.controller.fuller {
+            joy.key(key, pressed);
+        }
+    }
+
     pub fn send_sinclair_key(&mut self, num: SinclairJoyNum, key: SinclairKey, pressed: bool) {
         self.controller.send_sinclair_key(num, key, pressed);
     }
 
+    /// Selects joystick interface which receives `joystick_input` state. Inputs of the
+    /// previously selected joystick are released. Kempston and Fuller interfaces are attached
+    /// if they were not enabled in the settings
+    pub fn set_joystick_kind(&mut self, kind: JoystickKind) {
+        self.controller.set_joystick_kind(kind);
+    }
+
+    /// Sends logical joystick state to the joystick selected via `set_joystick_kind`
+    pub fn joystick_input(&mut self, state: JoyState) {
+        self.controller.joystick_input(state);
+    }
+
     pub fn send_mouse_button(&mut self, button: KempstonMouseButton, pressed: bool) {
         self.controller.send_mouse_button(button, pressed);
     }
@@ -258,11 +723,30 @@ impl<H: Host> Emulator<H> {
         self.controller.send_mouse_pos_diff(x, y);
     }
 
+    /// Returns last written values of all registers of the AY `chip`, e.g. for the host-side
+    /// visualization. Chip `0` is the main AY chip, chip `1` is the second Turbosound chip.
+    /// `None` is returned if there is no such chip
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn ay_registers(&self, chip: usize) -> Option<[u8; 16]> {
+        self.controller
+            .mixer
+            .ay_chip(chip)
+            .map(|chip| chip.registers())
+    }
+
     #[cfg(feature = "sound")]
     pub fn next_audio_sample(&mut self) -> Option<SoundSample<f32>> {
         self.controller.mixer.pop()
     }
 
+    /// Returns next samples of the separate sound sources. Samples are produced in lockstep
+    /// with [Emulator::next_audio_sample], `None` is returned if
+    /// [RustzxSettings::sound_channels_enabled] is not set
+    #[cfg(feature = "sound")]
+    pub fn next_channel_samples(&mut self) -> Option<ChannelSamples> {
+        self.controller.mixer.pop_channels()
+    }
+
     fn process_fast_load_event(&mut self) -> Result<()> {
         if self.controller.tape.can_fast_load() && self.fast_load {
             fastload::tap::fast_load_tap(self)?;
@@ -273,43 +757,244 @@ impl<H: Host> Emulator<H> {
     /// Execute `poke::Poke` action on the emulator
     pub fn execute_poke(&mut self, poke: impl poke::Poke) {
         for action in poke.actions().iter().copied() {
-            match action {
-                poke::PokeAction::Mem { addr, value } => {
-                    self.controller.memory.force_write(addr, value);
+            self.execute_poke_action(action);
+        }
+    }
+
+    /// Writes `value` to `addr` of the currently mapped memory
+    pub fn apply_poke(&mut self, addr: u16, value: u8) {
+        self.execute_poke_action(poke::PokeAction::mem(addr, value));
+    }
+
+    /// Writes list of `(addr, value)` pairs to the currently mapped memory
+    pub fn apply_pokes(&mut self, pokes: &[(u16, u8)]) {
+        for (addr, value) in pokes.iter().copied() {
+            self.apply_poke(addr, value);
+        }
+    }
+
+    fn execute_poke_action(&mut self, action: poke::PokeAction) {
+        match action {
+            poke::PokeAction::Mem { addr, value } => {
+                self.controller.memory.force_write(addr, value);
+            }
+            poke::PokeAction::Bank { bank, addr, value } => match self.settings.machine {
+                ZXMachine::Sinclair128K | ZXMachine::Pentagon128 if addr >= 0xC000 => {
+                    self.controller.write_bank(bank, addr, value);
                 }
+                _ => self.controller.memory.force_write(addr, value),
+            },
+        }
+    }
+
+    /// Returns count of frames which should be emulated during the next `emulate_frames`
+    /// call or `None` if emulation speed is not limited
+    fn frames_to_emulate(&mut self) -> Option<usize> {
+        match self.mode {
+            EmulationMode::FrameCount(frames) => Some(frames),
+            EmulationMode::Multiplier(multiplier) => {
+                self.frame_budget += multiplier.max(0.0);
+                let frames = self.frame_budget as usize;
+                self.frame_budget -= frames as f32;
+                Some(frames)
             }
+            EmulationMode::Max => None,
+        }
+    }
+
+    /// Passes the instruction at the current PC to the trace sink
+    fn trace_instruction(&mut self) {
+        let pc = self.cpu.regs.get_pc();
+        let mut bytes = [0u8; 4];
+        for (offset, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.controller.memory.read(pc.wrapping_add(offset as u16));
+        }
+        let entry = TraceEntry {
+            pc,
+            bytes,
+            registers: Z80Registers::capture(&self.cpu),
+        };
+        if let Some(sink) = &mut self.controller.trace_sink {
+            sink.on_instruction(&entry);
         }
     }
 
+    /// Emulates single CPU instruction (or interrupt), processing fast load requests.
+    /// Returns events which happened during the step
+    fn emulate_step(&mut self) -> Result<EmulationEvents> {
+        if self.controller.trace_sink.is_some() {
+            self.trace_instruction();
+        }
+        // Emulation step. if instant event happened then accept in and execute
+        self.cpu.emulate(&mut self.controller);
+        if let Some(e) = self.controller.take_last_emulation_error() {
+            return Err(e);
+        }
+
+        let events = self.controller.take_events();
+        if events.contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED) {
+            self.process_fast_load_event()?;
+        }
+        if events.contains(EmulationEvents::FAST_BOOT_TRIGGER_DETECTED) {
+            fastboot::skip_ram_test(self);
+        }
+        if self.tape_auto_control {
+            if events.contains(EmulationEvents::TAPE_LOAD_ROUTINE_ENTERED) && !self.fast_load {
+                self.controller.tape.play();
+            }
+            if events.contains(EmulationEvents::TAPE_LOAD_ROUTINE_EXITED)
+                && self.controller.tape.is_playing()
+            {
+                self.controller.tape.stop();
+            }
+        }
+        Ok(events)
+    }
+
+    /// Emulates exactly one frame regardless of the emulation speed, returns precise T-states
+    /// accounting of the frame. PC breakpoints are ignored, use `emulate_frames` for
+    /// debugging. Emulation stops early if I/O breakpoint is triggered
+    pub fn emulate_frame(&mut self) -> Result<FrameResult> {
+        let start_clocks = self.controller.frame_clocks();
+        let mut interrupt_taken = false;
+        let mut io_breakpoint_pc = None;
+        self.controller.reset_frame_counter();
+        while self.controller.frames_count() == 0 {
+            let events = self.emulate_step()?;
+            interrupt_taken |= events.contains(EmulationEvents::INTERRUPT_ACCEPTED);
+            if events.contains(EmulationEvents::IO_BREAKPOINT) {
+                // Interrupt may be accepted before the instruction, so PC is captured by
+                // the controller at the port access
+                io_breakpoint_pc = Some(self.controller.io_breakpoint_pc());
+                break;
+            }
+        }
+        if self.controller.frames_count() == 0 {
+            return Ok(FrameResult {
+                tstates_run: self.controller.frame_clocks() - start_clocks,
+                overshoot: 0,
+                interrupt_taken,
+                io_breakpoint_pc,
+            });
+        }
+        let overshoot = self.controller.frame_clocks();
+        Ok(FrameResult {
+            tstates_run: self.controller.clocks_frame() - start_clocks + overshoot,
+            overshoot,
+            interrupt_taken,
+            io_breakpoint_pc,
+        })
+    }
+
+    /// Emulates exactly `n` frames regardless of the emulation speed and time limits. Intended
+    /// for headless batch runs, where the result should depend only on the frames count
+    pub fn run_frames(&mut self, n: u64) -> Result<()> {
+        for _ in 0..n {
+            self.emulate_frame()?;
+        }
+        Ok(())
+    }
+
+    /// Runs emulation until CPU executes HALT and returns consumed T-states. Returns 0 if CPU
+    /// is already halted, or an error if HALT was not reached within `max_tstates`.
+    /// Breakpoints are ignored
+    pub fn run_until_halt(&mut self, max_tstates: u64) -> Result<u64> {
+        let clocks_frame = self.controller.clocks_frame() as u64;
+        let start_clocks = self.controller.frame_clocks() as u64;
+        self.controller.reset_frame_counter();
+        loop {
+            let elapsed = self.controller.frames_count() as u64 * clocks_frame
+                + self.controller.frame_clocks() as u64
+                - start_clocks;
+            if self.cpu.is_halted() && elapsed <= max_tstates {
+                return Ok(elapsed);
+            }
+            if elapsed >= max_tstates {
+                return Err(EmulationError::HaltNotReached.into());
+            }
+            self.emulate_step()?;
+        }
+    }
+
+    /// Runs emulation for T-states which correspond to `wall_ms` milliseconds of the real time
+    /// at the current speed multiplier, so the host can pace emulation just by passing the
+    /// elapsed time. Fractional and overshoot T-states are carried to the next call, therefore
+    /// emulation speed does not drift. `FrameCount` and `Max` modes are treated as the normal
+    /// speed. Returns emulated duration, stops early on breakpoint
+    pub fn emulate_for(&mut self, wall_ms: f32) -> Result<EmulationInfo> {
+        let multiplier = match self.mode {
+            EmulationMode::Multiplier(multiplier) => multiplier.max(0.0) as f64,
+            EmulationMode::FrameCount(_) | EmulationMode::Max => 1.0,
+        };
+        let cpu_hz = self.controller.cpu_hz() as f64;
+        self.tstates_budget += wall_ms.max(0.0) as f64 / 1000.0 * cpu_hz * multiplier;
+
+        let clocks_frame = self.controller.clocks_frame() as u64;
+        let start_clocks = self.controller.frame_clocks() as u64;
+        self.controller.reset_frame_counter();
+        let elapsed = |controller: &ZXController<H>| {
+            controller.frames_count() as u64 * clocks_frame + controller.frame_clocks() as u64
+                - start_clocks
+        };
+        let mut stop_reason = EmulationStopReason::Completed;
+        while (elapsed(&self.controller) as f64) < self.tstates_budget {
+            let events = self.emulate_step()?;
+            if events.intersects(EmulationEvents::PC_BREAKPOINT | EmulationEvents::IO_BREAKPOINT) {
+                stop_reason = EmulationStopReason::Breakpoint;
+                break;
+            }
+        }
+
+        let elapsed = elapsed(&self.controller);
+        self.tstates_budget -= elapsed as f64;
+        Ok(EmulationInfo {
+            duration: Duration::from_secs_f64(elapsed as f64 / cpu_hz),
+            stop_reason,
+        })
+    }
+
+    /// Returns 64-bit FNV-1a hash of all RAM banks contents. Useful to compare emulator state
+    /// with the golden value in automated tests
+    pub fn ram_checksum(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xCBF29CE484222325;
+        const FNV_PRIME: u64 = 0x100000001B3;
+
+        self.controller
+            .memory
+            .ram_data()
+            .iter()
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
+
     /// Perform emulatio up to `emulation_limit` duration, returns actual elapsed duration
     pub fn emulate_frames(&mut self, emulation_limit: Duration) -> Result<EmulationInfo> {
         let stopwatch = H::EmulationStopwatch::new();
+        let frames_limit = self.frames_to_emulate();
+        if frames_limit == Some(0) {
+            return Ok(EmulationInfo {
+                duration: stopwatch.measure(),
+                stop_reason: EmulationStopReason::Completed,
+            });
+        }
         // frame loop
         loop {
             // reset controller internal frame counter
             self.controller.reset_frame_counter();
             'cpu: loop {
-                // Emulation step. if instant event happened then accept in and execute
-                self.cpu.emulate(&mut self.controller);
-                if let Some(e) = self.controller.take_last_emulation_error() {
-                    return Err(e);
-                }
-
-                let events = self.controller.take_events();
-                if !events.is_empty() {
-                    if events.contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED) {
-                        self.process_fast_load_event()?;
-                    }
-                    if events.contains(EmulationEvents::PC_BREAKPOINT) {
-                        return Ok(EmulationInfo {
-                            duration: stopwatch.measure(),
-                            stop_reason: EmulationStopReason::Breakpoint,
-                        });
-                    }
+                let events = self.emulate_step()?;
+                if events
+                    .intersects(EmulationEvents::PC_BREAKPOINT | EmulationEvents::IO_BREAKPOINT)
+                {
+                    return Ok(EmulationInfo {
+                        duration: stopwatch.measure(),
+                        stop_reason: EmulationStopReason::Breakpoint,
+                    });
                 }
 
-                match self.mode {
-                    EmulationMode::FrameCount(frames) => {
+                match frames_limit {
+                    Some(frames) => {
                         if self.controller.frames_count() >= frames {
                             return Ok(EmulationInfo {
                                 duration: stopwatch.measure(),
@@ -317,7 +1002,7 @@ impl<H: Host> Emulator<H> {
                             });
                         };
                     }
-                    EmulationMode::Max => {
+                    None => {
                         if self.controller.frames_count() != 0 {
                             break 'cpu;
                         }
@@ -1,9 +1,26 @@
 //! Pokes are used to modify internal emulator state such as memory, registers, etc.
 
+mod pok;
+
+pub use pok::{PokeEntry, PokeSet, PokeTrainer};
+
+use alloc::vec::Vec;
+
 /// Action to perform on emulator state
 #[derive(Clone, Copy)]
 pub enum PokeAction {
-    Mem { addr: u16, value: u8 },
+    Mem {
+        addr: u16,
+        value: u8,
+    },
+    /// Memory write to the given RAM bank. `bank` is only taken into account for addresses
+    /// in the paged `0xC000..=0xFFFF` window of the machines with memory paging, otherwise
+    /// action behaves as [PokeAction::Mem]
+    Bank {
+        bank: u8,
+        addr: u16,
+        value: u8,
+    },
 }
 
 impl PokeAction {
@@ -11,6 +28,11 @@ impl PokeAction {
     pub const fn mem(addr: u16, value: u8) -> Self {
         Self::Mem { addr, value }
     }
+
+    /// Creates new banked memory poke action
+    pub const fn bank(bank: u8, addr: u16, value: u8) -> Self {
+        Self::Bank { bank, addr, value }
+    }
 }
 
 pub trait Poke {
@@ -18,6 +40,12 @@ pub trait Poke {
     fn actions(&self) -> &[PokeAction];
 }
 
+impl Poke for Vec<PokeAction> {
+    fn actions(&self) -> &[PokeAction] {
+        self
+    }
+}
+
 /// Poke which disables message and enter key prompt in 48K ROM when scrolling screen in BASIC mode
 pub struct DisableScrollMessageRom48;
 impl Poke for DisableScrollMessageRom48 {
@@ -0,0 +1,237 @@
+//! `*.pok` cheat files support
+//!
+//! File consists of trainers, each trainer starts with `N<name>` line and is followed by
+//! poke lines `M <bank> <addr> <value> <original>`, where the last poke line of the trainer
+//! starts with `Z` instead of `M`. File is terminated by `Y` line.
+use crate::{
+    emulator::poke::PokeAction,
+    error::PokeLoadError,
+    host::{LoadableAsset, SeekFrom, SeekableAsset},
+    Result,
+};
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+// Bank field with this bit set means that the poke does not depend on the paged bank
+const POK_BANK_IGNORE_BIT: u16 = 0x08;
+// Value which should be requested from the user instead of the fixed one
+const POK_VALUE_ASK_USER: u16 = 256;
+
+/// Single poke from the `*.pok` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PokeEntry {
+    /// RAM bank to write to, `None` if poke targets currently mapped memory
+    pub bank: Option<u8>,
+    pub addr: u16,
+    /// Value to write, `None` if value should be requested from the user
+    pub value: Option<u8>,
+    /// Value at `addr` before the poke was applied
+    pub original: u8,
+}
+
+/// Named group of pokes (e.g. "Infinite lives")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PokeTrainer {
+    pub name: String,
+    pub entries: Vec<PokeEntry>,
+}
+
+impl PokeTrainer {
+    /// Returns true if any of the trainer pokes requires a value from the user
+    pub fn requires_user_value(&self) -> bool {
+        self.entries.iter().any(|entry| entry.value.is_none())
+    }
+
+    /// Returns actions to apply the trainer, `user_value` is used for pokes without fixed value
+    pub fn actions(&self, user_value: u8) -> Vec<PokeAction> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let value = entry.value.unwrap_or(user_value);
+                match entry.bank {
+                    Some(bank) => PokeAction::bank(bank, entry.addr, value),
+                    None => PokeAction::mem(entry.addr, value),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns actions which restore original memory values
+    pub fn revert_actions(&self) -> Vec<PokeAction> {
+        self.entries
+            .iter()
+            .map(|entry| match entry.bank {
+                Some(bank) => PokeAction::bank(bank, entry.addr, entry.original),
+                None => PokeAction::mem(entry.addr, entry.original),
+            })
+            .collect()
+    }
+}
+
+/// Set of trainers loaded from the `*.pok` file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PokeSet {
+    trainers: Vec<PokeTrainer>,
+}
+
+impl PokeSet {
+    /// Loads trainers from `*.pok` file asset
+    pub fn load_pok(mut asset: impl LoadableAsset + SeekableAsset) -> Result<Self> {
+        let file_size = asset.seek(SeekFrom::End(0))?;
+        asset.seek(SeekFrom::Start(0))?;
+        let mut data = vec![0u8; file_size];
+        asset.read_exact(&mut data)?;
+        Self::parse(&data)
+    }
+
+    /// Returns all trainers from the file
+    pub fn trainers(&self) -> &[PokeTrainer] {
+        &self.trainers
+    }
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let mut trainers = Vec::new();
+        let mut current: Option<PokeTrainer> = None;
+
+        for line in data.split(|b| *b == b'\n') {
+            let line = trim_line(line);
+            let (&kind, rest) = match line.split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+            match kind {
+                b'N' => {
+                    if current.is_some() {
+                        return Err(PokeLoadError::InvalidPokFile.into());
+                    }
+                    current = Some(PokeTrainer {
+                        name: String::from_utf8_lossy(rest).trim().to_string(),
+                        entries: Vec::new(),
+                    });
+                }
+                b'M' | b'Z' => {
+                    let trainer = current.as_mut().ok_or(PokeLoadError::InvalidPokFile)?;
+                    trainer.entries.push(parse_entry(rest)?);
+                    if kind == b'Z' {
+                        trainers.extend(current.take());
+                    }
+                }
+                b'Y' => break,
+                _ => return Err(PokeLoadError::InvalidPokFile.into()),
+            }
+        }
+
+        if current.is_some() {
+            return Err(PokeLoadError::InvalidPokFile.into());
+        }
+
+        Ok(Self { trainers })
+    }
+}
+
+fn trim_line(mut line: &[u8]) -> &[u8] {
+    while let Some((last, rest)) = line.split_last() {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        line = rest;
+    }
+    line
+}
+
+fn parse_entry(line: &[u8]) -> Result<PokeEntry> {
+    let mut fields = line
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            core::str::from_utf8(field)
+                .ok()
+                .and_then(|field| field.parse::<u16>().ok())
+                .ok_or(PokeLoadError::InvalidPokFile)
+        });
+    let mut next_field = || fields.next().unwrap_or(Err(PokeLoadError::InvalidPokFile));
+
+    let bank = next_field()?;
+    let addr = next_field()?;
+    let value = next_field()?;
+    let original = next_field()?;
+
+    let bank = if bank & POK_BANK_IGNORE_BIT != 0 {
+        None
+    } else {
+        Some((bank & 0x07) as u8)
+    };
+    let value = match value {
+        POK_VALUE_ASK_USER => None,
+        value if value <= 0xFF => Some(value as u8),
+        _ => return Err(PokeLoadError::InvalidPokFile.into()),
+    };
+    if original > 0xFF {
+        return Err(PokeLoadError::InvalidPokFile.into());
+    }
+
+    Ok(PokeEntry {
+        bank,
+        addr,
+        value,
+        original: original as u8,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POK: &[u8] = b"NInfinite lives\r\n\
+        M  8 35136   0  53\r\n\
+        Z  3 49160 201 200\r\n\
+        NStart level\r\n\
+        Z  8 35000 256   1\r\n\
+        Y\r\n";
+
+    #[test]
+    fn pok_file_is_parsed() {
+        let set = PokeSet::parse(POK).unwrap();
+        let trainers = set.trainers();
+        assert_eq!(trainers.len(), 2);
+
+        assert_eq!(trainers[0].name, "Infinite lives");
+        assert_eq!(
+            trainers[0].entries,
+            [
+                PokeEntry {
+                    bank: None,
+                    addr: 35136,
+                    value: Some(0),
+                    original: 53,
+                },
+                PokeEntry {
+                    bank: Some(3),
+                    addr: 49160,
+                    value: Some(201),
+                    original: 200,
+                },
+            ]
+        );
+        assert!(!trainers[0].requires_user_value());
+
+        assert_eq!(trainers[1].name, "Start level");
+        assert_eq!(trainers[1].entries[0].value, None);
+        assert!(trainers[1].requires_user_value());
+    }
+
+    #[test]
+    fn invalid_pok_file_is_rejected() {
+        // Poke without trainer name
+        assert!(PokeSet::parse(b"Z 8 35136 0 53\nY\n").is_err());
+        // Unterminated trainer
+        assert!(PokeSet::parse(b"NLives\nM 8 35136 0 53\nY\n").is_err());
+        // Value out of range
+        assert!(PokeSet::parse(b"NLives\nZ 8 35136 300 53\nY\n").is_err());
+        // Missing fields
+        assert!(PokeSet::parse(b"NLives\nZ 8 35136\nY\n").is_err());
+    }
+}
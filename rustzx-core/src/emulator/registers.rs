@@ -0,0 +1,85 @@
+//! Host-facing copy of the Z80 register file, e.g. for debuggers and monitors
+use rustzx_z80::{RegName16, Z80};
+
+/// Complete Z80 register file. Alternate register pairs are stored with `_alt` suffix
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Z80Registers {
+    pub af: u16,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+    pub af_alt: u16,
+    pub bc_alt: u16,
+    pub de_alt: u16,
+    pub hl_alt: u16,
+    pub ix: u16,
+    pub iy: u16,
+    pub sp: u16,
+    pub pc: u16,
+    pub i: u8,
+    pub r: u8,
+    /// Interrupt mode, 0..=2
+    pub im: u8,
+    pub iff1: bool,
+    pub iff2: bool,
+    /// CPU is halted, PC points to the HALT instruction
+    pub halted: bool,
+}
+
+impl Z80Registers {
+    pub(crate) fn capture(cpu: &Z80) -> Self {
+        let mut regs = cpu.regs.clone();
+        let af = regs.get_reg_16(RegName16::AF);
+        let bc = regs.get_reg_16(RegName16::BC);
+        let de = regs.get_reg_16(RegName16::DE);
+        let hl = regs.get_reg_16(RegName16::HL);
+        regs.swap_af_alt();
+        regs.exx();
+
+        Self {
+            af,
+            bc,
+            de,
+            hl,
+            af_alt: regs.get_reg_16(RegName16::AF),
+            bc_alt: regs.get_reg_16(RegName16::BC),
+            de_alt: regs.get_reg_16(RegName16::DE),
+            hl_alt: regs.get_reg_16(RegName16::HL),
+            ix: regs.get_reg_16(RegName16::IX),
+            iy: regs.get_reg_16(RegName16::IY),
+            sp: regs.get_reg_16(RegName16::SP),
+            pc: regs.get_reg_16(RegName16::PC),
+            i: regs.get_i(),
+            r: regs.get_r(),
+            im: cpu.get_im().into(),
+            iff1: regs.get_iff1(),
+            iff2: regs.get_iff2(),
+            halted: cpu.is_halted(),
+        }
+    }
+
+    /// Applies registers to the `cpu`. Interrupt mode values above 2 are treated as IM 2
+    pub(crate) fn apply(&self, cpu: &mut Z80) {
+        let regs = &mut cpu.regs;
+        regs.set_reg_16(RegName16::AF, self.af_alt);
+        regs.set_reg_16(RegName16::BC, self.bc_alt);
+        regs.set_reg_16(RegName16::DE, self.de_alt);
+        regs.set_reg_16(RegName16::HL, self.hl_alt);
+        regs.swap_af_alt();
+        regs.exx();
+        regs.set_reg_16(RegName16::AF, self.af);
+        regs.set_reg_16(RegName16::BC, self.bc);
+        regs.set_reg_16(RegName16::DE, self.de);
+        regs.set_reg_16(RegName16::HL, self.hl);
+        regs.set_reg_16(RegName16::IX, self.ix);
+        regs.set_reg_16(RegName16::IY, self.iy);
+        regs.set_reg_16(RegName16::SP, self.sp);
+        regs.set_reg_16(RegName16::PC, self.pc);
+        regs.set_i(self.i);
+        regs.set_r(self.r);
+        regs.set_iff1(self.iff1);
+        regs.set_iff2(self.iff2);
+        cpu.set_im(self.im.min(2));
+        cpu.set_halted(self.halted);
+    }
+}
@@ -1 +1,2 @@
+pub mod rgba;
 pub mod scr;
@@ -0,0 +1,68 @@
+use crate::{
+    emulator::{screenshot::scr, Emulator},
+    host::Host,
+    utils::screen::bitmap_line_addr,
+    zx::{
+        constants::{
+            ATTR_BASE_REL, ATTR_COLS, CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y,
+            SCREEN_HEIGHT, SCREEN_WIDTH,
+        },
+        video::colors::{ZXAttribute, ZXBrightness, ZXColor},
+    },
+};
+use alloc::{vec, vec::Vec};
+
+const RGBA_PIXEL_SIZE: usize = 4;
+
+fn palette_color(palette: &[[u8; 4]; 16], color: ZXColor, brightness: ZXBrightness) -> [u8; 4] {
+    palette[color as usize + brightness as usize * 8]
+}
+
+/// Renders currently displayed screen memory to the RGBA buffer using `palette` (8 normal
+/// colors followed by 8 bright colors). Returns width, height and the buffer itself. When
+/// `include_border` is set, the canvas is surrounded with the border of current border color.
+pub fn capture<H: Host>(
+    emulator: &Emulator<H>,
+    palette: &[[u8; 4]; 16],
+    include_border: bool,
+) -> (u32, u32, Vec<u8>) {
+    let (width, height, origin_x, origin_y) = if include_border {
+        (SCREEN_WIDTH, SCREEN_HEIGHT, CANVAS_X, CANVAS_Y)
+    } else {
+        (CANVAS_WIDTH, CANVAS_HEIGHT, 0, 0)
+    };
+
+    let border = palette_color(
+        palette,
+        emulator.controller.border_color,
+        ZXBrightness::Normal,
+    );
+    let mut buffer = vec![0u8; width * height * RGBA_PIXEL_SIZE];
+    buffer
+        .chunks_exact_mut(RGBA_PIXEL_SIZE)
+        .for_each(|pixel| pixel.copy_from_slice(&border));
+
+    let scr = scr::capture(emulator);
+    let flash = emulator.controller.screen.flash();
+
+    for line in 0..CANVAS_HEIGHT {
+        let bitmap_base = (bitmap_line_addr(line) - 0x4000) as usize;
+        let attr_base = ATTR_BASE_REL as usize + (line / 8) * ATTR_COLS;
+        for col in 0..ATTR_COLS {
+            let bitmap = scr[bitmap_base + col];
+            let attr = ZXAttribute::from_byte(scr[attr_base + col]);
+            for pixel in 0..8 {
+                // from most significant bit
+                let state = ((bitmap << pixel) & 0x80) != 0;
+                let color =
+                    palette_color(palette, attr.active_color(state, flash), attr.brightness);
+                let x = origin_x + col * 8 + pixel;
+                let y = origin_y + line;
+                let offset = (y * width + x) * RGBA_PIXEL_SIZE;
+                buffer[offset..offset + RGBA_PIXEL_SIZE].copy_from_slice(&color);
+            }
+        }
+    }
+
+    (width as u32, height as u32, buffer)
+}
@@ -2,12 +2,11 @@ use crate::{
     emulator::Emulator,
     error::ScreenLoadError,
     host::{Host, LoadableAsset, SeekFrom, SeekableAsset},
-    zx::memory::Page,
     Result,
 };
 use rustzx_z80::CodeGenerator;
 
-const PRIMARY_SCREEN_MEMORY_SIZE: usize = 6912;
+pub(crate) const PRIMARY_SCREEN_MEMORY_SIZE: usize = 6912;
 
 /// Loads `*.scr` screenshot file
 pub fn load<H, A>(emulator: &mut Emulator<H>, mut asset: A) -> Result<()>
@@ -15,7 +14,6 @@ where
     H: Host,
     A: LoadableAsset + SeekableAsset,
 {
-    const SCREEN_ADDR: u16 = 0x4000;
     const LOOP_ADDR: u16 = 0x8000;
 
     let file_size = asset.seek(SeekFrom::End(0))?;
@@ -26,13 +24,8 @@ where
 
     asset.seek(SeekFrom::Start(0))?;
 
-    let bank = match emulator.controller.memory.get_page(SCREEN_ADDR) {
-        Page::Ram(page) => page,
-        Page::Rom(_) => {
-            // Machine with such memory map is not implemented yet
-            return Err(ScreenLoadError::MachineNotSupported.into());
-        }
-    };
+    // Load to the currently displayed bank (bank 7 when 128K shadow screen is active)
+    let bank = emulator.controller.screen_bank();
 
     // Generate infinite loop and jump to it
     CodeGenerator::new(&mut emulator.controller)
@@ -49,3 +42,13 @@ where
 
     Ok(())
 }
+
+/// Captures currently displayed screen memory in `*.scr` layout
+pub fn capture<H: Host>(emulator: &Emulator<H>) -> [u8; PRIMARY_SCREEN_MEMORY_SIZE] {
+    let bank = emulator.controller.screen_bank();
+    let memory = emulator.controller.memory.ram_page_data(bank);
+
+    let mut scr = [0u8; PRIMARY_SCREEN_MEMORY_SIZE];
+    scr.copy_from_slice(&memory[..PRIMARY_SCREEN_MEMORY_SIZE]);
+    scr
+}
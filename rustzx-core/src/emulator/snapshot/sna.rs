@@ -20,6 +20,21 @@ const SNA_128K_TAIL_BANKS: &[u8] = &[0, 1, 3, 4, 6, 7];
 const SNA_PAGINATED_PAGED_BANK_ADDRESS: u16 = 0xFFFF;
 const SNA_48K_RAM_PAGES_COUNT: u8 = 3;
 
+/// Returns machine model of the SNA snapshot, leaving asset at the start. Pentagon 128K
+/// snapshots are indistinguishable from Sinclair 128K ones, so the latter is reported
+pub fn detect_machine<A>(asset: &mut A) -> Result<ZXMachine>
+where
+    A: LoadableAsset + SeekableAsset,
+{
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    match size {
+        SNA_48K_SIZE => Ok(ZXMachine::Sinclair48K),
+        size if size > SNA_48K_SIZE => Ok(ZXMachine::Sinclair128K),
+        _ => Err(IoError::UnexpectedEof.into()),
+    }
+}
+
 /// SNA snapshot loading function
 pub fn load<H, A>(emulator: &mut Emulator<H>, mut asset: A) -> Result<()>
 where
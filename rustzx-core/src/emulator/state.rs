@@ -0,0 +1,83 @@
+//! Format-agnostic machine state which could be serialized with any `serde` format
+use crate::{
+    emulator::Emulator,
+    error::MachineStateLoadError,
+    host::Host,
+    zx::{controller::UlaState, machine::ZXMachine, memory::ZXMemory, tape::TapPosition},
+    Result,
+};
+use rustzx_z80::Z80;
+use serde::{Deserialize, Serialize};
+
+/// AY chip registers state
+#[derive(Serialize, Deserialize)]
+struct AyState {
+    registers: [u8; 16],
+    selected_reg: u8,
+}
+
+/// Full emulated machine state: CPU, memory, ULA, AY and tape position. Unlike snapshot
+/// formats, state is tied to the emulator version and is not intended for the long-term storage
+#[derive(Serialize, Deserialize)]
+pub struct MachineState {
+    machine: ZXMachine,
+    cpu: Z80,
+    memory: ZXMemory,
+    ula: UlaState,
+    ay: Option<AyState>,
+    tape: Option<TapPosition>,
+}
+
+impl MachineState {
+    /// Returns machine type of the saved state
+    pub fn machine(&self) -> ZXMachine {
+        self.machine
+    }
+}
+
+pub fn save<H: Host>(emulator: &Emulator<H>) -> MachineState {
+    let controller = &emulator.controller;
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    let ay = Some(AyState {
+        registers: controller.mixer.ay.registers(),
+        selected_reg: controller.mixer.ay.selected_reg(),
+    });
+    #[cfg(not(all(feature = "sound", feature = "ay")))]
+    let ay = None;
+
+    MachineState {
+        machine: controller.machine,
+        cpu: emulator.cpu.clone(),
+        memory: controller.memory.clone(),
+        ula: controller.ula_state(),
+        ay,
+        tape: controller.tape.position(),
+    }
+}
+
+pub fn load<H: Host>(emulator: &mut Emulator<H>, state: MachineState) -> Result<()> {
+    if state.machine != emulator.controller.machine {
+        return Err(MachineStateLoadError::MachineMismatch.into());
+    }
+
+    let controller = &mut emulator.controller;
+    emulator.cpu = state.cpu;
+    controller.memory = state.memory;
+    controller.set_ula_state(state.ula);
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    if let Some(ay) = state.ay {
+        for (reg, value) in ay.registers.iter().copied().enumerate() {
+            controller.mixer.ay.select_reg(reg as u8);
+            controller.mixer.ay.write(value);
+        }
+        controller.mixer.ay.select_reg(ay.selected_reg);
+    }
+
+    if let Some(position) = state.tape {
+        controller.tape.set_position(position)?;
+    }
+
+    Ok(())
+}
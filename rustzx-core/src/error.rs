@@ -1,3 +1,6 @@
+// Derived implementations still have to match the deprecated variants
+#![allow(deprecated)]
+
 use displaydoc::Display;
 use from_variants::FromVariants;
 
@@ -11,6 +14,16 @@ pub enum Error {
     TapeLoad(TapeLoadError),
     /// Failed to load screen
     ScreenLoad(ScreenLoadError),
+    /// Failed to load pokes
+    PokeLoad(PokeLoadError),
+    /// Failed to load machine state
+    MachineStateLoad(MachineStateLoadError),
+    /// Emulation failed
+    Emulation(EmulationError),
+    /// Failed to type text
+    Typing(TypingError),
+    /// Failed to remap key
+    KeyMap(KeyMapError),
 }
 
 #[derive(Debug, Display)]
@@ -34,7 +47,33 @@ pub enum RomLoadError {
 #[derive(Debug, Display)]
 pub enum TapeLoadError {
     /// Provided tap file is invalid
+    #[deprecated(
+        note = "tap loading reports specific `TruncatedBlock`, `TruncatedBlockHeader` \
+                         and `EmptyBlock` errors instead"
+    )]
     InvalidTapFile,
+    /// Tap block at offset {offset} ends before its declared size
+    TruncatedBlock { offset: usize },
+    /// Tap file ends in the middle of the block size header at offset {offset}
+    TruncatedBlockHeader { offset: usize },
+    /// Tap block at offset {offset} has zero size
+    EmptyBlock { offset: usize },
+    /// Provided pzx file has invalid header
+    InvalidPzxHeader,
+    /// Pzx file version {major} is not supported
+    UnsupportedPzxVersion { major: u8 },
+    /// Pzx chunk at offset {offset} ends before its declared size
+    TruncatedPzxChunk { offset: usize },
+    /// Provided csw file has invalid header
+    InvalidCswHeader,
+    /// Csw file version {major} is not supported
+    UnsupportedCswVersion { major: u8 },
+    /// Csw pulse data is corrupted
+    InvalidCswData,
+    /// Csw compression type {compression} is not supported
+    UnsupportedCswCompression { compression: u8 },
+    /// Csw Z-RLE compression requires `csw-z-rle` feature
+    CswZRleDisabled,
 }
 
 #[derive(Debug, Display)]
@@ -42,5 +81,38 @@ pub enum ScreenLoadError {
     /// Provided scr file is invalid
     InvalidScrFile,
     /// Selected machine can't be used to load given screen file
+    #[deprecated(note = "screen is loaded to the displayed screen bank on all machines")]
     MachineNotSupported,
 }
+
+#[derive(Debug, Display)]
+pub enum PokeLoadError {
+    /// Provided pok file is invalid
+    InvalidPokFile,
+}
+
+#[derive(Debug, Display)]
+pub enum MachineStateLoadError {
+    /// Machine state was saved for the different machine type
+    MachineMismatch,
+}
+
+#[derive(Debug, Display)]
+pub enum EmulationError {
+    /// T-states budget was exhausted before CPU executed HALT
+    HaltNotReached,
+    /// Tape does not support seeking between blocks
+    TapeNotSeekable,
+}
+
+#[derive(Debug, Display)]
+pub enum TypingError {
+    /// Character {ch:?} on line {line} can't be typed with the ZX Spectrum keyboard
+    UnsupportedCharacter { line: usize, ch: char },
+}
+
+#[derive(Debug, Display)]
+pub enum KeyMapError {
+    /// Position (half-row {half_row}, bit {bit}) is out of the keyboard matrix
+    InvalidPosition { half_row: usize, bit: u8 },
+}
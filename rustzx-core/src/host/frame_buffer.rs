@@ -1,4 +1,10 @@
 use crate::zx::video::colors::{ZXBrightness, ZXColor};
+use alloc::{vec, vec::Vec};
+
+const PALETTE_SIZE: usize = 16;
+const MAX_PIXEL_SIZE: usize = 4;
+// Dimmed scanlines keep half of the color intensity
+const SCANLINE_DIM_SHIFT: u32 = 1;
 
 pub enum FrameBufferSource {
     Screen,
@@ -12,3 +18,225 @@ pub trait FrameBuffer {
     /// Set `color` with `brightness` for pixel on canvas at (`x`, `y`)
     fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness);
 }
+
+/// Pixel data layout of the [`PixelBuffer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 4 bytes per pixel in R, G, B, A order
+    Rgba8888,
+    /// 4 bytes per pixel in B, G, R, A order
+    Bgra8888,
+    /// 2 bytes per pixel, little-endian 16-bit value with 5, 6 and 5 bits for R, G and B
+    Rgb565,
+    /// 1 byte per pixel with index in the palette table (8 normal colors followed by 8 bright
+    /// colors)
+    Indexed,
+}
+
+impl PixelFormat {
+    /// Returns size of the single pixel in bytes
+    pub fn pixel_size(self) -> usize {
+        match self {
+            Self::Rgba8888 | Self::Bgra8888 => 4,
+            Self::Rgb565 => 2,
+            Self::Indexed => 1,
+        }
+    }
+
+    /// Encodes palette color with `index` and `rgba` value
+    fn encode(self, index: usize, rgba: [u8; 4]) -> [u8; MAX_PIXEL_SIZE] {
+        let [r, g, b, a] = rgba;
+        match self {
+            Self::Rgba8888 => rgba,
+            Self::Bgra8888 => [b, g, r, a],
+            Self::Rgb565 => {
+                let value = ((r as u16 & 0xF8) << 8) | ((g as u16 & 0xFC) << 3) | (b as u16 >> 3);
+                let [lo, hi] = value.to_le_bytes();
+                [lo, hi, 0, 0]
+            }
+            Self::Indexed => [index as u8, 0, 0, 0],
+        }
+    }
+}
+
+/// Context of the [`PixelBuffer`]
+#[derive(Clone)]
+pub struct PixelBufferContext {
+    pub format: PixelFormat,
+    /// RGBA palette, 8 normal colors followed by 8 bright colors
+    pub palette: [[u8; 4]; PALETTE_SIZE],
+    /// Doubles the vertical resolution and dims every odd line to mimic CRT scanlines
+    pub scanlines: bool,
+}
+
+/// Frame buffer which renders pixels directly in the requested [`PixelFormat`], so the host
+/// can pass its data to the output without a conversion pass
+pub struct PixelBuffer {
+    buffer: Vec<u8>,
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    palette: [[u8; 4]; PALETTE_SIZE],
+    dimmed_palette: [[u8; 4]; PALETTE_SIZE],
+    // Palette colors encoded in the target pixel format, followed by dimmed colors
+    encoded_palette: [[u8; MAX_PIXEL_SIZE]; PALETTE_SIZE * 2],
+    scanlines: bool,
+}
+
+impl FrameBuffer for PixelBuffer {
+    type Context = PixelBufferContext;
+
+    fn new(
+        width: usize,
+        height: usize,
+        _source: FrameBufferSource,
+        context: Self::Context,
+    ) -> Self {
+        let dimmed_palette = context.palette.map(|[r, g, b, a]| {
+            [
+                r >> SCANLINE_DIM_SHIFT,
+                g >> SCANLINE_DIM_SHIFT,
+                b >> SCANLINE_DIM_SHIFT,
+                a,
+            ]
+        });
+        let mut encoded_palette = [[0u8; MAX_PIXEL_SIZE]; PALETTE_SIZE * 2];
+        for (index, (encoded, rgba)) in encoded_palette
+            .iter_mut()
+            .zip(context.palette.iter().chain(dimmed_palette.iter()))
+            .enumerate()
+        {
+            *encoded = context.format.encode(index, *rgba);
+        }
+        let height = if context.scanlines {
+            height * 2
+        } else {
+            height
+        };
+
+        Self {
+            buffer: vec![0u8; width * height * context.format.pixel_size()],
+            width,
+            height,
+            format: context.format,
+            palette: context.palette,
+            dimmed_palette,
+            encoded_palette,
+            scanlines: context.scanlines,
+        }
+    }
+
+    fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness) {
+        let pixel_size = self.format.pixel_size();
+        let index = color as usize + brightness as usize * 8;
+        let y = if self.scanlines { y * 2 } else { y };
+        let offset = (y * self.width + x) * pixel_size;
+        self.buffer[offset..offset + pixel_size]
+            .copy_from_slice(&self.encoded_palette[index][..pixel_size]);
+        if self.scanlines {
+            let offset = offset + self.width * pixel_size;
+            self.buffer[offset..offset + pixel_size]
+                .copy_from_slice(&self.encoded_palette[PALETTE_SIZE + index][..pixel_size]);
+        }
+    }
+}
+
+impl PixelBuffer {
+    /// Returns rendered pixels, rows are stored without padding
+    pub fn data(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns height of the rendered pixels, which is doubled if scanlines are enabled
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Returns RGBA palette table, which maps pixels of [`PixelFormat::Indexed`] buffer to
+    /// actual colors
+    pub fn palette(&self) -> &[[u8; 4]; PALETTE_SIZE] {
+        &self.palette
+    }
+
+    /// Returns RGBA palette of the dimmed scanlines. Pixels of [`PixelFormat::Indexed`]
+    /// buffer use indices `16..32` for these colors
+    pub fn dimmed_palette(&self) -> &[[u8; 4]; PALETTE_SIZE] {
+        &self.dimmed_palette
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zx::video::colors::ZXAttribute;
+
+    const PALETTE: [[u8; 4]; PALETTE_SIZE] = {
+        let mut palette = [[0x00, 0x00, 0x00, 0xFF]; PALETTE_SIZE];
+        // Normal and bright blue
+        palette[1] = [0x00, 0x00, 0xCD, 0xFF];
+        palette[9] = [0x00, 0x00, 0xFF, 0xFF];
+        // Normal and bright red
+        palette[2] = [0xCD, 0x00, 0x00, 0xFF];
+        palette[10] = [0xFF, 0x00, 0x00, 0xFF];
+        palette
+    };
+
+    /// Renders ink and paper of the bright, red paper, blue ink attribute to the 2x1 buffer
+    fn render_attribute(format: PixelFormat, scanlines: bool) -> PixelBuffer {
+        let context = PixelBufferContext {
+            format,
+            palette: PALETTE,
+            scanlines,
+        };
+        let mut buffer = PixelBuffer::new(2, 1, FrameBufferSource::Screen, context);
+        let attr = ZXAttribute::from_byte(0x51);
+        buffer.set_color(0, 0, attr.active_color(true, false), attr.brightness);
+        buffer.set_color(1, 0, attr.active_color(false, false), attr.brightness);
+        buffer
+    }
+
+    #[test]
+    fn attribute_is_rendered_in_requested_format() {
+        assert_eq!(
+            render_attribute(PixelFormat::Rgba8888, false).data(),
+            [0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0xFF]
+        );
+        assert_eq!(
+            render_attribute(PixelFormat::Bgra8888, false).data(),
+            [0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0xFF]
+        );
+        assert_eq!(
+            render_attribute(PixelFormat::Rgb565, false).data(),
+            [0x1F, 0x00, 0x00, 0xF8]
+        );
+
+        let indexed = render_attribute(PixelFormat::Indexed, false);
+        assert_eq!(indexed.data(), [9, 10]);
+        assert_eq!(indexed.palette()[9], PALETTE[9]);
+    }
+
+    #[test]
+    fn scanlines_double_height_and_dim_odd_lines() {
+        let buffer = render_attribute(PixelFormat::Rgba8888, true);
+        assert_eq!((buffer.width(), buffer.height()), (2, 2));
+        assert_eq!(
+            buffer.data(),
+            [
+                0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0xFF, // Normal line
+                0x00, 0x00, 0x7F, 0xFF, 0x7F, 0x00, 0x00, 0xFF, // Dimmed line
+            ]
+        );
+
+        let indexed = render_attribute(PixelFormat::Indexed, true);
+        assert_eq!(indexed.data(), [9, 10, 25, 26]);
+        assert_eq!(indexed.dimmed_palette()[9], [0x00, 0x00, 0x7F, 0xFF]);
+    }
+}
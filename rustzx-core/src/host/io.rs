@@ -1,4 +1,5 @@
 use crate::error::IoError;
+use alloc::vec::Vec;
 use core::usize;
 
 type Result<T> = core::result::Result<T, IoError>;
@@ -49,7 +50,7 @@ impl<T: AsRef<[u8]>> LoadableAsset for BufferCursor<T> {
         let data = self.data.as_ref();
 
         if self.pos >= data.len() {
-            return Err(IoError::UnexpectedEof);
+            return Ok(0);
         }
         let bytes_to_read = buf.len().min(data.len() - self.pos);
         buf[0..bytes_to_read].copy_from_slice(&data[self.pos..self.pos + bytes_to_read]);
@@ -88,6 +89,23 @@ pub trait LoadableAsset {
 
         Ok(())
     }
+
+    /// Reads all remaining data from asset and appends it to `buf`. Returns count of read bytes
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        const READ_CHUNK_SIZE: usize = 4 * 1024;
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        let mut total = 0;
+        loop {
+            match self.read(&mut chunk)? {
+                0 => break,
+                n => {
+                    buf.extend_from_slice(&chunk[..n]);
+                    total += n;
+                }
+            }
+        }
+        Ok(total)
+    }
 }
 
 pub trait DataRecorder {
@@ -113,6 +131,7 @@ pub trait DataRecorder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
 
     #[test]
     fn buffer_cursor_seek_works() {
@@ -138,4 +157,35 @@ mod tests {
         assert_eq!(tmp[0], 9);
         assert_eq!(tmp[1], 10);
     }
+
+    #[test]
+    fn buffer_cursor_reports_eof() {
+        let mut cursor = BufferCursor::new(vec![1, 2, 3]);
+        assert_eq!(cursor.seek(SeekFrom::End(0)).unwrap(), 3);
+
+        // Partial read is completed with the remaining bytes only
+        cursor.seek(SeekFrom::Start(1)).unwrap();
+        let mut tmp = [0u8; 4];
+        assert_eq!(cursor.read(&mut tmp).unwrap(), 2);
+        assert_eq!(tmp[..2], [2, 3]);
+        assert_eq!(cursor.read(&mut tmp).unwrap(), 0);
+
+        // Seeking past the end is allowed, but nothing can be read there
+        assert_eq!(cursor.seek(SeekFrom::Current(5)).unwrap(), 8);
+        assert_eq!(cursor.read(&mut tmp).unwrap(), 0);
+        assert!(matches!(
+            cursor.read_exact(&mut tmp[..1]),
+            Err(IoError::UnexpectedEof)
+        ));
+
+        cursor.seek(SeekFrom::Start(1)).unwrap();
+        assert!(matches!(
+            cursor.read_exact(&mut tmp),
+            Err(IoError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            cursor.seek(SeekFrom::End(-4)),
+            Err(IoError::SeekBeforeStart)
+        ));
+    }
 }
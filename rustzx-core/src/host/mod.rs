@@ -2,9 +2,13 @@ mod frame_buffer;
 mod io;
 
 pub use core::time::Duration;
-pub use frame_buffer::{FrameBuffer, FrameBufferSource};
+pub use frame_buffer::{
+    FrameBuffer, FrameBufferSource, PixelBuffer, PixelBufferContext, PixelFormat,
+};
 pub use io::{BufferCursor, DataRecorder, LoadableAsset, SeekFrom, SeekableAsset};
 
+use crate::Z80Registers;
+
 pub trait Stopwatch {
     fn new() -> Self;
     fn measure(&self) -> Duration;
@@ -21,6 +25,8 @@ pub enum SnapshotRecorder<DataRecorderImpl: DataRecorder> {
 
 pub enum Tape<LoadableAssetImpl: LoadableAsset> {
     Tap(LoadableAssetImpl),
+    Pzx(LoadableAssetImpl),
+    Csw(LoadableAssetImpl),
     // TODO(#56): Implement TZX tape format support
 }
 
@@ -92,6 +98,58 @@ impl DebugInterface for StubDebugInterface {
     }
 }
 
+/// Tape diagnostic event, see [TapeListener::on_tape_event]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeEvent {
+    /// XOR of the block bytes does not match its last (checksum) byte. Playback continues as
+    /// on the real hardware, but the loader will most likely report an error. Reported only
+    /// for tap tapes
+    ChecksumMismatch { block: usize, expected: u8, got: u8 },
+}
+
+/// Receives notifications about tape playback
+pub trait TapeListener {
+    /// Called once when tape playback stops after reaching the end of the tape. Not called
+    /// when tape is stopped manually
+    fn on_tape_stopped(&mut self);
+
+    /// Called when tape diagnostic event is detected during playback
+    fn on_tape_event(&mut self, _event: TapeEvent) {}
+}
+
+/// Tape listener which does nothing
+pub struct StubTapeListener;
+
+impl TapeListener for StubTapeListener {
+    fn on_tape_stopped(&mut self) {}
+}
+
+/// Instruction which is about to be executed, passed to the [TraceSink]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    /// Memory contents at `pc`, enough to hold the longest instruction
+    pub bytes: [u8; 4],
+    /// CPU registers before the instruction execution. Register changes made by the
+    /// instruction can be found by comparing them with the next entry
+    pub registers: Z80Registers,
+}
+
+/// Receives trace of the executed instructions. When trace sink is not set, emulator does
+/// not collect any trace data
+pub trait TraceSink {
+    /// Called before each instruction is executed. Chained index prefixes (e.g. `DD DD`) are
+    /// reported as separate instructions
+    fn on_instruction(&mut self, entry: &TraceEntry);
+}
+
+/// Trace sink which does nothing
+pub struct StubTraceSink;
+
+impl TraceSink for StubTraceSink {
+    fn on_instruction(&mut self, _entry: &TraceEntry) {}
+}
+
 /// Represents set of required types for emulator implementation
 /// based on `rustzx-core`.
 pub trait Host {
@@ -108,4 +166,8 @@ pub trait Host {
     type IoExtender: IoExtender;
     /// Debug interface logic (e.g. breakpoints)
     type DebugInterface: DebugInterface;
+    /// Tape playback notifications handler
+    type TapeListener: TapeListener;
+    /// Executed instructions trace handler
+    type TraceSink: TraceSink;
 }
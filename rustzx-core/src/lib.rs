@@ -1,5 +1,8 @@
 #![no_std]
 
+#[macro_use]
+mod logging;
+
 pub(crate) mod emulator;
 pub(crate) mod settings;
 pub(crate) mod utils;
@@ -8,9 +11,15 @@ pub mod error;
 pub mod host;
 pub mod zx;
 
-pub use emulator::{poke, EmulationInfo, EmulationStopReason, Emulator};
+#[cfg(feature = "serde")]
+pub use emulator::MachineState;
+pub use emulator::{
+    poke, EmulationInfo, EmulationStopReason, Emulator, FrameResult, TapeTimings,
+    TapeTimingsBuilder, TransportStatus, Z80Registers,
+};
 pub use settings::RustzxSettings;
 pub use utils::EmulationMode;
+pub use zx::tape::csw;
 
 #[cfg(feature = "strum")]
 pub use strum::IntoEnumIterator as IterableEnum;
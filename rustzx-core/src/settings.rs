@@ -1,26 +1,73 @@
-use crate::{utils::EmulationMode, zx::machine::ZXMachine};
+use crate::{
+    utils::EmulationMode,
+    zx::{
+        keys::KeyboardIssue,
+        machine::{ZXMachine, ZXTimings},
+    },
+};
 
 #[cfg(all(feature = "sound", feature = "ay"))]
 use crate::zx::sound::ay::ZXAYMode;
+#[cfg(feature = "precise-border")]
+use crate::zx::video::BorderSize;
 
 pub struct RustzxSettings {
     pub machine: ZXMachine,
+    /// ULA timings revision of the machine, see [ZXTimings]
+    pub timings: ZXTimings,
+    /// Keyboard circuit revision, determines the EAR bit read from the port `0xFE` when tape
+    /// is not playing
+    pub issue: KeyboardIssue,
     pub emulation_mode: EmulationMode,
     pub tape_fastload_enabled: bool,
+    /// Automatically starts tape when ROM enters tape loading routine and stops it when the
+    /// routine returns
+    pub tape_auto_control_enabled: bool,
+    /// Stops and rewinds tape on machine reset. When disabled, tape keeps its position and
+    /// playback state across resets
+    pub reset_rewinds_tape: bool,
     pub kempston_enabled: bool,
+    /// Enables Fuller Box: Fuller joystick on port `0x7F` and AY chip on ports `0x3F`/`0x5F`
+    /// instead of the 128K AY ports
+    pub fuller_enabled: bool,
     pub mouse_enabled: bool,
+    /// Emulates ULA "snow" effect, which appears when the I register points to the contended
+    /// memory
+    pub ula_snow_enabled: bool,
+    /// Skips startup RAM test of the original ROMs. ROM code is not modified, its RAM test
+    /// loops are executed instantly instead
+    pub fast_boot: bool,
+    /// Size of the border area of the border frame buffer
+    #[cfg(feature = "precise-border")]
+    pub border_size: BorderSize,
     #[cfg(all(feature = "sound", feature = "ay"))]
     pub ay_mode: ZXAYMode,
     #[cfg(all(feature = "sound", feature = "ay"))]
     pub ay_enabled: bool,
+    /// Adds second AY chip, selected by writing `0xFE` (second chip) or `0xFF` (first chip) to
+    /// the AY register select port
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub turbosound_enabled: bool,
     #[cfg(feature = "sound")]
     pub beeper_enabled: bool,
+    /// SpecDrum DAC port, `None` disables the device. See
+    /// [SPECDRUM_DEFAULT_PORT](crate::zx::sound::dac::SPECDRUM_DEFAULT_PORT)
+    #[cfg(feature = "sound")]
+    pub specdrum_port: Option<u8>,
+    /// Covox DAC port, `None` disables the device. See
+    /// [COVOX_DEFAULT_PORT](crate::zx::sound::dac::COVOX_DEFAULT_PORT)
+    #[cfg(feature = "sound")]
+    pub covox_port: Option<u8>,
     #[cfg(feature = "sound")]
     pub sound_enabled: bool,
     #[cfg(feature = "sound")]
     pub sound_volume: u8,
     #[cfg(feature = "sound")]
     pub sound_sample_rate: usize,
+    /// Fills separate buffers for each sound source in addition to the mixed output, see
+    /// [Emulator::next_channel_samples](crate::Emulator::next_channel_samples)
+    #[cfg(feature = "sound")]
+    pub sound_channels_enabled: bool,
     #[cfg(feature = "embedded-roms")]
     pub load_default_rom: bool,
     #[cfg(feature = "autoload")]
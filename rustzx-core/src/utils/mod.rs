@@ -1,9 +1,16 @@
 //! Some emulator-related utils
 
+pub mod rng;
 pub mod screen;
 
 #[derive(Copy, Clone)]
 pub enum EmulationMode {
+    /// Emulate given count of frames per `Emulator::emulate_frames` call
     FrameCount(usize),
+    /// Emulate frames with given speed multiplier relative to the normal speed. Fractional
+    /// frame counts are accumulated between `Emulator::emulate_frames` calls, e.g. with
+    /// `0.5` multiplier a frame is emulated only on every second call
+    Multiplier(f32),
+    /// Emulate as many frames as possible until the time limit is reached
     Max,
 }
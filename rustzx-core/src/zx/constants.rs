@@ -26,3 +26,15 @@ pub(crate) const BORDER_COLS: usize = 4;
 pub(crate) const BORDER_ROWS: usize = 3;
 /// Tape loading trap at LD-BREAK routine in ROM
 pub(crate) const ADDR_LD_BREAK: u16 = 0x056B;
+/// LD-BYTES ROM routine entry point
+pub(crate) const ADDR_LD_BYTES: u16 = 0x0556;
+/// SA/LD-RET ROM routine, common exit path of the tape load/save routines
+pub(crate) const ADDR_SA_LD_RET: u16 = 0x053F;
+/// RAM-CHECK routine of the 48K ROM, which fills and tests RAM on startup
+pub(crate) const ADDR_RAM_CHECK_48K: u16 = 0x11DA;
+/// RAM-DONE routine of the 48K ROM, RAM test exit path
+pub(crate) const ADDR_RAM_DONE_48K: u16 = 0x11EF;
+/// Startup delay loop of the 128K ROM 0
+pub(crate) const ADDR_STARTUP_DELAY_128K: u16 = 0x0004;
+/// LDIR of the 128K ROM 0 RAM test, which clears single RAM bank
+pub(crate) const ADDR_RAM_CLEAR_128K: u16 = 0x00E5;
@@ -1,21 +1,31 @@
 //! Contains ZX Spectrum System controller (like ula or so) of emulator
 use crate::{
     error::Error,
-    host::{DebugInterface, Host, HostContext, IoExtender},
+    host::{DebugInterface, Host, HostContext, IoExtender, TapeListener},
     settings::RustzxSettings,
     utils::screen::bitmap_line_addr,
     zx::{
-        constants::{ADDR_LD_BREAK, CANVAS_HEIGHT, CLOCKS_PER_COL},
+        constants::{
+            ADDR_LD_BREAK, ADDR_LD_BYTES, ADDR_RAM_CHECK_48K, ADDR_RAM_CLEAR_128K, ADDR_SA_LD_RET,
+            ADDR_STARTUP_DELAY_128K, CANVAS_HEIGHT, CLOCKS_PER_COL,
+        },
         events::EmulationEvents,
+        input::{InputEvent, InputQueue},
         joy::{
-            kempston::KempstonJoy,
+            cursor,
+            fuller::{
+                FullerJoy, FullerKey, FULLER_AY_DATA_PORT, FULLER_AY_REG_PORT, FULLER_JOY_PORT,
+            },
+            kempston::{KempstonJoy, KempstonKey},
             sinclair::{self, SinclairJoyNum, SinclairKey},
+            JoyState, JoystickKind,
         },
-        keys::{CompoundKey, ZXKey},
-        machine::ZXMachine,
+        keys::{CompoundKey, KeyMap, KeyboardIssue, ZXKey},
+        machine::{ZXMachine, ZXTimings},
         memory::{Page, RamType, RomType, ZXMemory, PAGE_SIZE},
         mouse::kempston::{KempstonMouse, KempstonMouseButton, KempstonMouseWheelDirection},
         tape::{TapeImpl, ZXTape},
+        typing::KeyboardTyper,
         video::{colors::ZXColor, screen::ZXScreen},
     },
 };
@@ -28,32 +38,91 @@ use crate::zx::sound::mixer::ZXMixer;
 #[cfg(feature = "precise-border")]
 use crate::zx::video::border::ZXBorder;
 
+/// Tape speed multiplier of the normal speed, in thousandths
+const TAPE_SPEED_NORMAL: u64 = 1000;
+/// Tape speed multiplier limit, which keeps tape clocks conversion within `u64`
+const MAX_TAPE_SPEED: f32 = 1000.0;
+
+/// ULA-related part of the machine state
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct UlaState {
+    border_color: ZXColor,
+    frame_clocks: usize,
+    paging_enabled: bool,
+    screen_bank: u8,
+    port_7ffd: u8,
+}
+
 /// ZX System controller
 pub(crate) struct ZXController<H: Host> {
     // parts of ZX Spectrum.
     pub machine: ZXMachine,
+    timings: ZXTimings,
+    issue: KeyboardIssue,
     pub memory: ZXMemory,
     pub screen: ZXScreen<H::FrameBuffer>,
     pub tape: ZXTape<H::TapeAsset>,
     #[cfg(feature = "precise-border")]
     pub border: ZXBorder<H::FrameBuffer>,
     pub kempston: Option<KempstonJoy>,
+    pub fuller: Option<FullerJoy>,
+    // Fuller Box AY ports are decoded instead of the 128K ones. Follows `fuller_enabled`
+    // setting, so Fuller joystick selected at runtime does not affect AY
+    fuller_ay: bool,
+    joystick_kind: JoystickKind,
     pub mouse: Option<KempstonMouse>,
+    snow_enabled: bool,
+    fast_boot_enabled: bool,
     pub io_extender: Option<H::IoExtender>,
     pub debug_interface: Option<H::DebugInterface>,
+    pub tape_listener: Option<H::TapeListener>,
+    pub trace_sink: Option<H::TraceSink>,
     #[cfg(feature = "sound")]
     pub mixer: ZXMixer,
+    #[cfg(feature = "sound")]
+    specdrum_port: Option<u8>,
+    #[cfg(feature = "sound")]
+    covox_port: Option<u8>,
     pub keyboard: [u8; 8],
+    // matrix positions of the keys sent via `send_key`
+    keymap: KeyMap,
     pub keyboard_extended: [u8; 8],
     pub keyboard_sinclair: [u8; 8],
+    // keyboard layer of the programmatically typed keys, updated on each frame
+    keyboard_typed: [u8; 8],
+    pub typer: KeyboardTyper,
+    input_queue: InputQueue,
     pub caps_shift_modifier_mask: u32,
     // current border color
     pub border_color: ZXColor,
+    // last value written to the ULA port
+    ula_output: u8,
     // clocls count from frame start
     frame_clocks: usize,
+    // CPU clock frequency, if it was changed from the machine one
+    cpu_hz: Option<usize>,
+    // Tape speed multiplier in thousandths
+    tape_speed: u64,
+    // Part of the CPU clocks which was not yet converted to the tape clocks
+    tape_clocks_remainder: u64,
     // frames count, which passed during emulation invocation
     passed_frames: usize,
+    // frames count, which passed since the controller creation
+    frame_number: u64,
+    // only one of `frame_skip` frames is rendered
+    frame_skip: usize,
+    // index of the current frame within the frame skip cycle
+    frame_skip_phase: usize,
     events: EmulationEvents,
+    // NMI edge which was not yet accepted by the CPU
+    nmi_pending: bool,
+    // (mask, value) pair of the ports which trigger I/O breakpoint
+    io_breakpoint: Option<(u16, u16)>,
+    // address of the instruction which is currently executed
+    instruction_pc: u16,
+    // address of the instruction which triggered the last I/O breakpoint
+    io_breakpoint_pc: u16,
     paging_enabled: bool,
     screen_bank: u8,
     current_port_7ffd: u8,
@@ -74,7 +143,7 @@ impl<H: Host> ZXController<H> {
                 paging = false;
                 screen_bank = 0;
             }
-            ZXMachine::Sinclair128K => {
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128 => {
                 memory = ZXMemory::new(RomType::K32, RamType::K128);
                 paging = true;
                 screen_bank = 5;
@@ -87,6 +156,20 @@ impl<H: Host> ZXController<H> {
             None
         };
 
+        let fuller = if settings.fuller_enabled {
+            Some(FullerJoy::default())
+        } else {
+            None
+        };
+
+        let joystick_kind = if kempston.is_some() {
+            JoystickKind::Kempston
+        } else if fuller.is_some() {
+            JoystickKind::Fuller
+        } else {
+            JoystickKind::Sinclair(SinclairJoyNum::Fist)
+        };
+
         let mouse = if settings.mouse_enabled {
             Some(KempstonMouse::default())
         } else {
@@ -95,32 +178,64 @@ impl<H: Host> ZXController<H> {
 
         let screen = ZXScreen::new(settings.machine, host_context.frame_buffer_context());
         #[cfg(feature = "precise-border")]
-        let border = ZXBorder::new(settings.machine, host_context.frame_buffer_context());
+        let border = ZXBorder::new(
+            settings.machine,
+            settings.border_size,
+            host_context.frame_buffer_context(),
+        );
 
         #[cfg(feature = "sound")]
         let mixer = Self::create_mixer(settings);
 
         let out = ZXController {
             machine: settings.machine,
+            timings: settings.timings,
+            issue: settings.issue,
             memory,
             screen,
             #[cfg(feature = "precise-border")]
             border,
             kempston,
+            fuller,
+            fuller_ay: settings.fuller_enabled,
+            joystick_kind,
             mouse,
+            snow_enabled: settings.ula_snow_enabled,
+            fast_boot_enabled: settings.fast_boot,
             io_extender: None,
             debug_interface: None,
+            tape_listener: None,
+            trace_sink: None,
             #[cfg(feature = "sound")]
             mixer,
+            #[cfg(feature = "sound")]
+            specdrum_port: settings.specdrum_port,
+            #[cfg(feature = "sound")]
+            covox_port: settings.covox_port,
             keyboard: [0xFF; 8],
+            keymap: Default::default(),
             keyboard_extended: [0xFF; 8],
             keyboard_sinclair: [0xFF; 8],
+            keyboard_typed: [0xFF; 8],
+            typer: Default::default(),
+            input_queue: Default::default(),
             caps_shift_modifier_mask: 0,
             border_color: ZXColor::Black,
+            ula_output: 0,
             frame_clocks: 0,
+            cpu_hz: None,
+            tape_speed: TAPE_SPEED_NORMAL,
+            tape_clocks_remainder: 0,
             passed_frames: 0,
+            frame_number: 0,
+            frame_skip: 1,
+            frame_skip_phase: 0,
             tape: Default::default(),
             events: Default::default(),
+            nmi_pending: false,
+            io_breakpoint: None,
+            instruction_pc: 0,
+            io_breakpoint_pc: 0,
             paging_enabled: paging,
             screen_bank,
             current_port_7ffd: 0,
@@ -145,6 +260,11 @@ impl<H: Host> ZXController<H> {
             settings.ay_enabled,
             #[cfg(feature = "ay")]
             settings.ay_mode,
+            #[cfg(feature = "ay")]
+            settings.turbosound_enabled,
+            settings.specdrum_port.is_some(),
+            settings.covox_port.is_some(),
+            settings.sound_channels_enabled,
             settings.sound_sample_rate,
         );
         mixer.volume(settings.sound_volume as f64 / 200.0);
@@ -154,7 +274,7 @@ impl<H: Host> ZXController<H> {
     /// returns current frame emulation pos in percents
     #[cfg(feature = "sound")]
     fn frame_pos(&self) -> f64 {
-        let val = self.frame_clocks as f64 / self.machine.specs().clocks_frame as f64;
+        let val = self.frame_clocks as f64 / self.clocks_frame() as f64;
         if val > 1.0 {
             1.0
         } else {
@@ -170,7 +290,7 @@ impl<H: Host> ZXController<H> {
                 let page = self.memory.rom_page_data_mut(0);
                 page.copy_from_slice(roms::ROM_48K);
             }
-            ZXMachine::Sinclair128K => {
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128 => {
                 let page = self.memory.rom_page_data_mut(0);
                 page.copy_from_slice(roms::ROM_128K_0);
                 let page = self.memory.rom_page_data_mut(1);
@@ -181,15 +301,30 @@ impl<H: Host> ZXController<H> {
 
     /// Changes key state in controller
     pub fn send_key(&mut self, key: ZXKey, pressed: bool) {
+        let (row, bit) = self.keymap.position(key);
         if pressed {
-            self.keyboard[key.row_id()] &= !key.mask();
+            self.keyboard[row] &= !(1 << bit);
             return;
         }
-        self.keyboard[key.row_id()] |= key.mask();
+        self.keyboard[row] |= 1 << bit;
+    }
+
+    /// Replaces key positions table, keys pressed with the previous table (including compound
+    /// keys) are released
+    pub fn set_keymap(&mut self, map: KeyMap) {
+        self.keymap = map;
+        self.keyboard = [0xFF; 8];
+        self.keyboard_extended = [0xFF; 8];
+        self.caps_shift_modifier_mask = 0;
     }
 
     pub fn send_sinclair_key(&mut self, num: SinclairJoyNum, key: SinclairKey, pressed: bool) {
         let key = sinclair::sinclair_event_to_zx_key(key, num);
+        self.send_joystick_matrix_key(key, pressed);
+    }
+
+    /// Changes key state in the keyboard layer of the keyboard-mapped joysticks
+    fn send_joystick_matrix_key(&mut self, key: ZXKey, pressed: bool) {
         if pressed {
             self.keyboard_sinclair[key.row_id()] &= !key.mask();
             return;
@@ -197,25 +332,110 @@ impl<H: Host> ZXController<H> {
         self.keyboard_sinclair[key.row_id()] |= key.mask();
     }
 
+    /// Selects joystick which receives `joystick_input` state. Inputs of the previously
+    /// selected joystick are released. Kempston and Fuller interfaces are attached if they
+    /// were not enabled in the settings, AY ports decoding is not changed by this
+    pub fn set_joystick_kind(&mut self, kind: JoystickKind) {
+        self.joystick_input(JoyState::default());
+        match kind {
+            JoystickKind::Kempston => {
+                self.kempston.get_or_insert_with(Default::default);
+            }
+            JoystickKind::Fuller => {
+                self.fuller.get_or_insert_with(Default::default);
+            }
+            JoystickKind::Sinclair(_) | JoystickKind::Cursor => {}
+        }
+        self.joystick_kind = kind;
+    }
+
+    /// Routes logical joystick state to the currently selected joystick
+    pub fn joystick_input(&mut self, state: JoyState) {
+        match self.joystick_kind {
+            JoystickKind::Kempston => {
+                if let Some(joy) = &mut self.kempston {
+                    joy.key(KempstonKey::Up, state.up);
+                    joy.key(KempstonKey::Down, state.down);
+                    joy.key(KempstonKey::Left, state.left);
+                    joy.key(KempstonKey::Right, state.right);
+                    joy.key(KempstonKey::Fire, state.fire);
+                }
+            }
+            JoystickKind::Fuller => {
+                if let Some(joy) = &mut self.fuller {
+                    joy.key(FullerKey::Up, state.up);
+                    joy.key(FullerKey::Down, state.down);
+                    joy.key(FullerKey::Left, state.left);
+                    joy.key(FullerKey::Right, state.right);
+                    joy.key(FullerKey::Fire, state.fire);
+                }
+            }
+            JoystickKind::Sinclair(num) => {
+                self.send_sinclair_key(num, SinclairKey::Up, state.up);
+                self.send_sinclair_key(num, SinclairKey::Down, state.down);
+                self.send_sinclair_key(num, SinclairKey::Left, state.left);
+                self.send_sinclair_key(num, SinclairKey::Right, state.right);
+                self.send_sinclair_key(num, SinclairKey::Fire, state.fire);
+            }
+            JoystickKind::Cursor => {
+                for (key, pressed) in cursor::cursor_keys(state) {
+                    self.send_joystick_matrix_key(key, pressed);
+                }
+            }
+        }
+    }
+
+    /// Schedules input `event` to be applied at the start of the `frame`. Events scheduled
+    /// for the current or already passed frames are applied immediately
+    pub fn schedule_input(&mut self, frame: u64, event: InputEvent) {
+        if frame <= self.frame_number {
+            self.apply_input(event);
+            return;
+        }
+        self.input_queue.push(frame, event);
+    }
+
+    fn apply_input(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Key { key, pressed } => self.send_key(key, pressed),
+            InputEvent::CompoundKey { key, pressed } => self.send_compound_key(key, pressed),
+            InputEvent::KempstonKey { key, pressed } => {
+                if let Some(joy) = &mut self.kempston {
+                    joy.key(key, pressed);
+                }
+            }
+            InputEvent::FullerKey { key, pressed } => {
+                if let Some(joy) = &mut self.fuller {
+                    joy.key(key, pressed);
+                }
+            }
+            InputEvent::SinclairKey { num, key, pressed } => {
+                self.send_sinclair_key(num, key, pressed)
+            }
+            InputEvent::Joystick(state) => self.joystick_input(state),
+        }
+    }
+
     pub fn send_compound_key(&mut self, key: CompoundKey, pressed: bool) {
         let mut dummy_modifier_mask = 0;
         let modifier_mask = match key.modifier_key() {
             ZXKey::Shift => &mut self.caps_shift_modifier_mask,
             _ => &mut dummy_modifier_mask,
         };
-        let primary_key = key.primary_key();
-        let modifier_key = key.modifier_key();
+        // Both keys are translated through the key map, same as the keys sent via `send_key`
+        let (primary_row, primary_bit) = self.keymap.position(key.primary_key());
+        let (modifier_row, modifier_bit) = self.keymap.position(key.modifier_key());
 
         if pressed {
             *modifier_mask |= key.modifier_mask();
-            self.keyboard_extended[primary_key.row_id()] &= !primary_key.mask();
-            self.keyboard_extended[modifier_key.row_id()] &= !modifier_key.mask();
+            self.keyboard_extended[primary_row] &= !(1 << primary_bit);
+            self.keyboard_extended[modifier_row] &= !(1 << modifier_bit);
         } else {
             *modifier_mask &= !key.modifier_mask();
             if *modifier_mask == 0 {
-                self.keyboard_extended[modifier_key.row_id()] |= modifier_key.mask();
+                self.keyboard_extended[modifier_row] |= 1 << modifier_bit;
             }
-            self.keyboard_extended[primary_key.row_id()] |= primary_key.mask();
+            self.keyboard_extended[primary_row] |= 1 << primary_bit;
         }
     }
 
@@ -239,8 +459,12 @@ impl<H: Host> ZXController<H> {
 
     /// Returns current bus floating value
     fn floating_bus_value(&self) -> u8 {
+        // Pentagon ULA does not leak screen data to the data bus
+        if self.machine == ZXMachine::Pentagon128 {
+            return 0xFF;
+        }
         let specs = self.machine.specs();
-        let clocks = self.frame_clocks;
+        let clocks = self.raster_clocks();
         if clocks < specs.clocks_first_pixel + 2 {
             return 0xFF;
         }
@@ -262,15 +486,20 @@ impl<H: Host> ZXController<H> {
         0xFF
     }
 
+    /// Returns contention clocks at the current frame position
+    fn contention_clocks(&self) -> usize {
+        self.machine.contention_clocks(self.raster_clocks())
+    }
+
     /// make contention
     fn do_contention(&mut self) {
-        let contention = self.machine.contention_clocks(self.frame_clocks);
+        let contention = self.contention_clocks();
         self.wait_internal(contention);
     }
 
     /// make contention + wait some clocks
     fn do_contention_and_wait(&mut self, wait_time: usize) {
-        let contention = self.machine.contention_clocks(self.frame_clocks);
+        let contention = self.contention_clocks();
         self.wait_internal(contention + wait_time);
     }
 
@@ -306,12 +535,34 @@ impl<H: Host> ZXController<H> {
 
     /// Starts a new frame
     fn new_frame(&mut self) {
-        self.frame_clocks -= self.machine.specs().clocks_frame;
+        self.frame_clocks -= self.clocks_frame();
+        self.keyboard_typed = self.typer.next_frame();
+        self.frame_number += 1;
+        while let Some(event) = self.input_queue.pop_due(self.frame_number) {
+            self.apply_input(event);
+        }
         self.screen.new_frame();
         #[cfg(feature = "precise-border")]
         self.border.new_frame();
         #[cfg(feature = "sound")]
         self.mixer.new_frame();
+
+        self.frame_skip_phase = (self.frame_skip_phase + 1) % self.frame_skip;
+        self.set_rendering(self.frame_skip_phase == 0);
+    }
+
+    /// Renders only one of each `frame_skip` frames, value of `0` is treated as `1`.
+    /// Emulation and sound are not affected
+    pub fn set_frame_skip(&mut self, frame_skip: usize) {
+        self.frame_skip = frame_skip.max(1);
+        self.frame_skip_phase = 0;
+        self.set_rendering(true);
+    }
+
+    fn set_rendering(&mut self, rendering: bool) {
+        self.screen.set_rendering(rendering);
+        #[cfg(feature = "precise-border")]
+        self.border.set_rendering(rendering);
     }
 
     /// Collects all events from the last emulation step
@@ -324,15 +575,138 @@ impl<H: Host> ZXController<H> {
         self.passed_frames
     }
 
+    /// Triggers I/O breakpoint event on each access of the port, which matches `port_value`
+    /// in the bits set in `port_mask`
+    pub fn set_io_breakpoint(&mut self, port_mask: u16, port_value: u16) {
+        self.io_breakpoint = Some((port_mask, port_value & port_mask));
+    }
+
+    pub fn clear_io_breakpoint(&mut self) {
+        self.io_breakpoint = None;
+    }
+
+    fn check_io_breakpoint(&mut self, port: u16) {
+        if let Some((mask, value)) = self.io_breakpoint {
+            if port & mask == value {
+                self.events |= EmulationEvents::IO_BREAKPOINT;
+                self.io_breakpoint_pc = self.instruction_pc;
+            }
+        }
+    }
+
+    /// Returns address of the instruction which triggered the last I/O breakpoint event
+    pub fn io_breakpoint_pc(&self) -> u16 {
+        self.io_breakpoint_pc
+    }
+
+    /// Returns frames count passed since the controller creation
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+
+    /// Returns clocks passed since the start of the current frame
+    pub fn frame_clocks(&self) -> usize {
+        self.frame_clocks
+    }
+
+    /// Returns clocks left until the next frame interrupt
+    pub fn clocks_to_interrupt(&self) -> usize {
+        self.clocks_frame().saturating_sub(self.frame_clocks)
+    }
+
+    /// Requests non-maskable interrupt, which is accepted by the CPU before the next instruction
+    pub fn trigger_nmi(&mut self) {
+        self.nmi_pending = true;
+    }
+
+    /// Changes CPU clock frequency. ULA, tape and sound keep their real time timings, so
+    /// the frame length in CPU clocks changes proportionally. Contention delays are not
+    /// scaled, therefore contended timings are approximate on the changed frequency
+    pub fn set_cpu_hz(&mut self, hz: usize) {
+        let hz = hz.max(1);
+        self.cpu_hz = (hz != self.machine.specs().freq_cpu).then_some(hz);
+        self.tape_clocks_remainder = 0;
+    }
+
+    /// Changes tape playback speed multiplier relative to the CPU, e.g. `2.0` makes all tape
+    /// pulses twice shorter. Multiplier is applied with `0.001` precision
+    pub fn set_tape_speed(&mut self, speed: f32) {
+        self.tape_speed = (speed.clamp(0.0, MAX_TAPE_SPEED) * TAPE_SPEED_NORMAL as f32) as u64;
+        self.tape_clocks_remainder = 0;
+    }
+
+    /// Returns current CPU clock frequency
+    pub fn cpu_hz(&self) -> usize {
+        self.cpu_hz.unwrap_or(self.machine.specs().freq_cpu)
+    }
+
+    /// Returns frame length in CPU clocks
+    pub fn clocks_frame(&self) -> usize {
+        let clocks_frame = self.machine.specs().clocks_frame;
+        match self.cpu_hz {
+            Some(hz) => {
+                (clocks_frame as u64 * hz as u64 / self.machine.specs().freq_cpu as u64) as usize
+            }
+            None => clocks_frame,
+        }
+    }
+
+    /// Converts CPU clocks to the clocks of the machine frequency, in which ULA timings are
+    /// defined
+    fn machine_clocks(&self, clocks: usize) -> usize {
+        match self.cpu_hz {
+            Some(hz) => (clocks as u64 * self.machine.specs().freq_cpu as u64 / hz as u64) as usize,
+            None => clocks,
+        }
+    }
+
+    /// Converts passed CPU clocks to the tape clocks, carrying the conversion remainder to
+    /// the next call
+    fn tape_clocks(&mut self, clocks: usize) -> usize {
+        if self.cpu_hz.is_none() && self.tape_speed == TAPE_SPEED_NORMAL {
+            return clocks;
+        }
+        let total = clocks as u64 * self.machine.specs().freq_cpu as u64 * self.tape_speed
+            + self.tape_clocks_remainder;
+        let divider = self.cpu_hz() as u64 * TAPE_SPEED_NORMAL;
+        self.tape_clocks_remainder = total % divider;
+        (total / divider) as usize
+    }
+
+    /// Returns ULA clocks passed since the start of the current frame. ULA of the late timings
+    /// machine lags behind the interrupt by [ZXTimings::offset] clocks, so contention, floating
+    /// bus and screen rendering are delayed, while the interrupt window is not
+    pub fn raster_clocks(&self) -> usize {
+        self.machine_clocks(self.frame_clocks)
+            .saturating_sub(self.timings.offset())
+    }
+
     pub fn reset_frame_counter(&mut self) {
         self.passed_frames = 0;
     }
 
+    /// Resets machine hardware state on reset button press, memory contents are kept
+    pub fn reset(&mut self) {
+        self.nmi_pending = false;
+        self.events = Default::default();
+        // ULA output latch is cleared, so border turns black until ROM sets its own color
+        self.ula_output = 0;
+        self.set_border_color(self.raster_clocks(), ZXColor::Black);
+        // Beeper follows the cleared ULA latch, AY chips are cleared by the RESET line
+        #[cfg(feature = "sound")]
+        self.mixer.reset();
+        if self.machine != ZXMachine::Sinclair48K {
+            self.paging_enabled = true;
+            self.write_7ffd(0);
+        }
+    }
+
     pub fn write_7ffd(&mut self, val: u8) {
         if !self.paging_enabled {
             return;
         }
         self.current_port_7ffd = val;
+        log_debug!("Port 0x7FFD paging changed to {:#04X}", val);
         // remap top 16K of the ram
         self.memory.remap(3, Page::Ram(val & 0x07));
         // third block is not pageable
@@ -352,9 +726,47 @@ impl<H: Host> ZXController<H> {
         self.current_port_7ffd
     }
 
+    /// Returns RAM bank which is currently displayed by the ULA
+    pub fn screen_bank(&self) -> u8 {
+        self.screen_bank
+    }
+
+    /// Returns RAM page which holds 16K `bank` in the 128K machine numbering. 48K machine
+    /// has only banks 5, 2 and 0, which are fixed at `0x4000`, `0x8000` and `0xC000`
+    fn bank_page(&self, bank: u8) -> Option<u8> {
+        match self.machine {
+            ZXMachine::Sinclair48K => match bank {
+                5 => Some(0),
+                2 => Some(1),
+                0 => Some(2),
+                _ => None,
+            },
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128 => (bank < 8).then_some(bank),
+        }
+    }
+
+    /// Reads byte at `offset` of the RAM `bank` regardless of the memory paging. Returns
+    /// `0xFF` if machine has no such bank
+    pub fn read_bank(&self, bank: u8, offset: u16) -> u8 {
+        match self.bank_page(bank) {
+            Some(page) => self.memory.ram_page_data(page)[offset as usize % PAGE_SIZE],
+            None => 0xFF,
+        }
+    }
+
+    /// Writes byte at `offset` of the RAM `bank` regardless of the memory paging. Writes to
+    /// the banks which machine does not have are ignored
+    pub fn write_bank(&mut self, bank: u8, offset: u16, value: u8) {
+        if let Some(page) = self.bank_page(bank) {
+            let offset = offset % PAGE_SIZE as u16;
+            self.memory.ram_page_data_mut(page)[offset as usize] = value;
+            self.screen.update(offset, page as usize, value);
+        }
+    }
+
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn read_ay_port(&mut self) -> u8 {
-        self.mixer.ay.read()
+        self.mixer.active_ay().read()
     }
 
     #[cfg(not(all(feature = "sound", feature = "ay")))]
@@ -364,7 +776,7 @@ impl<H: Host> ZXController<H> {
 
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn write_ay_port(&mut self, value: u8) {
-        self.mixer.ay.write(value);
+        self.mixer.active_ay().write(value);
     }
 
     #[cfg(not(all(feature = "sound", feature = "ay")))]
@@ -372,18 +784,60 @@ impl<H: Host> ZXController<H> {
 
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn select_ay_reg(&mut self, value: u8) {
-        self.mixer.ay.select_reg(value)
+        use crate::zx::sound::ay::{TURBOSOUND_SELECT_FIRST, TURBOSOUND_SELECT_SECOND};
+
+        match value {
+            TURBOSOUND_SELECT_FIRST | TURBOSOUND_SELECT_SECOND
+                if self.mixer.turbosound.is_some() =>
+            {
+                self.mixer
+                    .select_turbosound_chip(value == TURBOSOUND_SELECT_SECOND);
+            }
+            _ => self.mixer.active_ay().select_reg(value),
+        }
     }
 
     #[cfg(not(all(feature = "sound", feature = "ay")))]
     fn select_ay_reg(&mut self, _: u8) {}
 
+    #[cfg(feature = "sound")]
+    fn is_dac_port(&self, port: u16) -> bool {
+        let port = Some(port as u8);
+        port == self.specdrum_port || port == self.covox_port
+    }
+
+    #[cfg(not(feature = "sound"))]
+    fn is_dac_port(&self, _: u16) -> bool {
+        false
+    }
+
+    #[cfg(feature = "sound")]
+    fn write_dac_port(&mut self, port: u16, value: u8) {
+        let port = Some(port as u8);
+        if port == self.specdrum_port {
+            if let Some(dac) = &mut self.mixer.specdrum {
+                dac.write(value);
+            }
+        }
+        if port == self.covox_port {
+            if let Some(dac) = &mut self.mixer.covox {
+                dac.write(value);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "sound"))]
+    fn write_dac_port(&mut self, _: u16, _: u8) {}
+
     pub(crate) fn set_border_color(
         &mut self,
         #[cfg(feature = "precise-border")] clocks: usize,
         #[cfg(not(feature = "precise-border"))] _clocks: usize,
         color: ZXColor,
     ) {
+        if color as u8 != self.border_color as u8 {
+            self.screen.border_changed();
+        }
         self.border_color = color;
         #[cfg(feature = "precise-border")]
         self.border.set_border(clocks, color);
@@ -393,6 +847,30 @@ impl<H: Host> ZXController<H> {
         self.last_emulation_error.take()
     }
 
+    #[cfg(feature = "serde")]
+    pub(crate) fn ula_state(&self) -> UlaState {
+        UlaState {
+            border_color: self.border_color,
+            frame_clocks: self.frame_clocks,
+            paging_enabled: self.paging_enabled,
+            screen_bank: self.screen_bank,
+            port_7ffd: self.current_port_7ffd,
+        }
+    }
+
+    /// Restores ULA state. Memory should be restored beforehand, as memory-dependent devices
+    /// are refreshed from the current memory content
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_ula_state(&mut self, state: UlaState) {
+        self.frame_clocks = state.frame_clocks;
+        self.set_border_color(self.raster_clocks(), state.border_color);
+        self.paging_enabled = state.paging_enabled;
+        self.screen_bank = state.screen_bank;
+        self.current_port_7ffd = state.port_7ffd;
+        self.screen.switch_bank(state.screen_bank as usize);
+        self.refresh_memory_dependent_devices();
+    }
+
     pub(crate) fn refresh_memory_dependent_devices(&mut self) {
         match self.machine {
             ZXMachine::Sinclair48K => {
@@ -400,7 +878,7 @@ impl<H: Host> ZXController<H> {
                     self.screen.update(idx as u16, 0, *data);
                 }
             }
-            ZXMachine::Sinclair128K => {
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128 => {
                 for (idx, data) in self.memory.ram_page_data(5).iter().enumerate() {
                     self.screen.update(idx as u16, 5, *data);
                 }
@@ -419,15 +897,35 @@ impl<H: Host> Z80Bus for ZXController<H> {
         // check mapped memory page at 0x0000 .. 0x3FFF
         let check_fast_load = match self.machine {
             ZXMachine::Sinclair48K if self.memory.get_bank_type(0) == Page::Rom(0) => true,
-            ZXMachine::Sinclair128K if self.memory.get_bank_type(0) == Page::Rom(1) => true,
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128
+                if self.memory.get_bank_type(0) == Page::Rom(1) =>
+            {
+                true
+            }
             _ => false,
         };
         if check_fast_load {
-            // Tape LOAD/VERIFY
-            if addr == ADDR_LD_BREAK {
-                // Add event (Fast tape loading request) it must be executed
-                // by emulator immediately
-                self.events |= EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED;
+            match addr {
+                // Tape LOAD/VERIFY
+                ADDR_LD_BREAK => {
+                    // Add event (Fast tape loading request) it must be executed
+                    // by emulator immediately
+                    self.events |= EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED;
+                }
+                ADDR_LD_BYTES => self.events |= EmulationEvents::TAPE_LOAD_ROUTINE_ENTERED,
+                ADDR_SA_LD_RET => self.events |= EmulationEvents::TAPE_LOAD_ROUTINE_EXITED,
+                _ => {}
+            }
+        }
+        if self.fast_boot_enabled && self.memory.get_bank_type(0) == Page::Rom(0) {
+            let startup_trap = match self.machine {
+                ZXMachine::Sinclair48K => addr == ADDR_RAM_CHECK_48K,
+                ZXMachine::Sinclair128K | ZXMachine::Pentagon128 => {
+                    addr == ADDR_STARTUP_DELAY_128K || addr == ADDR_RAM_CLEAR_128K
+                }
+            };
+            if startup_trap {
+                self.events |= EmulationEvents::FAST_BOOT_TRIGGER_DETECTED;
             }
         }
         if let Some(debug) = &mut self.debug_interface {
@@ -455,16 +953,28 @@ impl<H: Host> Z80Bus for ZXController<H> {
     /// Changes internal state on clocks count change (emulation processing)
     fn wait_internal(&mut self, clk: usize) {
         self.frame_clocks += clk;
-        if let Err(e) = self.tape.process_clocks(clk) {
+        let tape_was_playing = self.tape.is_playing();
+        let tape_clocks = self.tape_clocks(clk);
+        if let Err(e) = self.tape.process_clocks(tape_clocks) {
             self.last_emulation_error = Some(e);
         }
+        while let Some(event) = self.tape.take_event() {
+            if let Some(listener) = &mut self.tape_listener {
+                listener.on_tape_event(event);
+            }
+        }
+        if tape_was_playing && !self.tape.is_playing() {
+            if let Some(listener) = &mut self.tape_listener {
+                listener.on_tape_stopped();
+            }
+        }
         #[cfg(feature = "sound")]
         {
             let pos = self.frame_pos();
             self.mixer.process(pos);
         }
-        self.screen.process_clocks(self.frame_clocks);
-        if self.frame_clocks >= self.machine.specs().clocks_frame {
+        self.screen.process_clocks(self.raster_clocks());
+        if self.frame_clocks >= self.clocks_frame() {
             self.new_frame();
             self.passed_frames += 1;
         }
@@ -479,6 +989,8 @@ impl<H: Host> Z80Bus for ZXController<H> {
                     self.do_contention();
                 }
             }
+            // Pentagon has no contended memory
+            ZXMachine::Pentagon128 => {}
         }
         self.wait_internal(clk);
     }
@@ -491,6 +1003,7 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     /// read io from hardware
     fn read_io(&mut self, port: u16) -> u8 {
+        self.check_io_breakpoint(port);
         // all contentions check
         self.io_contention_first(port);
         self.io_contention_last(port);
@@ -510,16 +1023,26 @@ impl<H: Host> Z80Bus for ZXController<H> {
             for n in 0..8 {
                 // if bit of row reset
                 if ((h >> n) & 0x01) == 0 {
-                    let keyboard_byte =
-                        self.keyboard[n] & self.keyboard_extended[n] & self.keyboard_sinclair[n];
+                    let keyboard_byte = self.keyboard[n]
+                        & self.keyboard_extended[n]
+                        & self.keyboard_sinclair[n]
+                        & self.keyboard_typed[n];
                     tmp &= keyboard_byte;
                 }
             }
 
-            // Emulate zx spectrum "issue 2" model.
-            // For future "issue 3" implementation condition will be `!self.ear`, but
-            // different zx spectrum "issues" emulation is not planned yet
-            if !self.tape.current_bit() {
+            let ear = if self.tape.is_playing() {
+                self.tape.current_bit()
+            } else {
+                // Without tape signal EAR input is driven by the ULA output, threshold of
+                // which differs between keyboard issues
+                let mask = match self.issue {
+                    KeyboardIssue::Issue2 => 0x18,
+                    KeyboardIssue::Issue3 => 0x10,
+                };
+                self.ula_output & mask != 0
+            };
+            if !ear {
                 tmp ^= 0x40;
             }
             // 5 and 7 bits are unused
@@ -530,7 +1053,11 @@ impl<H: Host> Z80Bus for ZXController<H> {
             self.mouse.as_ref().unwrap().x_pos_port
         } else if self.mouse.is_some() && (port & 0x0521 == 0x0501) {
             self.mouse.as_ref().unwrap().y_pos_port
-        } else if port & 0xC002 == 0xC000 {
+        } else if self.fuller_ay && (port & 0x00FF == FULLER_AY_REG_PORT) {
+            self.read_ay_port()
+        } else if self.fuller.is_some() && (port & 0x00FF == FULLER_JOY_PORT) {
+            self.fuller.as_ref().unwrap().read()
+        } else if !self.fuller_ay && (port & 0xC002 == 0xC000) {
             self.read_ay_port()
         } else if self.kempston.is_some() && (port & 0x00E0 == 0) {
             self.kempston.as_ref().unwrap().read()
@@ -544,7 +1071,9 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     /// write value to hardware port
     fn write_io(&mut self, port: u16, data: u8) {
-        // first contention
+        self.check_io_breakpoint(port);
+        // first contention, device latches the data right after it (including 128K paging,
+        // which takes effect mid-instruction and affects the rest of the scanline rendering)
         self.io_contention_first(port);
 
         // find active port
@@ -554,19 +1083,34 @@ impl<H: Host> Z80Bus for ZXController<H> {
             .map_or(false, |e| e.extends_port(port))
         {
             self.io_extender.as_mut().unwrap().write(port, data);
-        } else if port & 0xC002 == 0xC000 {
+        } else if self.fuller_ay && (port & 0x00FF == FULLER_AY_REG_PORT) {
+            self.select_ay_reg(data);
+        } else if self.fuller_ay && (port & 0x00FF == FULLER_AY_DATA_PORT) {
+            self.write_ay_port(data);
+        } else if !self.fuller_ay && (port & 0xC002 == 0xC000) {
             self.select_ay_reg(data);
-        } else if port & 0xC002 == 0x8000 {
+        } else if !self.fuller_ay && (port & 0xC002 == 0x8000) {
             self.write_ay_port(data);
+        } else if self.is_dac_port(port) {
+            self.write_dac_port(port, data);
         } else if port & 0x0001 == 0 {
-            self.set_border_color(self.frame_clocks, ZXColor::from_bits(data & 0x07));
+            self.set_border_color(self.raster_clocks(), ZXColor::from_bits(data & 0x07));
+            self.ula_output = data;
+            if let ZXTape::Loopback(tape) = &mut self.tape {
+                tape.record_mic(data & 0x08 != 0);
+            }
             #[cfg(feature = "sound")]
             {
                 let mic = data & 0x08 != 0;
                 let ear = data & 0x10 != 0;
                 self.mixer.beeper.change_state(ear, mic);
             }
-        } else if (port & 0x8002 == 0) && (self.machine == ZXMachine::Sinclair128K) {
+        } else if (port & 0x8002 == 0)
+            && matches!(
+                self.machine,
+                ZXMachine::Sinclair128K | ZXMachine::Pentagon128
+            )
+        {
             self.write_7ffd(data);
         }
         // last contention after byte write
@@ -575,20 +1119,44 @@ impl<H: Host> Z80Bus for ZXController<H> {
         self.wait_internal(1);
     }
 
-    /// value, requested during `INT0` interrupt
+    /// value on the data bus during interrupt acknowledge (used as IM2 vector low byte). No
+    /// device drives the bus, so ULA floating bus value is read, which is 0xFF while INT is
+    /// active at the top border
     fn read_interrupt(&mut self) -> u8 {
-        0xFF
+        self.floating_bus_value()
+    }
+
+    fn int_accepted(&mut self) {
+        self.events |= EmulationEvents::INTERRUPT_ACCEPTED;
+    }
+
+    fn instruction_start(&mut self, addr: u16) {
+        self.instruction_pc = addr;
+    }
+
+    fn refresh(&mut self, ir: u16) {
+        if self.snow_enabled && self.addr_is_contended(ir) {
+            // Refresh is performed during the last 2 clocks of the opcode fetch
+            let [refresh_low, _] = ir.to_le_bytes();
+            self.screen
+                .snow(self.raster_clocks().saturating_sub(2), refresh_low);
+        }
     }
 
     /// checks system maskable interrupt pin state
     fn int_active(&self) -> bool {
-        self.frame_clocks % self.machine.specs().clocks_frame
+        self.machine_clocks(self.frame_clocks % self.clocks_frame())
             < self.machine.specs().interrupt_length
     }
 
     /// checks non-maskable interrupt pin state
     fn nmi_active(&self) -> bool {
-        false
+        self.nmi_pending
+    }
+
+    fn nmi_accepted(&mut self) {
+        // NMI is edge-triggered, so it is accepted only once per request
+        self.nmi_pending = false;
     }
 
     /// CPU calls it when RETI instruction was processed
@@ -597,3 +1165,1089 @@ impl<H: Host> Z80Bus for ZXController<H> {
     /// CPU calls when was being halted
     fn halt(&mut self, _: bool) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zx::{
+        joy::fuller::FullerKey,
+        tape::LoopbackTape,
+        testing::{test_controller, test_settings},
+        typing::KeyStroke,
+    };
+    use alloc::vec::Vec;
+    use rustzx_z80::Z80;
+
+    const PROGRAM_ADDR: u16 = 0x8000;
+    const OPCODE_NOP: u8 = 0x00;
+    const OPCODE_EI: u8 = 0xFB;
+    const PREFIX_DD: u8 = 0xDD;
+
+    /// Runs `steps` CPU steps of `program` in IM1 starting at `frame_clocks` and returns true
+    /// if the maskable interrupt has been accepted
+    fn int_accepted(program: &[u8], iff1: bool, frame_clocks: usize, steps: usize) -> bool {
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        for (offset, byte) in program.iter().enumerate() {
+            controller.memory.write(PROGRAM_ADDR + offset as u16, *byte);
+        }
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        cpu.regs.set_iff1(iff1);
+        cpu.set_im(1);
+        controller.frame_clocks = frame_clocks;
+
+        let mut events = EmulationEvents::empty();
+        for _ in 0..steps {
+            cpu.emulate(&mut controller);
+            events |= controller.take_events();
+        }
+        events.contains(EmulationEvents::INTERRUPT_ACCEPTED)
+    }
+
+    #[test]
+    fn int_is_accepted_only_within_window() {
+        let specs = ZXMachine::Sinclair48K.specs();
+        let window = specs.interrupt_length;
+        assert!(int_accepted(&[OPCODE_NOP], true, 0, 1));
+        assert!(int_accepted(&[OPCODE_NOP], true, window - 1, 1));
+        assert!(!int_accepted(&[OPCODE_NOP], true, window, 1));
+        // Disabled interrupts
+        assert!(!int_accepted(&[OPCODE_NOP], false, 0, 1));
+        // NOP crosses frame boundary, interrupt is accepted on the next step
+        let frame_end = specs.clocks_frame - 1;
+        assert!(!int_accepted(&[OPCODE_NOP; 2], true, frame_end, 1));
+        assert!(int_accepted(&[OPCODE_NOP; 2], true, frame_end, 2));
+    }
+
+    #[test]
+    fn ei_delays_int_to_window_boundary() {
+        let window = ZXMachine::Sinclair48K.specs().interrupt_length;
+        let program = [OPCODE_EI, OPCODE_NOP, OPCODE_NOP];
+        // Interrupt is accepted only after the instruction which follows EI
+        assert!(!int_accepted(&program, false, window - 12, 2));
+        assert!(int_accepted(&program, false, window - 12, 3));
+        // Instruction after EI finishes exactly at the end of the INT window, interrupt is
+        // missed
+        assert!(!int_accepted(&program, false, window - 8, 3));
+    }
+
+    #[test]
+    fn late_timings_shift_int_window_relative_to_contention() {
+        let machine = ZXMachine::Sinclair48K;
+        let specs = machine.specs();
+        // Clocks from the end of the INT window to the first contended clock
+        let int_to_contention = |timings| {
+            let mut settings = test_settings(machine);
+            settings.timings = timings;
+            let mut controller = test_controller(&settings);
+            let first_contended = (0..specs.clocks_frame)
+                .find(|clocks| {
+                    controller.frame_clocks = *clocks;
+                    controller.contention_clocks() > 0
+                })
+                .unwrap();
+            first_contended - specs.interrupt_length
+        };
+
+        let early = int_to_contention(ZXTimings::Early);
+        let late = int_to_contention(ZXTimings::Late);
+        assert_eq!(early, specs.clocks_first_pixel - 1 - specs.interrupt_length);
+        assert_eq!(late - early, ZXTimings::Late.offset());
+    }
+
+    #[test]
+    fn idle_ear_bit_depends_on_keyboard_issue() {
+        let ear_bit = |issue, output| {
+            let mut settings = test_settings(ZXMachine::Sinclair48K);
+            settings.issue = issue;
+            let mut controller = test_controller(&settings);
+            controller.write_io(0x00FE, output);
+            controller.read_io(0xFFFE) & 0x40 != 0
+        };
+
+        // EAR and MIC output bits
+        for (output, issue2, issue3) in [
+            (0x00, false, false),
+            (0x08, true, false),
+            (0x10, true, true),
+            (0x18, true, true),
+        ] {
+            assert_eq!(ear_bit(KeyboardIssue::Issue2, output), issue2);
+            assert_eq!(ear_bit(KeyboardIssue::Issue3, output), issue3);
+        }
+    }
+
+    #[test]
+    fn nmi_wakes_cpu_from_halt_and_preserves_iff2() {
+        const OPCODE_HALT: u8 = 0x76;
+        const NMI_ADDR: u16 = 0x0066;
+        const STACK_ADDR: u16 = 0x9000;
+
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        controller.memory.write(PROGRAM_ADDR, OPCODE_HALT);
+        // Stop at the NMI handler to check CPU state on its entry
+        controller.memory.force_write(NMI_ADDR, OPCODE_HALT);
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        cpu.regs.set_sp(STACK_ADDR);
+        cpu.regs.set_iff1(true);
+        cpu.regs.set_iff2(true);
+        cpu.set_im(1);
+        // Keep maskable interrupt out of the way
+        controller.frame_clocks = ZXMachine::Sinclair48K.specs().interrupt_length;
+
+        cpu.emulate(&mut controller);
+        assert!(cpu.is_halted());
+
+        controller.trigger_nmi();
+        cpu.emulate(&mut controller);
+        assert_eq!(cpu.regs.get_pc(), NMI_ADDR);
+        assert!(!cpu.regs.get_iff1());
+        assert!(cpu.regs.get_iff2());
+        // Return address points to the instruction after HALT
+        assert_eq!(cpu.regs.get_sp(), STACK_ADDR - 2);
+        let return_addr = u16::from_le_bytes([
+            controller.memory.read(STACK_ADDR - 2),
+            controller.memory.read(STACK_ADDR - 1),
+        ]);
+        assert_eq!(return_addr, PROGRAM_ADDR + 1);
+
+        // Single request triggers NMI only once
+        cpu.emulate(&mut controller);
+        assert_eq!(cpu.regs.get_sp(), STACK_ADDR - 2);
+    }
+
+    #[test]
+    fn cpu_hz_scales_frame_and_tape_clocks() {
+        const CPU_HZ: usize = 7_000_000;
+        // One millisecond in T-states of the overclocked CPU
+        const MS_CLOCKS: usize = CPU_HZ / 1000;
+
+        let specs = ZXMachine::Sinclair48K.specs();
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        controller.set_cpu_hz(CPU_HZ);
+        assert_eq!(controller.clocks_frame(), specs.clocks_frame * 2);
+        controller.wait_internal(specs.clocks_frame);
+        assert_eq!(controller.frames_count(), 0);
+        controller.wait_internal(specs.clocks_frame);
+        assert_eq!(controller.frames_count(), 1);
+
+        // Record 1ms long MIC pulse and play it back
+        controller.tape = LoopbackTape::default().into();
+        controller.write_io(0x00FE, 0x08);
+        for _ in 0..MS_CLOCKS {
+            controller.wait_internal(1);
+        }
+        controller.write_io(0x00FE, 0x00);
+        controller.tape.play();
+        let mut pulse_clocks = 0usize;
+        controller.wait_internal(1);
+        while controller.tape.current_bit() {
+            controller.wait_internal(1);
+            pulse_clocks += 1;
+        }
+        // Pulse is recorded in 3.5MHz tape clocks, but still takes 1ms on playback, with
+        // a couple of tape clocks taken by the edge switching
+        assert!(pulse_clocks.abs_diff(MS_CLOCKS) <= 4, "{}", pulse_clocks);
+    }
+
+    #[test]
+    fn tape_speed_scales_pulse_lengths() {
+        use crate::{host::BufferCursor, zx::tape::Tap};
+
+        // Returns CPU clocks taken by the second pilot pulse
+        let pilot_pulse_clocks = |speed: f32| {
+            let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+            controller.set_tape_speed(speed);
+            let image = BufferCursor::new(alloc::vec![0x02, 0x00, 0xFF, 0x00]);
+            controller.tape = Tap::from_asset(image, ZXMachine::Sinclair48K)
+                .unwrap()
+                .into();
+            controller.tape.play();
+            controller.wait_internal(1);
+            while controller.tape.current_bit() {
+                controller.wait_internal(1);
+            }
+            let mut clocks = 0;
+            while !controller.tape.current_bit() {
+                controller.wait_internal(1);
+                clocks += 1;
+            }
+            clocks
+        };
+
+        // Edge switching takes a single tape clock. Scaled pulses may be a couple of CPU
+        // clocks off, as conversion remainder is carried between pulses
+        let normal: usize = pilot_pulse_clocks(1.0);
+        assert_eq!(normal, 2168 + 1);
+        assert!(pilot_pulse_clocks(2.0).abs_diff(normal / 2) <= 2);
+        assert!(pilot_pulse_clocks(0.5).abs_diff(normal * 2) <= 2);
+    }
+
+    #[test]
+    fn halt_executes_nops_until_interrupt() {
+        const OPCODE_HALT: u8 = 0x76;
+        const STACK_ADDR: u16 = 0x9000;
+        const NOP_CLOCKS: usize = 4;
+        const HALTED_NOPS: usize = 9;
+
+        let clocks_frame = ZXMachine::Sinclair48K.specs().clocks_frame;
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        controller.memory.write(PROGRAM_ADDR, OPCODE_HALT);
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        cpu.regs.set_sp(STACK_ADDR);
+        cpu.regs.set_iff1(true);
+        cpu.set_im(1);
+        // HALT and following NOPs end exactly at the start of the interrupt window
+        controller.frame_clocks = clocks_frame - (HALTED_NOPS + 1) * NOP_CLOCKS;
+
+        cpu.emulate(&mut controller);
+        for _ in 0..HALTED_NOPS {
+            assert!(cpu.is_halted());
+            assert_eq!(cpu.regs.get_pc(), PROGRAM_ADDR);
+            let clocks_before = controller.frame_clocks;
+            cpu.emulate(&mut controller);
+            // Last NOP ends the frame
+            let clocks_after = controller.frame_clocks + controller.frames_count() * clocks_frame;
+            assert_eq!(clocks_after - clocks_before, NOP_CLOCKS);
+            assert!(!controller
+                .take_events()
+                .contains(EmulationEvents::INTERRUPT_ACCEPTED));
+        }
+        assert_eq!(controller.frames_count(), 1);
+        assert_eq!(controller.frame_clocks, 0);
+
+        cpu.emulate(&mut controller);
+        assert!(controller
+            .take_events()
+            .contains(EmulationEvents::INTERRUPT_ACCEPTED));
+        assert!(!cpu.is_halted());
+        // Interrupt returns to the instruction after HALT
+        let return_addr = u16::from_le_bytes([
+            controller.memory.read(STACK_ADDR - 2),
+            controller.memory.read(STACK_ADDR - 1),
+        ]);
+        assert_eq!(return_addr, PROGRAM_ADDR + 1);
+    }
+
+    #[test]
+    fn clocks_to_interrupt_wraps_at_frame_end() {
+        const OPCODE_NOP: u8 = 0x00;
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        controller.memory.write(PROGRAM_ADDR, OPCODE_NOP);
+        controller.memory.write(PROGRAM_ADDR + 1, OPCODE_NOP);
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        let clocks_frame = controller.clocks_frame();
+        controller.frame_clocks = clocks_frame - 6;
+        assert_eq!(controller.clocks_to_interrupt(), 6);
+
+        cpu.emulate(&mut controller);
+        assert_eq!(controller.clocks_to_interrupt(), 2);
+        cpu.emulate(&mut controller);
+        assert_eq!(controller.clocks_to_interrupt(), clocks_frame - 2);
+    }
+
+    #[test]
+    fn scheduled_input_is_applied_at_its_frame() {
+        use ZXKey::*;
+
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        let clocks_frame = ZXMachine::Sinclair48K.specs().clocks_frame;
+        controller.schedule_input(
+            10,
+            InputEvent::Key {
+                key: A,
+                pressed: true,
+            },
+        );
+        controller.schedule_input(
+            12,
+            InputEvent::Key {
+                key: A,
+                pressed: false,
+            },
+        );
+
+        let mut frames = Vec::new();
+        for _ in 0..13 {
+            frames.push((controller.frame_number(), pressed_keys(&mut controller)));
+            controller.wait_internal(clocks_frame);
+        }
+        let expected = (0..13)
+            .map(|frame| {
+                let keys = if (10..12).contains(&frame) {
+                    rows(&[A])
+                } else {
+                    rows(&[])
+                };
+                (frame, keys)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(frames, expected);
+    }
+
+    #[test]
+    fn bank_access_ignores_paging() {
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair128K));
+        // Bank 0 is paged at 0xC000
+        controller.write_7ffd(0x00);
+        controller.write_bank(7, 0x0010, 0xA5);
+        assert_eq!(controller.read_bank(7, 0x0010), 0xA5);
+        assert_ne!(controller.memory.read(0xC010), 0xA5);
+        assert_eq!(
+            controller.read_bank(0, 0x0010),
+            controller.memory.read(0xC010)
+        );
+
+        controller.write_7ffd(0x07);
+        assert_eq!(controller.memory.read(0xC010), 0xA5);
+
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        controller.write_bank(5, 0x0000, 0x5A);
+        controller.write_bank(0, 0x3FFF, 0xC3);
+        assert_eq!(controller.memory.read(0x4000), 0x5A);
+        assert_eq!(controller.memory.read(0xFFFF), 0xC3);
+        assert_eq!(controller.read_bank(7, 0x0000), 0xFF);
+    }
+
+    #[test]
+    fn memory_accesses_are_contended_individually() {
+        const OPCODE_INC_MEM_HL: u8 = 0x34;
+        let machine = ZXMachine::Sinclair48K;
+        let mut controller = test_controller(&test_settings(machine));
+        controller.memory.write(PROGRAM_ADDR, OPCODE_INC_MEM_HL);
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        cpu.regs.set_hl(0x4000);
+        // Uncontended opcode fetch ends at the first contended clock of the screen line,
+        // contention pattern is 6,5,4,3,2,1,0,0
+        let start = machine.specs().clocks_first_pixel - 1 - 4;
+        controller.frame_clocks = start;
+        cpu.emulate(&mut controller);
+        // Reference breakdown: pc:4, hl:3, hl:1, hl(write):3. Each access to the contended
+        // `hl` is delayed according to the frame position at which it starts, the write falls
+        // on the uncontended clock of the pattern
+        assert_eq!(controller.frame_clocks - start, 4 + (6 + 3) + (5 + 1) + 3);
+    }
+
+    #[test]
+    fn keys_are_translated_through_keymap() {
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        let mut keymap = KeyMap::default();
+        assert_eq!(keymap.position(ZXKey::SymShift), (7, 1));
+        // Swap Z and Y, as on the QWERTZ layout
+        keymap.set(ZXKey::Z, 5, 4).unwrap();
+        keymap.set(ZXKey::Y, 0, 1).unwrap();
+        assert!(keymap.set(ZXKey::Y, 8, 0).is_err());
+        assert!(keymap.set(ZXKey::Y, 0, 5).is_err());
+        assert_eq!(keymap.position(ZXKey::Y), (0, 1));
+
+        controller.send_key(ZXKey::Y, true);
+        controller.set_keymap(keymap);
+        // Keys pressed before the map change are released
+        assert_eq!(pressed_keys(&mut controller), [0; 8]);
+
+        controller.send_key(ZXKey::Z, true);
+        assert_eq!(pressed_keys(&mut controller), rows(&[ZXKey::Y]));
+        controller.send_key(ZXKey::Y, true);
+        controller.send_key(ZXKey::Z, false);
+        assert_eq!(pressed_keys(&mut controller), rows(&[ZXKey::Z]));
+    }
+
+    #[test]
+    fn compound_keys_are_translated_through_keymap() {
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        let mut keymap = KeyMap::default();
+        // Swap Caps Shift and Z, and move 5 to the position of Q
+        keymap.set(ZXKey::Shift, 0, 1).unwrap();
+        keymap.set(ZXKey::Z, 0, 0).unwrap();
+        keymap.set(ZXKey::N5, 2, 0).unwrap();
+
+        controller.send_compound_key(CompoundKey::ArrowLeft, true);
+        controller.set_keymap(keymap);
+        // Compound keys pressed before the map change are released
+        assert_eq!(pressed_keys(&mut controller), [0; 8]);
+
+        controller.send_compound_key(CompoundKey::ArrowLeft, true);
+        assert_eq!(pressed_keys(&mut controller), rows(&[ZXKey::Z, ZXKey::Q]));
+        // Remapped and compound keys use the same positions of the shared keys
+        controller.send_key(ZXKey::Shift, true);
+        controller.send_compound_key(CompoundKey::ArrowLeft, false);
+        assert_eq!(pressed_keys(&mut controller), rows(&[ZXKey::Z]));
+    }
+
+    #[test]
+    fn late_timings_delay_floating_bus() {
+        let machine = ZXMachine::Sinclair48K;
+        let first_fetch = machine.specs().clocks_first_pixel + 2;
+        let floating_bus = |timings, clocks| {
+            let mut settings = test_settings(machine);
+            settings.timings = timings;
+            let mut controller = test_controller(&settings);
+            for offset in 0..32 {
+                controller.memory.write(0x4000 + offset, offset as u8);
+                controller
+                    .memory
+                    .write(0x5800 + offset, 0x80 | offset as u8);
+            }
+            controller.frame_clocks = clocks;
+            controller.floating_bus_value()
+        };
+
+        for clocks in first_fetch - 2..first_fetch + 16 {
+            let early = floating_bus(ZXTimings::Early, clocks);
+            let late = floating_bus(ZXTimings::Late, clocks + ZXTimings::Late.offset());
+            assert_eq!(early, late, "{}", clocks);
+        }
+        assert_eq!(floating_bus(ZXTimings::Early, first_fetch), 0x00);
+        assert_eq!(floating_bus(ZXTimings::Late, first_fetch), 0xFF);
+    }
+
+    /// Returns T-states taken by `OUT (port_low), A` executed from uncontended memory with
+    /// I/O cycle starting at `io_start` frame clocks
+    fn out_n_a_clocks(machine: ZXMachine, acc: u8, port_low: u8, io_start: usize) -> usize {
+        const OPCODE_OUT_N_A: u8 = 0xD3;
+        let mut controller = test_controller(&test_settings(machine));
+        controller.memory.write(PROGRAM_ADDR, OPCODE_OUT_N_A);
+        controller.memory.write(PROGRAM_ADDR + 1, port_low);
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        cpu.regs.set_acc(acc);
+        // Opcode fetch and port byte read take 7 clocks
+        let start = io_start - 7;
+        controller.frame_clocks = start;
+        cpu.emulate(&mut controller);
+        controller.frame_clocks - start
+    }
+
+    #[test]
+    fn ula_port_io_is_contended() {
+        for machine in [ZXMachine::Sinclair48K, ZXMachine::Sinclair128K] {
+            // First contended clock of the screen line, contention pattern is 6,5,4,3,2,1,0,0
+            let io_start = machine.specs().clocks_first_pixel - 1;
+            // Contended high byte, ULA port: C:1, C:3
+            assert_eq!(out_n_a_clocks(machine, 0x40, 0xFE, io_start), 7 + 6 + 1 + 3);
+            // Uncontended high byte, ULA port: N:1, C:3
+            assert_eq!(out_n_a_clocks(machine, 0x80, 0xFE, io_start), 7 + 1 + 5 + 3);
+            // Contended high byte, non-ULA port: C:1, C:1, C:1, C:1
+            assert_eq!(
+                out_n_a_clocks(machine, 0x40, 0xFF, io_start),
+                7 + 6 + 1 + 1 + 6 + 1 + 1
+            );
+            // Uncontended high byte, non-ULA port: N:4
+            assert_eq!(out_n_a_clocks(machine, 0x80, 0xFF, io_start), 7 + 4);
+        }
+        // Pentagon has no contention at all
+        let io_start = ZXMachine::Pentagon128.specs().clocks_first_pixel - 1;
+        assert_eq!(
+            out_n_a_clocks(ZXMachine::Pentagon128, 0x40, 0xFE, io_start),
+            7 + 4
+        );
+    }
+
+    #[test]
+    fn paging_port_write_is_contended() {
+        let machine = ZXMachine::Sinclair128K;
+        let io_start = machine.specs().clocks_first_pixel - 1;
+        // 0x7FFD has contended high byte and is not ULA port: C:1, C:1, C:1, C:1
+        assert_eq!(
+            out_n_a_clocks(machine, 0x7F, 0xFD, io_start),
+            7 + 6 + 1 + 1 + 6 + 1 + 1
+        );
+
+        // Paging is applied by the same instruction: 0x7F selects bank 7, shadow screen,
+        // 48K ROM and locks paging
+        let mut controller = test_controller(&test_settings(machine));
+        controller.memory.write(PROGRAM_ADDR, 0xD3);
+        controller.memory.write(PROGRAM_ADDR + 1, 0xFD);
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        cpu.regs.set_acc(0x7F);
+        cpu.emulate(&mut controller);
+        assert_eq!(controller.read_7ffd(), 0x7F);
+        assert!(matches!(controller.memory.get_page(0xC000), Page::Ram(7)));
+        assert_eq!(controller.screen_bank, 7);
+        assert!(!controller.paging_enabled);
+    }
+
+    #[test]
+    fn fast_load_trap_requires_48k_basic_rom_on_128k() {
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair128K));
+        // 128K editor ROM is paged in after reset
+        controller.pc_callback(ADDR_LD_BREAK);
+        assert!(!controller
+            .take_events()
+            .contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED));
+
+        controller.write_7ffd(0x10);
+        controller.pc_callback(ADDR_LD_BREAK);
+        assert!(controller
+            .take_events()
+            .contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED));
+    }
+
+    #[test]
+    fn tape_stopped_is_notified_once_at_end_of_tape() {
+        use crate::{
+            host::BufferCursor,
+            zx::{
+                tape::Tap,
+                testing::{TestHost, TestTapeListener},
+            },
+        };
+
+        let image = alloc::vec![0x02, 0x00, 0xFF, 0xAA];
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        controller.tape = Tap::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K)
+            .unwrap()
+            .into();
+        controller.tape_listener = Some(TestTapeListener::default());
+        let stops =
+            |controller: &ZXController<TestHost>| controller.tape_listener.as_ref().unwrap().stops;
+
+        // Manual stop is not reported
+        controller.tape.play();
+        controller.wait_internal(1000);
+        controller.tape.stop();
+        controller.wait_internal(1000);
+        assert_eq!(stops(&controller), 0);
+
+        controller.tape.play();
+        while controller.tape.is_playing() {
+            controller.wait_internal(1000);
+        }
+        assert_eq!(stops(&controller), 1);
+        controller.wait_internal(100_000);
+        assert_eq!(stops(&controller), 1);
+    }
+
+    #[test]
+    fn im2_vector_is_formed_from_i_and_bus_value() {
+        const VECTOR_TABLE: u16 = 0x9000;
+        const HANDLER_ADDR: u16 = 0xA000;
+
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        let mut cpu = Z80::default();
+        // Vector low byte is read from the floating bus, which carries screen data while ULA
+        // fetches the paper area
+        controller.memory.write(0x4000, 0x42);
+        controller.frame_clocks = ZXMachine::Sinclair48K.specs().clocks_first_pixel + 2;
+        assert_eq!(VECTOR_TABLE | controller.read_interrupt() as u16, 0x9042);
+        // INT is active at the top border, where the bus is idle, therefore vector table
+        // entry is at (I << 8) | 0xFF
+        controller.frame_clocks = 0;
+        let vector_addr = VECTOR_TABLE | controller.read_interrupt() as u16;
+        assert_eq!(vector_addr, 0x90FF);
+        controller.write_word(vector_addr, HANDLER_ADDR, 0);
+        controller.memory.write(HANDLER_ADDR, OPCODE_NOP);
+        controller.memory.write(PROGRAM_ADDR, OPCODE_NOP);
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        cpu.regs.set_i((VECTOR_TABLE >> 8) as u8);
+        cpu.regs.set_iff1(true);
+        cpu.set_im(2);
+
+        cpu.emulate(&mut controller);
+        assert!(controller
+            .take_events()
+            .contains(EmulationEvents::INTERRUPT_ACCEPTED));
+        // Handler NOP is executed right after the interrupt
+        assert_eq!(cpu.regs.get_pc(), HANDLER_ADDR + 1);
+    }
+
+    #[test]
+    fn int_is_not_accepted_mid_prefix() {
+        let frame_end = ZXMachine::Sinclair48K.specs().clocks_frame - 4;
+        // Double DD prefix finishes within INT window, but interrupt should wait until the
+        // prefixed instruction is executed
+        let program = [PREFIX_DD, PREFIX_DD, OPCODE_NOP, OPCODE_NOP];
+        assert!(!int_accepted(&program, true, frame_end, 2));
+        assert!(int_accepted(&program, true, frame_end, 3));
+    }
+
+    fn fuller_controller() -> ZXController<crate::zx::testing::TestHost> {
+        let mut settings = test_settings(ZXMachine::Sinclair48K);
+        settings.fuller_enabled = true;
+        test_controller(&settings)
+    }
+
+    #[test]
+    fn fuller_joystick_bit_layout() {
+        let mut controller = fuller_controller();
+        assert_eq!(controller.read_io(0x007F), 0xFF);
+
+        let joy = controller.fuller.as_mut().unwrap();
+        joy.key(FullerKey::Up, true);
+        joy.key(FullerKey::Fire, true);
+        assert_eq!(controller.read_io(0x007F), 0b0111_1110);
+
+        let joy = controller.fuller.as_mut().unwrap();
+        joy.key(FullerKey::Up, false);
+        joy.key(FullerKey::Fire, false);
+        joy.key(FullerKey::Down, true);
+        joy.key(FullerKey::Left, true);
+        joy.key(FullerKey::Right, true);
+        assert_eq!(controller.read_io(0x007F), 0b1111_0001);
+    }
+
+    #[test]
+    fn joystick_fire_is_routed_to_selected_kind() {
+        let fire = JoyState {
+            fire: true,
+            ..Default::default()
+        };
+        let keyboard_port = |key: ZXKey| u16::from_le_bytes([0xFE, !(1 << key.row_id())]);
+        // Kind, port, fire bit mask and value of the bit when fire is pressed
+        let cases = [
+            (JoystickKind::Kempston, 0x001F, 0x10, 0x10),
+            (JoystickKind::Fuller, FULLER_JOY_PORT, 0x80, 0x00),
+            (
+                JoystickKind::Sinclair(SinclairJoyNum::Fist),
+                keyboard_port(ZXKey::N0),
+                ZXKey::N0.mask(),
+                0x00,
+            ),
+            (
+                JoystickKind::Sinclair(SinclairJoyNum::Second),
+                keyboard_port(ZXKey::N5),
+                ZXKey::N5.mask(),
+                0x00,
+            ),
+            (
+                JoystickKind::Cursor,
+                keyboard_port(ZXKey::N0),
+                ZXKey::N0.mask(),
+                0x00,
+            ),
+        ];
+
+        for (kind, port, mask, pressed) in cases {
+            let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+            controller.set_joystick_kind(kind);
+            assert_ne!(controller.read_io(port) & mask, pressed, "{:?}", kind);
+            controller.joystick_input(fire);
+            assert_eq!(controller.read_io(port) & mask, pressed, "{:?}", kind);
+            // Switching the kind releases inputs of the previous joystick
+            controller.set_joystick_kind(JoystickKind::Cursor);
+            controller.set_joystick_kind(kind);
+            assert_ne!(controller.read_io(port) & mask, pressed, "{:?}", kind);
+        }
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    #[test]
+    fn fuller_ay_register_access() {
+        let mut controller = fuller_controller();
+        controller.write_io(0x003F, 0x07);
+        controller.write_io(0x005F, 0x3E);
+        controller.write_io(0x003F, 0x08);
+        controller.write_io(0x005F, 0x0F);
+
+        controller.write_io(0x003F, 0x07);
+        assert_eq!(controller.read_io(0x003F), 0x3E);
+        controller.write_io(0x003F, 0x08);
+        assert_eq!(controller.read_io(0x003F), 0x0F);
+
+        // 128K AY ports should not be decoded in Fuller mode
+        controller.write_io(0xFFFD, 0x07);
+        controller.write_io(0xBFFD, 0x00);
+        assert_eq!(controller.read_io(0x003F), 0x0F);
+        controller.write_io(0x003F, 0x07);
+        assert_eq!(controller.read_io(0x003F), 0x3E);
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    #[test]
+    fn ay_ports_are_kept_after_fuller_joystick_selection() {
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair128K));
+        for (kind, value) in [(JoystickKind::Fuller, 0x3E), (JoystickKind::Kempston, 0x2A)] {
+            controller.set_joystick_kind(kind);
+            controller.write_io(0xFFFD, 0x07);
+            controller.write_io(0xBFFD, value);
+            assert_eq!(controller.read_io(0xFFFD), value, "{:?}", kind);
+        }
+        // Fuller joystick is still readable
+        controller.set_joystick_kind(JoystickKind::Fuller);
+        assert_eq!(controller.read_io(FULLER_JOY_PORT), 0xFF);
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    #[test]
+    fn ay_registers_are_read_back() {
+        let mut settings = test_settings(ZXMachine::Sinclair128K);
+        settings.sound_enabled = true;
+        settings.ay_enabled = true;
+        let values = |reg: u8| reg.wrapping_mul(17) ^ 0x05;
+        let programmed_controller = || {
+            let mut controller = test_controller(&settings);
+            for reg in 0..16 {
+                controller.write_io(0xFFFD, reg);
+                controller.write_io(0xBFFD, values(reg));
+            }
+            controller
+        };
+
+        let mut controller = programmed_controller();
+        for reg in 0..16 {
+            controller.write_io(0xFFFD, reg);
+            assert_eq!(controller.read_io(0xFFFD), values(reg));
+        }
+        let expected: [u8; 16] = core::array::from_fn(|reg| values(reg as u8));
+        assert_eq!(controller.mixer.ay.registers(), expected);
+
+        // Reading envelope shape register should not restart the envelope, therefore output
+        // should be the same as when reading unrelated register
+        let mut reading = programmed_controller();
+        let mut baseline = programmed_controller();
+        reading.write_io(0xFFFD, 13);
+        baseline.write_io(0xFFFD, 0);
+        for _ in 0..64 {
+            assert_eq!(reading.read_io(0xFFFD), values(13));
+            assert_eq!(baseline.read_io(0xFFFD), values(0));
+            reading.wait_internal(1000);
+            baseline.wait_internal(1000);
+        }
+        let samples = |controller: &mut ZXController<_>| {
+            core::iter::from_fn(|| controller.mixer.pop())
+                .map(|sample| (sample.left, sample.right))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(samples(&mut reading), samples(&mut baseline));
+    }
+
+    /// Plays tone on the channel A of the second Turbosound chip and on the channel C of the
+    /// first one, muting disabled chips, and returns the produced samples
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    fn turbosound_output(first: bool, second: bool) -> Vec<(f32, f32)> {
+        let mut settings = test_settings(ZXMachine::Sinclair128K);
+        settings.sound_enabled = true;
+        settings.ay_enabled = true;
+        settings.turbosound_enabled = true;
+        let mut controller = test_controller(&settings);
+        let volume = |enabled: bool| if enabled { 0x0F } else { 0x00 };
+        let chips = [
+            (0xFE, [(0, 0x40), (7, 0x3E), (8, volume(second))]),
+            (0xFF, [(4, 0x60), (7, 0x3B), (10, volume(first))]),
+        ];
+        for (chip, regs) in chips {
+            controller.write_io(0xFFFD, chip);
+            for (reg, value) in regs {
+                controller.write_io(0xFFFD, reg);
+                controller.write_io(0xBFFD, value);
+            }
+        }
+        controller.wait_internal(20000);
+        core::iter::from_fn(|| controller.mixer.pop())
+            .map(|sample| (sample.left, sample.right))
+            .collect()
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    #[test]
+    fn turbosound_registers_are_read_back_per_chip() {
+        let mut settings = test_settings(ZXMachine::Sinclair128K);
+        settings.sound_enabled = true;
+        settings.ay_enabled = true;
+        settings.turbosound_enabled = true;
+        let mut controller = test_controller(&settings);
+        for (chip, value) in [(0xFF, 0x11), (0xFE, 0x22)] {
+            controller.write_io(0xFFFD, chip);
+            controller.write_io(0xFFFD, 0);
+            controller.write_io(0xBFFD, value);
+        }
+        let registers = |index| {
+            controller
+                .mixer
+                .ay_chip(index)
+                .map(|chip| chip.registers()[0])
+        };
+        assert_eq!(registers(0), Some(0x11));
+        assert_eq!(registers(1), Some(0x22));
+        assert_eq!(registers(2), None);
+
+        settings.turbosound_enabled = false;
+        let controller = test_controller(&settings);
+        assert!(controller.mixer.ay_chip(1).is_none());
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    #[test]
+    fn reset_silences_sound_devices() {
+        let mut settings = test_settings(ZXMachine::Sinclair128K);
+        settings.sound_enabled = true;
+        settings.beeper_enabled = true;
+        settings.sound_channels_enabled = true;
+        settings.ay_enabled = true;
+        settings.turbosound_enabled = true;
+        let mut controller = test_controller(&settings);
+        let beeper_levels = |controller: &mut ZXController<_>| {
+            controller.wait_internal(1000);
+            while controller.mixer.pop().is_some() {}
+            core::iter::from_fn(|| controller.mixer.pop_channels())
+                .map(|channels| channels.beeper.left)
+                .collect::<Vec<_>>()
+        };
+        controller.write_io(0x00FE, 0x10);
+        controller.write_io(0xFFFD, 0xFE);
+        for (reg, value) in [(0, 0x40), (7, 0x3E), (8, 0x0F)] {
+            controller.write_io(0xFFFD, reg);
+            controller.write_io(0xBFFD, value);
+        }
+        assert!(beeper_levels(&mut controller)
+            .iter()
+            .all(|&level| level > 0.0));
+
+        controller.reset();
+        assert!(beeper_levels(&mut controller)
+            .iter()
+            .all(|&level| level == 0.0));
+        for chip in 0..2 {
+            let registers = controller.mixer.ay_chip(chip).unwrap().registers();
+            assert_eq!(registers, [0; 16]);
+        }
+        // First chip is selected again
+        controller.write_io(0xFFFD, 0);
+        controller.write_io(0xBFFD, 0x40);
+        assert_eq!(controller.mixer.ay_chip(0).unwrap().registers()[0], 0x40);
+        assert_eq!(controller.mixer.ay_chip(1).unwrap().registers()[0], 0x00);
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    #[test]
+    fn turbosound_chips_are_mixed_independently() {
+        let silence = turbosound_output(false, false);
+        let first = turbosound_output(true, false);
+        let second = turbosound_output(false, true);
+        let both = turbosound_output(true, true);
+        assert!(silence.iter().all(|&(l, r)| l == 0.0 && r == 0.0));
+        assert_ne!(first, silence);
+        assert_ne!(second, silence);
+        assert_ne!(first, second);
+        // Chips output is summed
+        for ((both, first), second) in both.iter().zip(&first).zip(&second) {
+            assert!((both.0 - (first.0 + second.0)).abs() < 1e-5);
+            assert!((both.1 - (first.1 + second.1)).abs() < 1e-5);
+        }
+    }
+
+    #[cfg(feature = "sound")]
+    #[test]
+    fn dac_output_follows_written_ramp() {
+        use crate::zx::sound::dac::SPECDRUM_DEFAULT_PORT;
+
+        let mut settings = test_settings(ZXMachine::Sinclair48K);
+        settings.sound_enabled = true;
+        settings.specdrum_port = Some(SPECDRUM_DEFAULT_PORT);
+        let mut controller = test_controller(&settings);
+        // Master volume of 0.5 multiplied by the DAC positive half-wave range of 0.5
+        let expected_sample = |value: u8| value as f32 / 255.0 * 0.25;
+
+        let mut last_sample = 0.0;
+        for value in (0..=255u8).step_by(15) {
+            controller.write_io(SPECDRUM_DEFAULT_PORT as u16, value);
+            controller.wait_internal(1000);
+            let mut samples = core::iter::from_fn(|| controller.mixer.pop()).peekable();
+            assert!(samples.peek().is_some());
+            let sample = samples.last().unwrap();
+            assert!((sample.left - expected_sample(value)).abs() < 1e-6);
+            assert!((sample.right - expected_sample(value)).abs() < 1e-6);
+            assert!(sample.left >= last_sample);
+            last_sample = sample.left;
+        }
+
+        // Covox is disabled, therefore its port should not affect the output
+        controller.write_io(0x00FB, 0);
+        controller.wait_internal(1000);
+        let sample = core::iter::from_fn(|| controller.mixer.pop())
+            .last()
+            .unwrap();
+        assert!((sample.left - expected_sample(255)).abs() < 1e-6);
+    }
+
+    #[cfg(feature = "sound")]
+    #[test]
+    fn channel_buffers_separate_beeper_from_ay() {
+        let mut settings = test_settings(ZXMachine::Sinclair48K);
+        settings.sound_enabled = true;
+        settings.beeper_enabled = true;
+        settings.sound_channels_enabled = true;
+        #[cfg(feature = "ay")]
+        {
+            settings.ay_enabled = true;
+        }
+        let mut controller = test_controller(&settings);
+
+        let mut mixed = Vec::new();
+        let mut channels = Vec::new();
+        for period in 0..32 {
+            controller.write_io(0x00FE, if period % 2 == 0 { 0x10 } else { 0x00 });
+            controller.wait_internal(1000);
+            mixed.extend(core::iter::from_fn(|| controller.mixer.pop()));
+            channels.extend(core::iter::from_fn(|| controller.mixer.pop_channels()));
+        }
+
+        assert_eq!(mixed.len(), channels.len());
+        assert!(channels
+            .iter()
+            .all(|c| c.ay.left == 0.0 && c.ay.right == 0.0));
+        // Square wave with positive half-wave only, scaled by the master volume
+        let beeper_levels = channels.iter().map(|c| c.beeper.left);
+        assert!(beeper_levels.clone().any(|level| level == 0.0));
+        assert!(beeper_levels.clone().any(|level| level == 0.25));
+        assert!(beeper_levels
+            .clone()
+            .all(|level| level == 0.0 || level == 0.25));
+        assert!(mixed
+            .iter()
+            .zip(&channels)
+            .all(|(mixed, channels)| mixed.left == channels.beeper.left));
+    }
+
+    /// Returns pressed keys bits (active high) of each keyboard half-row
+    fn pressed_keys(controller: &mut ZXController<crate::zx::testing::TestHost>) -> [u8; 8] {
+        let mut rows = [0u8; 8];
+        for (row, pressed) in rows.iter_mut().enumerate() {
+            let port = u16::from_le_bytes([0xFE, !(1 << row)]);
+            *pressed = !controller.read_io(port) & 0x1F;
+        }
+        rows
+    }
+
+    fn rows(keys: &[ZXKey]) -> [u8; 8] {
+        let mut rows = [0u8; 8];
+        for key in keys {
+            rows[key.row_id()] |= key.mask();
+        }
+        rows
+    }
+
+    /// Runs a frame of NOPs with the given I register value and returns the number of
+    /// displayed bitmap bytes which differ from the screen memory
+    fn snow_corrupted_blocks(i: u8, snow_enabled: bool) -> usize {
+        use crate::{
+            utils::screen::bitmap_line_addr,
+            zx::constants::{ATTR_BASE_REL, ATTR_COLS, CANVAS_HEIGHT},
+        };
+
+        let mut settings = test_settings(ZXMachine::Sinclair48K);
+        settings.ula_snow_enabled = snow_enabled;
+        let mut controller = test_controller(&settings);
+        // Each bitmap byte holds low byte of its address; blue ink on white paper
+        for addr in 0x4000..0x4000 + ATTR_BASE_REL {
+            controller.write_internal(addr, addr as u8);
+        }
+        for addr in 0x4000 + ATTR_BASE_REL..0x5B00 {
+            controller.write_internal(addr, 0x39);
+        }
+
+        // RAM is filled with zeros, therefore CPU executes NOPs
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(PROGRAM_ADDR);
+        cpu.regs.set_i(i);
+        while controller.passed_frames == 0 {
+            cpu.emulate(&mut controller);
+        }
+
+        let frame = controller.screen.frame_buffer();
+        let mut corrupted = 0;
+        for line in 0..CANVAS_HEIGHT {
+            for col in 0..ATTR_COLS {
+                let displayed = (0..8).fold(0u8, |byte, pixel| {
+                    let ink = frame.color(col * 8 + pixel, line) == ZXColor::Blue as u8;
+                    (byte << 1) | ink as u8
+                });
+                let addr = bitmap_line_addr(line) + col as u16;
+                assert_eq!(controller.memory.read(addr), addr as u8);
+                if displayed != addr as u8 {
+                    corrupted += 1;
+                }
+            }
+        }
+        corrupted
+    }
+
+    #[test]
+    fn snow_corrupts_displayed_bytes_when_i_is_contended() {
+        assert!(snow_corrupted_blocks(0x40, true) > 0);
+        assert_eq!(snow_corrupted_blocks(0x3F, true), 0);
+        assert_eq!(snow_corrupted_blocks(0x80, true), 0);
+        assert_eq!(snow_corrupted_blocks(0x40, false), 0);
+    }
+
+    #[test]
+    fn frame_skip_renders_one_of_n_frames() {
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        let clocks_frame = ZXMachine::Sinclair48K.specs().clocks_frame;
+        controller.set_frame_skip(3);
+        // Blue ink on white paper in the first character cell
+        controller.write_internal(0x5800, 0x39);
+
+        let mut rendered = Vec::new();
+        for frame in 0..6 {
+            // Draw frame index in the first bitmap byte
+            controller.write_internal(0x4000, frame);
+            controller.wait_internal(clocks_frame);
+            assert_eq!(controller.frames_count(), frame as usize + 1);
+            assert_eq!(controller.frame_clocks(), 0);
+
+            let buffer = controller.screen.frame_buffer();
+            let displayed = (0..8).fold(0u8, |byte, pixel| {
+                let ink = buffer.color(pixel, 0) == ZXColor::Blue as u8;
+                (byte << 1) | ink as u8
+            });
+            rendered.push(displayed == frame);
+        }
+        assert_eq!(rendered, [true, false, false, true, false, false]);
+    }
+
+    #[test]
+    fn typed_text_is_visible_in_keyboard_matrix() {
+        use ZXKey::*;
+
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        let clocks_frame = ZXMachine::Sinclair48K.specs().clocks_frame;
+        // Live user input should be kept intact
+        controller.send_key(Q, true);
+        for stroke in "LOAD \"\"".chars().filter_map(KeyStroke::from_char) {
+            controller.typer.push(stroke);
+        }
+
+        let mut frames = Vec::new();
+        while !controller.typer.is_idle() {
+            controller.wait_internal(clocks_frame);
+            frames.push(pressed_keys(&mut controller));
+        }
+
+        // Each key is held for 2 frames and then released for 5 frames
+        let expected = [[Shift, L, Q], [Shift, O, Q], [Shift, A, Q], [Shift, D, Q]]
+            .iter()
+            .map(|keys| rows(keys))
+            .chain([rows(&[Space, Q])])
+            .chain([rows(&[SymShift, P, Q]); 2])
+            .flat_map(|pressed| [[pressed; 2].as_slice(), &[rows(&[Q]); 5]].concat())
+            // Typer becomes idle on the frame after the last release
+            .chain([rows(&[Q])])
+            .collect::<Vec<_>>();
+        assert_eq!(frames, expected);
+    }
+
+    #[test]
+    fn typed_symbol_presses_symbol_shift_with_key() {
+        use ZXKey::*;
+
+        let mut controller = test_controller(&test_settings(ZXMachine::Sinclair48K));
+        let clocks_frame = ZXMachine::Sinclair48K.specs().clocks_frame;
+        controller.typer.push(KeyStroke::from_char('+').unwrap());
+
+        let mut frames = Vec::new();
+        while !controller.typer.is_idle() {
+            controller.wait_internal(clocks_frame);
+            frames.push(pressed_keys(&mut controller));
+        }
+        // Symbol Shift is pressed and released together with the key, so it is not leaked
+        // to the following strokes
+        assert_eq!(frames[..2], [rows(&[SymShift, K]); 2]);
+        assert!(frames[2..].iter().all(|pressed| *pressed == [0; 8]));
+    }
+}
@@ -8,6 +8,16 @@ bitflags! {
         const TAPE_FAST_LOAD_TRIGGER_DETECTED = 0b00000001;
         /// Set when PC breakpoint is reached
         const PC_BREAKPOINT = 0b00000010;
+        /// Set when CPU accepts maskable interrupt
+        const INTERRUPT_ACCEPTED = 0b00000100;
+        /// Set when ROM tape loading routine is entered
+        const TAPE_LOAD_ROUTINE_ENTERED = 0b00001000;
+        /// Set when ROM tape loading routine is exited
+        const TAPE_LOAD_ROUTINE_EXITED = 0b00010000;
+        /// Set when ROM startup RAM test is reached and fast boot is enabled
+        const FAST_BOOT_TRIGGER_DETECTED = 0b00100000;
+        /// Set when CPU accesses port matching the I/O breakpoint
+        const IO_BREAKPOINT = 0b01000000;
     }
 }
 
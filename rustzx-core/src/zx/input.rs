@@ -0,0 +1,64 @@
+//! Input events scheduled for the specific frames, e.g. for the macro or demo playback. Unlike
+//! immediate `send_*` calls, scheduled input does not depend on the host timings and is
+//! reproducible between runs
+use crate::zx::{
+    joy::{
+        fuller::FullerKey,
+        kempston::KempstonKey,
+        sinclair::{SinclairJoyNum, SinclairKey},
+        JoyState,
+    },
+    keys::{CompoundKey, ZXKey},
+};
+use alloc::collections::VecDeque;
+
+/// Input event which can be scheduled via `Emulator::schedule_input`
+#[derive(Clone, Copy)]
+pub enum InputEvent {
+    Key {
+        key: ZXKey,
+        pressed: bool,
+    },
+    CompoundKey {
+        key: CompoundKey,
+        pressed: bool,
+    },
+    KempstonKey {
+        key: KempstonKey,
+        pressed: bool,
+    },
+    FullerKey {
+        key: FullerKey,
+        pressed: bool,
+    },
+    SinclairKey {
+        num: SinclairJoyNum,
+        key: SinclairKey,
+        pressed: bool,
+    },
+    /// Logical joystick state, which is sent to the joystick selected via
+    /// `Emulator::set_joystick_kind`
+    Joystick(JoyState),
+}
+
+/// Queue of the input events ordered by their frame numbers. Events scheduled for the same
+/// frame are applied in the order of scheduling
+#[derive(Default)]
+pub(crate) struct InputQueue {
+    events: VecDeque<(u64, InputEvent)>,
+}
+
+impl InputQueue {
+    pub fn push(&mut self, frame: u64, event: InputEvent) {
+        let pos = self.events.partition_point(|(f, _)| *f <= frame);
+        self.events.insert(pos, (frame, event));
+    }
+
+    /// Removes and returns the next event which should be applied on or before `frame`
+    pub fn pop_due(&mut self, frame: u64) -> Option<InputEvent> {
+        if self.events.front()?.0 > frame {
+            return None;
+        }
+        self.events.pop_front().map(|(_, event)| event)
+    }
+}
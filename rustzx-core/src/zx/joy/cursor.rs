@@ -0,0 +1,13 @@
+//! Cursor (Protek/AGF) joystick, which emulates presses of the cursor keys `5`-`8` and `0`
+use crate::zx::{joy::JoyState, keys::ZXKey};
+
+/// Returns keyboard keys and their states for the given joystick state
+pub(crate) fn cursor_keys(state: JoyState) -> [(ZXKey, bool); 5] {
+    [
+        (ZXKey::N5, state.left),
+        (ZXKey::N6, state.down),
+        (ZXKey::N7, state.up),
+        (ZXKey::N8, state.right),
+        (ZXKey::N0, state.fire),
+    ]
+}
@@ -0,0 +1,39 @@
+/// Fuller Box AY register select (write) and register value (read) port
+pub(crate) const FULLER_AY_REG_PORT: u16 = 0x3F;
+/// Fuller Box AY register value write port
+pub(crate) const FULLER_AY_DATA_PORT: u16 = 0x5F;
+/// Fuller joystick port
+pub(crate) const FULLER_JOY_PORT: u16 = 0x7F;
+
+/// Fuller joystick key type. Port bit encoded in enum values
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Clone, Copy)]
+pub enum FullerKey {
+    Up = 0x01,
+    Down = 0x02,
+    Left = 0x04,
+    Right = 0x08,
+    Fire = 0x80,
+}
+
+/// Fuller Box joystick, which is read from port `0x7F`
+#[derive(Default)]
+pub(crate) struct FullerJoy {
+    state: u8,
+}
+
+impl FullerJoy {
+    /// Simulates key press/release
+    pub fn key(&mut self, key: FullerKey, state: bool) {
+        if state {
+            self.state |= key as u8;
+        } else {
+            self.state &= !(key as u8);
+        }
+    }
+
+    /// Reads joy value. Pressed keys are active-low
+    pub fn read(&self) -> u8 {
+        !self.state
+    }
+}
@@ -1,2 +1,25 @@
+pub(crate) mod cursor;
+pub mod fuller;
 pub mod kempston;
 pub mod sinclair;
+
+use sinclair::SinclairJoyNum;
+
+/// Joystick interface which receives input passed to `Emulator::joystick_input`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoystickKind {
+    Kempston,
+    Fuller,
+    Sinclair(SinclairJoyNum),
+    Cursor,
+}
+
+/// Logical joystick state, independent of the joystick interface
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct JoyState {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub fire: bool,
+}
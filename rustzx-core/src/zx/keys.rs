@@ -1,4 +1,16 @@
 //! Module with hardware key port\masks
+use crate::{error::KeyMapError, Result};
+
+/// Keyboard circuit revision of the 48K board, which determines bit 6 (EAR input) value of
+/// the port `0xFE` when tape is not playing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyboardIssue {
+    /// Bit 6 is set when either EAR (bit 4) or MIC (bit 3) was set by the last `0xFE` write
+    Issue2,
+    /// Bit 6 is set only when EAR (bit 4) was set by the last `0xFE` write
+    #[default]
+    Issue3,
+}
 
 /// Struct, which contains mast and port of key
 #[rustfmt::skip]
@@ -23,6 +35,48 @@ pub enum ZXKey {
     Space, SymShift, M, N, B,
 }
 
+const KEYS_PER_ROW: usize = 5;
+const ROWS_COUNT: usize = 8;
+const KEYS_COUNT: usize = ROWS_COUNT * KEYS_PER_ROW;
+
+/// Table which maps keys sent via `Emulator::send_key` and `Emulator::send_compound_key` to
+/// the keyboard matrix positions, e.g. to adapt the non-QWERTY host layouts or remap the keys
+/// for the specific game. Sinclair joystick and typed text keep the original positions, as
+/// they emulate the physical keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    // (half-row, bit) position of each key, indexed by the original matrix position of the key
+    positions: [(usize, u8); KEYS_COUNT],
+}
+
+impl Default for KeyMap {
+    /// Original ZX Spectrum layout, each key is mapped to its own matrix position
+    fn default() -> Self {
+        let mut positions = [(0, 0); KEYS_COUNT];
+        for (index, position) in positions.iter_mut().enumerate() {
+            *position = (index / KEYS_PER_ROW, (index % KEYS_PER_ROW) as u8);
+        }
+        Self { positions }
+    }
+}
+
+impl KeyMap {
+    /// Maps `key` to the matrix `half_row` (`0` for port `0xFEFE` to `7` for port `0x7FFE`)
+    /// and `bit` (`0..5`) of it. Map is left unchanged if the position is out of the matrix
+    pub fn set(&mut self, key: ZXKey, half_row: usize, bit: u8) -> Result<()> {
+        if half_row >= ROWS_COUNT || bit as usize >= KEYS_PER_ROW {
+            return Err(KeyMapError::InvalidPosition { half_row, bit }.into());
+        }
+        self.positions[key.matrix_index()] = (half_row, bit);
+        Ok(())
+    }
+
+    /// Returns (`half_row`, `bit`) matrix position of the `key`
+    pub fn position(&self, key: ZXKey) -> (usize, u8) {
+        self.positions[key.matrix_index()]
+    }
+}
+
 #[cfg_attr(feature = "strum", derive(strum::EnumIter))]
 #[derive(Debug, Clone, Copy)]
 pub enum CompoundKey {
@@ -95,6 +149,11 @@ impl ZXKey {
         }
     }
 
+    /// Index of the key in the keyboard matrix, ordered by half-row and bit
+    fn matrix_index(self) -> usize {
+        self.row_id() * KEYS_PER_ROW + self.mask().trailing_zeros() as usize
+    }
+
     fn half_port(self) -> u8 {
         use ZXKey::*;
         match self {
@@ -42,11 +42,52 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    /// Pentagon 128 Specs. Pentagon has no memory and IO contention and its frame
+    /// consists of 320 lines of 224 clocks
+    pub static ref SPECS_PENTAGON_128: ZXSpecs = {
+        ZXSpecsBuilder::new()
+            .freq_cpu(3_500_000)
+            .clocks_first_pixel(17988)
+            .clocks_ula_read_shift(2)
+            .clocks_ula_beam_shift(1)
+            .clocks_row(36, 128, 28, 32)
+            .lines(64, 192, 48, 16)
+            .contention([0, 0, 0, 0, 0, 0, 0, 0], 1)
+            .interrupt_length(36)
+            .rom_pages(2)
+            .build()
+    };
+}
+
 /// Machine type
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ZXMachine {
     Sinclair48K,
     Sinclair128K,
+    Pentagon128,
+}
+
+/// ULA timings revision. On late timings machines ULA lags behind the CPU clock by one
+/// clock, so memory and IO contention, floating bus and screen rendering start one clock later
+/// relative to the interrupt
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ZXTimings {
+    #[default]
+    Early,
+    Late,
+}
+
+impl ZXTimings {
+    /// Returns ULA delay in clocks relative to the early timings
+    pub fn offset(self) -> usize {
+        match self {
+            ZXTimings::Early => 0,
+            ZXTimings::Late => 1,
+        }
+    }
 }
 
 impl ZXMachine {
@@ -55,10 +96,12 @@ impl ZXMachine {
         match self {
             ZXMachine::Sinclair48K => &SPECS_48K,
             ZXMachine::Sinclair128K => &SPECS_128K,
+            ZXMachine::Pentagon128 => &SPECS_PENTAGON_128,
         }
     }
 
-    /// Returns contention during specified time
+    /// Returns contention during specified time for the machine with early timings, for late
+    /// timings `clocks` should be shifted by [ZXTimings::offset]
     pub fn contention_clocks(self, clocks: usize) -> usize {
         let specs = self.specs();
         if (clocks < (specs.clocks_first_pixel - 1))
@@ -73,6 +116,24 @@ impl ZXMachine {
         return self.specs().contention_pattern[clocks_trough_line % 8];
     }
 
+    /// Returns beam position (`line`, `pixel`) at the given frame `clocks`. Lines are counted
+    /// from the frame start, so the first line of the screen paper is the first line after
+    /// the top border. Pixels are counted from the left edge of the paper, two pixels per
+    /// clock: `0..256` is the paper, then right border, horizontal retrace and left border of
+    /// the next line follow
+    pub fn raster_position(self, clocks: usize) -> (u16, u16) {
+        let specs = self.specs();
+        let line_clocks = specs.clocks_line as isize;
+        let lines = (specs.clocks_frame / specs.clocks_line) as isize;
+        // First screen line is the line which contains the most of the first paper row clocks
+        let first_screen_line = (specs.clocks_first_pixel as isize + line_clocks / 2) / line_clocks;
+        let origin = specs.clocks_first_pixel as isize - first_screen_line * line_clocks;
+        let relative = (clocks % specs.clocks_frame) as isize - origin;
+        let line = relative.div_euclid(line_clocks).rem_euclid(lines);
+        let pixel = relative.rem_euclid(line_clocks) * 2;
+        (line as u16, pixel as u16)
+    }
+
     /// Checks port contention on machine
     pub fn port_is_contended(self, port: u16) -> bool {
         match self {
@@ -80,6 +141,7 @@ impl ZXMachine {
                 // every even port
                 (port & 0x0001) == 0
             }
+            ZXMachine::Pentagon128 => false,
         }
     }
 
@@ -91,6 +153,47 @@ impl ZXMachine {
                 let contended_pages = [1, 3, 5, 7];
                 contended_pages.iter().any(|&x| x == page)
             }
+            ZXMachine::Pentagon128 => false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pentagon_frame_length() {
+        let specs = ZXMachine::Pentagon128.specs();
+        assert_eq!(specs.clocks_line, 224);
+        assert_eq!(specs.lines_all + specs.lines_vsync, 320);
+        assert_eq!(specs.clocks_frame, 71680);
+    }
+
+    #[test]
+    fn raster_position_matches_screen_geometry() {
+        let machine = ZXMachine::Sinclair48K;
+        assert_eq!(machine.raster_position(0), (0, 0));
+        // Top-left paper pixel
+        assert_eq!(machine.raster_position(14336), (64, 0));
+        assert_eq!(machine.raster_position(14336 + 10 * 224 + 5), (74, 10));
+        // Right border starts after 128 clocks of the paper
+        assert_eq!(machine.raster_position(14336 + 128), (64, 256));
+        assert_eq!(machine.raster_position(69887), (311, 446));
+
+        assert_eq!(ZXMachine::Sinclair128K.raster_position(14362), (63, 0));
+        assert_eq!(ZXMachine::Sinclair128K.raster_position(0), (0, 4));
+        assert_eq!(ZXMachine::Pentagon128.raster_position(17988), (80, 0));
+    }
+
+    #[test]
+    fn pentagon_has_no_contention() {
+        let machine = ZXMachine::Pentagon128;
+        let specs = machine.specs();
+        assert!((0..specs.clocks_frame).all(|clocks| machine.contention_clocks(clocks) == 0));
+        assert!((0..8).all(|page| !machine.bank_is_contended(page)));
+        assert!((0..=0xFFFF).all(|port| !machine.port_is_contended(port)));
+        // Sanity check for the contended machine
+        assert!(ZXMachine::Sinclair128K.bank_is_contended(5));
+    }
+}
@@ -28,12 +28,15 @@ pub enum RamType {
 
 // Page info and type
 #[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Page {
     Ram(u8),
     Rom(u8),
 }
 
 // Memory struct
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ZXMemory {
     rom: Vec<u8>,
     ram: Vec<u8>,
@@ -150,6 +153,16 @@ impl ZXMemory {
         &self.ram[shift..shift + PAGE_SIZE]
     }
 
+    /// Returns all ram pages as a single slice
+    pub fn ram_data(&self) -> &[u8] {
+        &self.ram
+    }
+
+    /// Returns all ram pages as a single mutable slice
+    pub fn ram_data_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
     /// Calculates [Page] and local offset from memory address
     fn paged_address(&self, addr: u16) -> (Page, usize) {
         let page = self.map[(addr as usize) / PAGE_SIZE];
@@ -6,8 +6,12 @@ pub(crate) mod memory;
 #[cfg(feature = "embedded-roms")]
 pub(crate) mod roms;
 pub(crate) mod tape;
+#[cfg(test)]
+pub(crate) mod testing;
+pub(crate) mod typing;
 
 pub mod constants;
+pub mod input;
 pub mod joy;
 pub mod keys;
 pub mod machine;
@@ -3,6 +3,10 @@ use aym::{AyMode, AymBackend, AymPrecise, SoundChip};
 
 /// AY chip runs on the same frequency on 128K, 2+, 3+
 const AY_FREQ: usize = 1773400;
+/// Value written to the register select port which selects the first Turbosound chip
+pub(crate) const TURBOSOUND_SELECT_FIRST: u8 = 0xFF;
+/// Value written to the register select port which selects the second Turbosound chip
+pub(crate) const TURBOSOUND_SELECT_SECOND: u8 = 0xFE;
 
 /// AY output mode
 #[derive(Clone, Copy)]
@@ -37,6 +41,15 @@ impl ZXAyChip {
         }
     }
 
+    /// Clears all registers as the chip RESET line does, which silences all channels
+    pub fn reset(&mut self) {
+        for reg in 0..self.regs.len() {
+            self.select_reg(reg as u8);
+            self.write(0);
+        }
+        self.current_reg = 0;
+    }
+
     pub fn select_reg(&mut self, reg: u8) {
         // AY chip have only 16 regs [0..=15]
         self.current_reg = (reg & 0x0F) as usize;
@@ -51,6 +64,16 @@ impl ZXAyChip {
     pub fn read(&self) -> u8 {
         self.regs[self.current_reg]
     }
+
+    /// Returns last written values of all registers
+    pub fn registers(&self) -> [u8; 16] {
+        self.regs
+    }
+
+    /// Returns currently selected register
+    pub fn selected_reg(&self) -> u8 {
+        self.current_reg as u8
+    }
 }
 
 impl SampleGenerator<f64> for ZXAyChip {
@@ -0,0 +1,29 @@
+use crate::zx::sound::sample::{SampleGenerator, SoundSample};
+
+/// SpecDrum default port
+pub const SPECDRUM_DEFAULT_PORT: u8 = 0xDF;
+/// Covox default port
+pub const COVOX_DEFAULT_PORT: u8 = 0xFB;
+
+/// Simple 8-bit unsigned DAC (SpecDrum, Covox)
+#[derive(Default)]
+pub(crate) struct ZXDac {
+    value: u8,
+}
+
+impl ZXDac {
+    /// Changes DAC output value
+    pub fn write(&mut self, value: u8) {
+        self.value = value;
+    }
+}
+
+impl SampleGenerator<f64> for ZXDac {
+    fn gen_sample(&mut self) -> SoundSample<f64> {
+        // Same positive half-wave range as beeper produces, see `ZXBeeper`
+        const DAC_SAMPLE_FACTOR: f64 = 0.5;
+
+        let sample = self.value as f64 / u8::MAX as f64 * DAC_SAMPLE_FACTOR;
+        SoundSample::new(sample, sample)
+    }
+}
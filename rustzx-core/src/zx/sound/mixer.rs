@@ -3,7 +3,8 @@ use crate::zx::{
     constants::FPS,
     sound::{
         beeper::ZXBeeper,
-        sample::{SampleGenerator, SoundSample},
+        dac::ZXDac,
+        sample::{ChannelSamples, SampleGenerator, SoundSample},
     },
 };
 
@@ -21,14 +22,30 @@ pub(crate) struct ZXMixer {
     /// direct access to AY device
     #[cfg(feature = "ay")]
     pub ay: ZXAyChip,
+    /// Second AY chip of the Turbosound, `None` if disabled
+    #[cfg(feature = "ay")]
+    pub turbosound: Option<ZXAyChip>,
+    #[cfg(feature = "ay")]
+    turbosound_selected: bool,
+    /// SpecDrum DAC, `None` if disabled
+    pub specdrum: Option<ZXDac>,
+    /// Covox DAC, `None` if disabled
+    pub covox: Option<ZXDac>,
     ring_buffer: VecDeque<SoundSample<f32>>,
+    /// Per-source samples, `None` if per-channel output is disabled
+    channels_buffer: Option<VecDeque<ChannelSamples>>,
     last_pos: usize,
     last_sample: SoundSample<f32>,
+    last_channels: ChannelSamples,
     master_volume: f64,
     #[cfg(feature = "ay")]
     use_ay: bool,
     use_beeper: bool,
     sample_rate: usize,
+    /// Count of samples in the current frame
+    frame_samples: usize,
+    /// Accumulated fractional part of the samples count per frame, in `1 / FPS` samples
+    samples_remainder: usize,
 }
 
 impl ZXMixer {
@@ -36,25 +53,45 @@ impl ZXMixer {
     /// # Arguments
     /// - `use_beeper` - process beeper or not
     /// - `use_ay` - process ay chip or not
+    /// - `use_turbosound` - add second AY chip or not
+    /// - `use_specdrum`, `use_covox` - process DACs or not
+    /// - `use_channels` - fill per-source buffers in addition to the mixed one
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         use_beeper: bool,
         #[cfg(feature = "ay")] use_ay: bool,
         #[cfg(feature = "ay")] ay_mode: ZXAYMode,
+        #[cfg(feature = "ay")] use_turbosound: bool,
+        use_specdrum: bool,
+        use_covox: bool,
+        use_channels: bool,
         sample_rate: usize,
     ) -> ZXMixer {
-        ZXMixer {
+        let mut mixer = ZXMixer {
             beeper: ZXBeeper::default(),
             #[cfg(feature = "ay")]
             ay: ZXAyChip::new(sample_rate, ay_mode),
+            #[cfg(feature = "ay")]
+            turbosound: use_turbosound.then(|| ZXAyChip::new(sample_rate, ay_mode)),
+            #[cfg(feature = "ay")]
+            turbosound_selected: false,
+            specdrum: use_specdrum.then(ZXDac::default),
+            covox: use_covox.then(ZXDac::default),
             ring_buffer: VecDeque::with_capacity(sample_rate),
+            channels_buffer: use_channels.then(|| VecDeque::with_capacity(sample_rate)),
             last_pos: 0,
             last_sample: SoundSample::new(0.0, 0.0),
+            last_channels: ChannelSamples::default(),
             master_volume: 0.5,
             #[cfg(feature = "ay")]
             use_ay,
             use_beeper,
             sample_rate,
-        }
+            frame_samples: 0,
+            samples_remainder: 0,
+        };
+        mixer.update_frame_samples();
+        mixer
     }
 
     /// changes volume
@@ -67,7 +104,7 @@ impl ZXMixer {
     /// Updates internal buffer of mixer and fills it with new samples
     pub fn process(&mut self, current_time: f64) {
         // buffer overflow
-        if self.ring_buffer.len() >= self.samples_per_frame() {
+        if self.is_buffer_full() {
             return;
         }
         // so at this moment we need to get new samples from devices
@@ -80,42 +117,146 @@ impl ZXMixer {
         self.last_pos = curr_pos;
         // fill buffer with new samples
         for _ in 0..sample_count {
-            let sample = self.gen_sample();
-            self.ring_buffer.push_back(sample);
+            self.gen_sample();
+            self.push_last_sample();
         }
     }
 
     /// fills buffer to eng on new frame
     pub fn new_frame(&mut self) {
-        if self.ring_buffer.len() < self.samples_per_frame() {
-            for _ in self.ring_buffer.len()..self.samples_per_frame() {
-                self.ring_buffer.push_back(self.last_sample);
-            }
+        while !self.is_buffer_full() {
+            self.push_last_sample();
         }
         self.last_pos = 0;
+        self.update_frame_samples();
+    }
+
+    /// Selects samples count of the next frame. Fractional part of the samples count is
+    /// carried over to the following frames, so the long-run sample rate matches the
+    /// configured one regardless of the frame length jitter
+    fn update_frame_samples(&mut self) {
+        self.samples_remainder += self.sample_rate % FPS;
+        self.frame_samples = self.sample_rate / FPS;
+        if self.samples_remainder >= FPS {
+            self.samples_remainder -= FPS;
+            self.frame_samples += 1;
+        }
     }
 
     pub fn pop(&mut self) -> Option<SoundSample<f32>> {
         self.ring_buffer.pop_front()
     }
 
-    fn gen_sample(&mut self) -> SoundSample<f32> {
-        let mut master_float = if self.use_beeper {
+    /// Resets state of the sound devices on machine reset. Already generated samples and
+    /// sample timing are kept, so the output stays continuous
+    pub fn reset(&mut self) {
+        self.beeper = ZXBeeper::default();
+        #[cfg(feature = "ay")]
+        {
+            self.ay.reset();
+            if let Some(chip) = &mut self.turbosound {
+                chip.reset();
+            }
+            self.turbosound_selected = false;
+        }
+        for dac in [&mut self.specdrum, &mut self.covox].into_iter().flatten() {
+            *dac = ZXDac::default();
+        }
+    }
+
+    /// Selects Turbosound chip for the subsequent register accesses, ignored if Turbosound
+    /// is disabled
+    #[cfg(feature = "ay")]
+    pub fn select_turbosound_chip(&mut self, second: bool) {
+        self.turbosound_selected = second && self.turbosound.is_some();
+    }
+
+    /// Returns AY chip which is currently selected for the register access
+    #[cfg(feature = "ay")]
+    pub fn active_ay(&mut self) -> &mut ZXAyChip {
+        match &mut self.turbosound {
+            Some(chip) if self.turbosound_selected => chip,
+            _ => &mut self.ay,
+        }
+    }
+
+    /// Returns AY chip by its index, `0` is the first chip and `1` is the second Turbosound
+    /// chip. `None` is returned if there is no such chip
+    #[cfg(feature = "ay")]
+    pub fn ay_chip(&self, index: usize) -> Option<&ZXAyChip> {
+        match index {
+            0 => Some(&self.ay),
+            1 => self.turbosound.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns next per-source samples, `None` if buffer is empty or per-channel output is
+    /// disabled
+    pub fn pop_channels(&mut self) -> Option<ChannelSamples> {
+        self.channels_buffer.as_mut()?.pop_front()
+    }
+
+    /// Returns true if none of the buffers can accept more samples for the current frame. Each
+    /// buffer is filled independently, so the host may drain only one of them
+    fn is_buffer_full(&self) -> bool {
+        let limit = self.samples_per_frame();
+        self.ring_buffer.len() >= limit
+            && self
+                .channels_buffer
+                .as_ref()
+                .is_none_or(|buffer| buffer.len() >= limit)
+    }
+
+    fn push_last_sample(&mut self) {
+        let limit = self.samples_per_frame();
+        if self.ring_buffer.len() < limit {
+            self.ring_buffer.push_back(self.last_sample);
+        }
+        if let Some(buffer) = self.channels_buffer.as_mut().filter(|b| b.len() < limit) {
+            buffer.push_back(self.last_channels);
+        }
+    }
+
+    /// Generates next samples of all sources and stores them as the last ones
+    fn gen_sample(&mut self) {
+        let silence = || SoundSample::new(0.0, 0.0);
+        let beeper = if self.use_beeper {
             self.beeper.gen_sample()
         } else {
-            SoundSample::new(0.0, 0.0)
+            silence()
         };
         #[cfg(feature = "ay")]
-        if self.use_ay {
-            master_float.mix(&self.ay.gen_sample());
+        let ay = if self.use_ay {
+            let mut sample = self.ay.gen_sample();
+            if let Some(chip) = &mut self.turbosound {
+                sample.mix(&chip.gen_sample());
+            }
+            sample
+        } else {
+            silence()
+        };
+        #[cfg(not(feature = "ay"))]
+        let ay = silence();
+        let mut dac = silence();
+        for device in [&mut self.specdrum, &mut self.covox].into_iter().flatten() {
+            dac.mix(&device.gen_sample());
         }
-        let master = master_float.mul_eq(self.master_volume).into_f32();
-        self.last_sample = master;
-        master
+
+        let mut master_float = beeper;
+        master_float.mix(&ay).mix(&dac);
+        let volume = self.master_volume;
+        let scale = |mut sample: SoundSample<f64>| sample.mul_eq(volume).into_f32();
+        self.last_sample = scale(master_float);
+        self.last_channels = ChannelSamples {
+            beeper: scale(beeper),
+            ay: scale(ay),
+            dac: scale(dac),
+        };
     }
 
     fn samples_per_frame(&self) -> usize {
-        self.sample_rate / FPS
+        self.frame_samples
     }
 
     fn sample_count_for_frame_fraction(&self, fraction: f64) -> usize {
@@ -125,3 +266,42 @@ impl ZXMixer {
         (self.samples_per_frame() as f64 * fraction) as usize
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_mixer(sample_rate: usize) -> ZXMixer {
+        ZXMixer::new(
+            true,
+            #[cfg(feature = "ay")]
+            false,
+            #[cfg(feature = "ay")]
+            ZXAYMode::Mono,
+            #[cfg(feature = "ay")]
+            false,
+            false,
+            false,
+            false,
+            sample_rate,
+        )
+    }
+
+    #[test]
+    fn frame_samples_count_carries_fraction() {
+        for sample_rate in [44100, 48000, 11025, 22051] {
+            let mut mixer = test_mixer(sample_rate);
+            let mut total = 0;
+            for _ in 0..FPS * 10 {
+                mixer.process(0.3);
+                // Frame end is usually overshot by a few clocks
+                mixer.process(1.01);
+                mixer.new_frame();
+                let frame_samples = core::iter::from_fn(|| mixer.pop()).count();
+                assert!(frame_samples.abs_diff(sample_rate / FPS) <= 1);
+                total += frame_samples;
+            }
+            assert_eq!(total, sample_rate * 10);
+        }
+    }
+}
@@ -1,6 +1,7 @@
-//! Module implements emulation of sound chip AY, Spectrum Beeper and Mixer
+//! Module implements emulation of sound chip AY, Spectrum Beeper, DACs and Mixer
 #[cfg(feature = "ay")]
 pub mod ay;
+pub mod dac;
 pub mod sample;
 
 pub(crate) mod beeper;
@@ -65,6 +65,27 @@ impl SoundSample<f64> {
     }
 }
 
+/// Samples of the separate sound sources, produced in lockstep with the mixed output when
+/// per-channel output is enabled. Sources which are disabled produce silence
+#[derive(Clone, Copy)]
+pub struct ChannelSamples {
+    pub beeper: SoundSample<f32>,
+    pub ay: SoundSample<f32>,
+    /// Combined output of SpecDrum and Covox DACs
+    pub dac: SoundSample<f32>,
+}
+
+impl Default for ChannelSamples {
+    fn default() -> Self {
+        let silence = SoundSample::new(0.0, 0.0);
+        Self {
+            beeper: silence,
+            ay: silence,
+            dac: silence,
+        }
+    }
+}
+
 /// Trait which signals that structure can generate SoundSamples
 pub(crate) trait SampleGenerator<T>
 where
@@ -0,0 +1,317 @@
+//! CSW (Compressed Square Wave) tape format support. Pulse data decoder is independent of
+//! the tape playback, so it can be reused by the tooling. Whole file is decoded to the pulses
+//! list at load time and played from memory, fast loading is not supported. Z-RLE
+//! compressed files require `csw-z-rle` feature.
+//!
+//! Format specification: <https://ramsoft.bbk.org.omegahg.com/csw.html>
+use crate::{
+    error::TapeLoadError,
+    host::LoadableAsset,
+    zx::{
+        machine::ZXMachine,
+        tape::{InstantTape, TapeImpl, TransportStatus},
+    },
+    Result,
+};
+use alloc::{vec, vec::Vec};
+
+const SIGNATURE: &[u8] = b"Compressed Square Wave\x1A";
+// Offsets of the header fields which follow the signature and version
+const V1_DATA_OFFSET: usize = 0x20;
+const V2_EXTENSION_OFFSET: usize = 0x34;
+
+/// Run-length encoded pulses
+pub const COMPRESSION_RLE: u8 = 0x01;
+/// Run-length encoded pulses, compressed with zlib
+pub const COMPRESSION_Z_RLE: u8 = 0x02;
+
+/// Decodes CSW pulse `data` with the given `compression` type to the list of pulse lengths
+/// in samples. Pulse lengths longer than 255 samples are stored as zero byte followed by
+/// 32-bit length
+pub fn decode_csw(data: &[u8], compression: u8) -> Result<Vec<u32>> {
+    match compression {
+        COMPRESSION_RLE => decode_rle(data),
+        #[cfg(feature = "csw-z-rle")]
+        COMPRESSION_Z_RLE => {
+            let rle = miniz_oxide::inflate::decompress_to_vec_zlib(data)
+                .map_err(|_| TapeLoadError::InvalidCswData)?;
+            decode_rle(&rle)
+        }
+        #[cfg(not(feature = "csw-z-rle"))]
+        COMPRESSION_Z_RLE => Err(TapeLoadError::CswZRleDisabled.into()),
+        compression => Err(TapeLoadError::UnsupportedCswCompression { compression }.into()),
+    }
+}
+
+fn decode_rle(data: &[u8]) -> Result<Vec<u32>> {
+    let mut pulses = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+    while let Some(&byte) = bytes.next() {
+        let length = if byte != 0 {
+            byte as u32
+        } else {
+            let mut length = [0u8; 4];
+            for value in length.iter_mut() {
+                *value = *bytes.next().ok_or(TapeLoadError::InvalidCswData)?;
+            }
+            u32::from_le_bytes(length)
+        };
+        pulses.push(length);
+    }
+    Ok(pulses)
+}
+
+pub struct Csw {
+    // Recording is played as a single block of the instant tape
+    tape: InstantTape,
+}
+
+impl Csw {
+    /// Loads `.csw` file and expands its pulses to the edge list. Both CSW v1 (RLE) and v2
+    /// (RLE or Z-RLE) files are supported. Whole recording is treated as a single block
+    pub fn from_asset(mut asset: impl LoadableAsset, machine: ZXMachine) -> Result<Self> {
+        let mut file = Vec::new();
+        asset.read_to_end(&mut file)?;
+
+        let header = parse_header(&file)?;
+        let samples = decode_csw(&file[header.data_offset..], header.compression)?;
+
+        // Pulses are converted from the accumulated sample count, so the rounding error does
+        // not drift over the long recordings. Pulses which are shorter than a single clock
+        // are dropped, but still switch the level of the following pulses
+        let freq_cpu = machine.specs().freq_cpu as u64;
+        let mut total_samples = 0u64;
+        let mut total_clocks = 0u64;
+        let mut level = header.initial_level;
+        let mut edges = Vec::with_capacity(samples.len());
+        for length in samples {
+            total_samples += length as u64;
+            let clocks = total_samples * freq_cpu / header.sample_rate as u64;
+            let pulse = (clocks - total_clocks) as usize;
+            total_clocks = clocks;
+            if pulse != 0 {
+                edges.push((level, pulse));
+            }
+            level = !level;
+        }
+
+        // Pauses are recorded as the regular pulses, so they can't be told apart
+        Ok(Self {
+            tape: InstantTape::new(edges, vec![0], Vec::new(), 0),
+        })
+    }
+}
+
+impl TapeImpl for Csw {
+    fn can_fast_load(&self) -> bool {
+        false
+    }
+
+    fn next_block_byte(&mut self) -> Result<Option<u8>> {
+        Ok(None)
+    }
+
+    fn next_block(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn current_bit(&self) -> bool {
+        self.tape.current_bit()
+    }
+
+    fn edge_count(&self) -> u64 {
+        self.tape.edge_count()
+    }
+
+    fn process_clocks(&mut self, clocks: usize) -> Result<()> {
+        self.tape.process_clocks(clocks)
+    }
+
+    fn stop(&mut self) {
+        self.tape.stop();
+    }
+
+    fn play(&mut self) {
+        self.tape.play();
+    }
+
+    fn is_playing(&self) -> bool {
+        self.tape.is_playing()
+    }
+
+    fn set_pause_scale(&mut self, _scale: f32) {}
+
+    fn transport_status(&self) -> TransportStatus {
+        self.tape.transport_status()
+    }
+
+    fn rewind(&mut self) -> Result<()> {
+        self.tape.rewind()
+    }
+
+    fn restart_block(&mut self) -> Result<()> {
+        self.tape.restart_block()
+    }
+
+    fn seek_block(&mut self, block: usize) -> Result<()> {
+        self.tape.seek_block(block)
+    }
+}
+
+struct CswHeader {
+    sample_rate: u32,
+    compression: u8,
+    initial_level: bool,
+    data_offset: usize,
+}
+
+fn parse_header(file: &[u8]) -> Result<CswHeader> {
+    let invalid = || TapeLoadError::InvalidCswHeader;
+    if !file.starts_with(SIGNATURE) {
+        return Err(invalid().into());
+    }
+    let byte = |offset: usize| file.get(offset).copied().ok_or_else(invalid);
+    let header = match byte(0x17)? {
+        1 => CswHeader {
+            sample_rate: u16::from_le_bytes([byte(0x19)?, byte(0x1A)?]) as u32,
+            compression: match byte(0x1B)? {
+                // Z-RLE compression was introduced in v2
+                COMPRESSION_RLE => COMPRESSION_RLE,
+                compression => {
+                    return Err(TapeLoadError::UnsupportedCswCompression { compression }.into())
+                }
+            },
+            initial_level: byte(0x1C)? & 0x01 != 0,
+            data_offset: V1_DATA_OFFSET,
+        },
+        2 => CswHeader {
+            sample_rate: u32::from_le_bytes([byte(0x19)?, byte(0x1A)?, byte(0x1B)?, byte(0x1C)?]),
+            compression: byte(0x21)?,
+            initial_level: byte(0x22)? & 0x01 != 0,
+            data_offset: V2_EXTENSION_OFFSET + byte(0x23)? as usize,
+        },
+        major => return Err(TapeLoadError::UnsupportedCswVersion { major }.into()),
+    };
+    if header.sample_rate == 0 || header.data_offset > file.len() {
+        return Err(invalid().into());
+    }
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, host::BufferCursor};
+
+    const RLE: &[u8] = &[0x10, 0x20, 0x00, 0x00, 0x01, 0x00, 0x00, 0xFF];
+    const PULSES: &[u32] = &[0x10, 0x20, 0x100, 0xFF];
+
+    #[test]
+    fn rle_pulses_are_decoded() {
+        assert_eq!(decode_csw(RLE, COMPRESSION_RLE).unwrap(), PULSES);
+        assert!(matches!(
+            decode_csw(&RLE[..4], COMPRESSION_RLE),
+            Err(Error::TapeLoad(TapeLoadError::InvalidCswData))
+        ));
+    }
+
+    #[cfg(feature = "csw-z-rle")]
+    #[test]
+    fn z_rle_pulses_are_decoded() {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(RLE, 6);
+        assert_eq!(decode_csw(&compressed, COMPRESSION_Z_RLE).unwrap(), PULSES);
+        assert!(matches!(
+            decode_csw(RLE, COMPRESSION_Z_RLE),
+            Err(Error::TapeLoad(TapeLoadError::InvalidCswData))
+        ));
+        assert!(matches!(
+            decode_csw(RLE, 0x03),
+            Err(Error::TapeLoad(TapeLoadError::UnsupportedCswCompression {
+                compression: 0x03
+            }))
+        ));
+    }
+
+    #[cfg(not(feature = "csw-z-rle"))]
+    #[test]
+    fn z_rle_requires_feature() {
+        assert!(matches!(
+            decode_csw(RLE, COMPRESSION_Z_RLE),
+            Err(Error::TapeLoad(TapeLoadError::CswZRleDisabled))
+        ));
+    }
+
+    /// Builds CSW file of the given `major` version with 3.5MHz sample rate, so samples are
+    /// equal to the 48K CPU clocks
+    fn csw_image(major: u8, compression: u8, initial_level: bool, data: &[u8]) -> Vec<u8> {
+        let mut image = SIGNATURE.to_vec();
+        image.extend_from_slice(&[major, 0]);
+        if major == 1 {
+            image.extend_from_slice(&[0x00, 0x00, compression, initial_level as u8, 0, 0, 0]);
+            image[0x19..0x1B].copy_from_slice(&44100u16.to_le_bytes());
+        } else {
+            image.extend_from_slice(&3_500_000u32.to_le_bytes());
+            image.extend_from_slice(&(PULSES.len() as u32).to_le_bytes());
+            // Header extension of 2 bytes
+            image.extend_from_slice(&[compression, initial_level as u8, 2]);
+            image.extend_from_slice(&[0u8; 16]);
+            image.extend_from_slice(&[0xAA, 0xBB]);
+        }
+        image.extend_from_slice(data);
+        image
+    }
+
+    /// Plays the whole tape clock by clock, returns tape level after the first clock and
+    /// edge count before the tape was rewound at its end
+    fn play_to_end(tape: &mut Csw) -> (bool, u64) {
+        tape.play();
+        tape.process_clocks(1).unwrap();
+        let initial_level = tape.current_bit();
+        let mut edge_count = 0;
+        while tape.is_playing() {
+            edge_count = tape.edge_count();
+            tape.process_clocks(1).unwrap();
+        }
+        (initial_level, edge_count)
+    }
+
+    #[test]
+    fn csw_v1_file_is_played() {
+        let image = csw_image(1, COMPRESSION_RLE, true, RLE);
+        let mut csw = Csw::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K).unwrap();
+        // 44.1kHz samples are scaled to the 3.5MHz clocks
+        assert_eq!(csw.tape.edges[0], (true, 0x10 * 3_500_000 / 44100));
+        // Tape starts low, so the first high pulse is an edge as well
+        assert_eq!(play_to_end(&mut csw), (true, PULSES.len() as u64));
+    }
+
+    #[cfg(feature = "csw-z-rle")]
+    #[test]
+    fn csw_v2_file_is_played() {
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(RLE, 6);
+        let image = csw_image(2, COMPRESSION_Z_RLE, false, &data);
+        let mut csw = Csw::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K).unwrap();
+        assert_eq!(
+            csw.tape.edges,
+            [(false, 0x10), (true, 0x20), (false, 0x100), (true, 0xFF)]
+        );
+        assert_eq!(play_to_end(&mut csw), (false, PULSES.len() as u64 - 1));
+    }
+
+    #[test]
+    fn invalid_csw_is_rejected() {
+        let load =
+            |image: Vec<u8>| Csw::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K);
+        assert!(load(Vec::new()).is_err());
+        assert!(load(SIGNATURE.to_vec()).is_err());
+        assert!(load(csw_image(3, COMPRESSION_RLE, false, RLE)).is_err());
+        assert!(load(csw_image(1, 0x03, false, RLE)).is_err());
+        // Z-RLE is not allowed in v1 files
+        assert!(matches!(
+            load(csw_image(1, COMPRESSION_Z_RLE, false, RLE)),
+            Err(Error::TapeLoad(TapeLoadError::UnsupportedCswCompression {
+                compression: COMPRESSION_Z_RLE
+            }))
+        ));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{zx::tape::TapeImpl, Result};
+use crate::{
+    zx::tape::{TapeImpl, TransportStatus},
+    Result,
+};
 
 pub struct Empty;
 
@@ -19,6 +22,10 @@ impl TapeImpl for Empty {
         false
     }
 
+    fn edge_count(&self) -> u64 {
+        0
+    }
+
     fn process_clocks(&mut self, _clocks: usize) -> Result<()> {
         Ok(())
     }
@@ -27,7 +34,25 @@ impl TapeImpl for Empty {
 
     fn play(&mut self) {}
 
+    fn is_playing(&self) -> bool {
+        false
+    }
+
+    fn set_pause_scale(&mut self, _scale: f32) {}
+
+    fn transport_status(&self) -> TransportStatus {
+        TransportStatus::default()
+    }
+
     fn rewind(&mut self) -> Result<()> {
         Ok(())
     }
+
+    fn restart_block(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn seek_block(&mut self, _block: usize) -> Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,217 @@
+//! Tape which is decoded to the pulses list once at load time and then played from memory,
+//! making playback independent of the asset reads. Fast loading is not supported, as
+//! blocks data is not kept after decoding
+use crate::{
+    zx::tape::{
+        player::{Edge, EdgePlayer, EdgeSource},
+        TapeImpl, TransportStatus,
+    },
+    Result,
+};
+use alloc::vec::Vec;
+
+pub struct InstantTape {
+    // Tape level and its duration in clocks for each tape edge
+    pub(super) edges: Vec<(bool, usize)>,
+    // Indices of the first edges of the blocks
+    pub(super) block_starts: Vec<usize>,
+    // Indices of the edges which represent pauses between blocks
+    pauses: Vec<usize>,
+    // Unscaled pause length in clocks
+    pause_length: usize,
+    next_edge: usize,
+    player: EdgePlayer,
+}
+
+impl InstantTape {
+    pub(crate) fn new(
+        edges: Vec<(bool, usize)>,
+        block_starts: Vec<usize>,
+        pauses: Vec<usize>,
+        pause_length: usize,
+    ) -> Self {
+        Self {
+            edges,
+            block_starts,
+            pauses,
+            pause_length,
+            next_edge: 0,
+            player: EdgePlayer::default(),
+        }
+    }
+}
+
+impl EdgeSource for InstantTape {
+    fn player(&mut self) -> &mut EdgePlayer {
+        &mut self.player
+    }
+
+    fn next_edge(&mut self) -> Result<Edge> {
+        match self.edges.get(self.next_edge) {
+            Some(&(level, length)) => {
+                self.next_edge += 1;
+                Ok(Edge::Level(level, length))
+            }
+            None => Ok(Edge::End),
+        }
+    }
+}
+
+impl TapeImpl for InstantTape {
+    fn can_fast_load(&self) -> bool {
+        false
+    }
+
+    fn next_block_byte(&mut self) -> Result<Option<u8>> {
+        Ok(None)
+    }
+
+    fn next_block(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn current_bit(&self) -> bool {
+        self.player.curr_bit
+    }
+
+    fn edge_count(&self) -> u64 {
+        self.player.edge_count
+    }
+
+    fn process_clocks(&mut self, clocks: usize) -> Result<()> {
+        self.play_edges(clocks)
+    }
+
+    fn stop(&mut self) {
+        self.player.stop();
+    }
+
+    fn play(&mut self) {
+        self.player.play();
+    }
+
+    fn is_playing(&self) -> bool {
+        self.player.playing
+    }
+
+    fn set_pause_scale(&mut self, scale: f32) {
+        let length = (self.pause_length as f32 * scale.max(0.0)) as usize;
+        for &pause in &self.pauses {
+            self.edges[pause].1 = length;
+        }
+    }
+
+    fn transport_status(&self) -> TransportStatus {
+        let block = self
+            .block_starts
+            .partition_point(|start| *start < self.next_edge)
+            .saturating_sub(1);
+        let block_progress = match self.block_starts.get(block) {
+            Some(&start) => {
+                let end = self
+                    .block_starts
+                    .get(block + 1)
+                    .copied()
+                    .unwrap_or(self.edges.len());
+                (self.next_edge.saturating_sub(start)) as f32 / (end - start).max(1) as f32
+            }
+            None => 0.0,
+        };
+        TransportStatus {
+            playing: self.player.playing,
+            block,
+            total_blocks: self.block_starts.len(),
+            block_progress,
+        }
+    }
+
+    fn rewind(&mut self) -> Result<()> {
+        self.next_edge = 0;
+        self.player.rewind();
+        Ok(())
+    }
+
+    fn restart_block(&mut self) -> Result<()> {
+        self.next_edge = self
+            .block_starts
+            .iter()
+            .copied()
+            .take_while(|start| *start < self.next_edge)
+            .last()
+            .unwrap_or(0);
+        self.player.restart();
+        Ok(())
+    }
+
+    fn seek_block(&mut self, block: usize) -> Result<()> {
+        let last_block = self.block_starts.len().saturating_sub(1);
+        if let Some(&start) = self.block_starts.get(block.min(last_block)) {
+            self.next_edge = start;
+            self.player.restart();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        host::BufferCursor,
+        zx::{
+            machine::ZXMachine,
+            tape::Tap,
+            testing::{sample_levels, tap_image},
+        },
+    };
+    use alloc::vec;
+
+    #[test]
+    fn instant_tape_matches_streaming_tape() {
+        let image = tap_image(&[
+            &[0x00, 0x03, 0x41, 0x42][..],
+            &[0xFF, 0x00, 0xA5, 0x5A, 0xFF],
+        ]);
+        let tap = || Tap::from_asset(BufferCursor::new(image.clone()), ZXMachine::Sinclair48K);
+
+        let mut streaming = tap().unwrap();
+        let mut instant = tap().unwrap().decode().unwrap();
+        assert_eq!(instant.block_starts.len(), 2);
+        assert_eq!(instant.pauses.len(), 2);
+        assert_eq!(
+            sample_levels(&mut instant, 97),
+            sample_levels(&mut streaming, 97)
+        );
+
+        streaming.rewind().unwrap();
+        instant.rewind().unwrap();
+        streaming.set_pause_scale(0.25);
+        instant.set_pause_scale(0.25);
+        assert_eq!(
+            sample_levels(&mut instant, 97),
+            sample_levels(&mut streaming, 97)
+        );
+    }
+
+    #[test]
+    fn stopped_edge_is_replayed_from_its_start() {
+        let edges = vec![(true, 100), (false, 200), (true, 300)];
+        let mut tape = InstantTape::new(edges, vec![0], Vec::new(), 0);
+        tape.play();
+        // Switch to the next edge takes a single clock, stop in the middle of the second edge
+        tape.process_clocks(1 + 100 + 1 + 50).unwrap();
+        assert_eq!((tape.current_bit(), tape.edge_count()), (false, 2));
+        tape.stop();
+        assert_eq!((tape.current_bit(), tape.edge_count()), (true, 1));
+
+        tape.play();
+        let clocks = (1..)
+            .find(|_| {
+                tape.process_clocks(1).unwrap();
+                tape.current_bit()
+            })
+            .unwrap();
+        assert_eq!(clocks, 1 + 200 + 1);
+        assert_eq!(tape.edge_count(), 3);
+    }
+}
@@ -0,0 +1,103 @@
+//! Tape which records MIC output edges while stopped and plays them back to the EAR input,
+//! which allows to SAVE and then LOAD data back within the same session without a file
+use crate::{
+    error::EmulationError,
+    zx::tape::{InstantTape, TapeImpl, TransportStatus},
+    Result,
+};
+use alloc::vec::Vec;
+
+pub struct LoopbackTape {
+    // Recorded MIC levels are played back as a single block of the instant tape
+    tape: InstantTape,
+    mic: bool,
+    clocks_since_edge: usize,
+}
+
+impl Default for LoopbackTape {
+    fn default() -> Self {
+        Self {
+            tape: InstantTape::new(Vec::new(), Vec::new(), Vec::new(), 0),
+            mic: false,
+            clocks_since_edge: 0,
+        }
+    }
+}
+
+impl LoopbackTape {
+    /// Records MIC output level, ignored during playback
+    pub fn record_mic(&mut self, level: bool) {
+        if self.tape.is_playing() || level == self.mic {
+            return;
+        }
+        match self.tape.edges.last_mut() {
+            Some((_, length)) => *length = self.clocks_since_edge,
+            None => self.tape.block_starts.push(0),
+        }
+        self.tape.edges.push((level, 0));
+        self.mic = level;
+        self.clocks_since_edge = 0;
+    }
+}
+
+impl TapeImpl for LoopbackTape {
+    fn can_fast_load(&self) -> bool {
+        false
+    }
+
+    fn next_block_byte(&mut self) -> Result<Option<u8>> {
+        Ok(None)
+    }
+
+    fn next_block(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn current_bit(&self) -> bool {
+        self.tape.current_bit()
+    }
+
+    fn edge_count(&self) -> u64 {
+        self.tape.edge_count()
+    }
+
+    fn process_clocks(&mut self, clocks: usize) -> Result<()> {
+        if !self.tape.is_playing() {
+            self.clocks_since_edge = self.clocks_since_edge.saturating_add(clocks);
+            return Ok(());
+        }
+        self.tape.process_clocks(clocks)
+    }
+
+    fn stop(&mut self) {
+        self.tape.stop();
+    }
+
+    fn play(&mut self) {
+        self.tape.play();
+    }
+
+    fn is_playing(&self) -> bool {
+        self.tape.is_playing()
+    }
+
+    fn set_pause_scale(&mut self, _scale: f32) {}
+
+    fn transport_status(&self) -> TransportStatus {
+        self.tape.transport_status()
+    }
+
+    fn rewind(&mut self) -> Result<()> {
+        self.tape.rewind()
+    }
+
+    fn restart_block(&mut self) -> Result<()> {
+        self.tape.restart_block()
+    }
+
+    fn seek_block(&mut self, _block: usize) -> Result<()> {
+        // Recording is a single growing block, seeking would only rewind it, which is what
+        // `rewind` is for
+        Err(EmulationError::TapeNotSeekable.into())
+    }
+}
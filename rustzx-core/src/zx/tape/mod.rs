@@ -1,11 +1,22 @@
+pub mod csw;
 mod empty;
+mod instant;
+mod loopback;
+mod player;
+mod pzx;
 mod tap;
 
+pub use csw::Csw;
 pub use empty::Empty;
-pub use tap::Tap;
+pub use instant::InstantTape;
+pub use loopback::LoopbackTape;
+pub use pzx::Pzx;
+#[cfg(feature = "serde")]
+pub use tap::TapPosition;
+pub use tap::{Tap, TapeTimings, TapeTimingsBuilder};
 
 use crate::{
-    host::{LoadableAsset, SeekableAsset},
+    host::{LoadableAsset, SeekableAsset, TapeEvent},
     Result,
 };
 
@@ -15,15 +26,64 @@ use enum_dispatch::enum_dispatch;
 #[enum_dispatch(TapeImpl)]
 pub enum ZXTape<A: LoadableAsset + SeekableAsset> {
     Tap(Tap<A>),
+    Instant(InstantTape),
+    Loopback(LoopbackTape),
+    Pzx(Pzx),
+    Csw(Csw),
     Empty(Empty),
 }
 
+impl<A: LoadableAsset + SeekableAsset> ZXTape<A> {
+    /// Removes the tape, releasing its asset. Tape becomes [Empty], therefore playback
+    /// requests are ignored until the new tape is inserted. Unlike [TapeImpl] methods, this
+    /// replaces the tape variant itself, so it is only available on [ZXTape]
+    pub fn eject(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<A: LoadableAsset + SeekableAsset> ZXTape<A> {
+    /// Returns current playback position or `None` if tape is not inserted or position of
+    /// the instant, pzx or csw tape is requested
+    pub fn position(&self) -> Option<TapPosition> {
+        match self {
+            Self::Tap(tap) => Some(tap.position()),
+            Self::Instant(_) | Self::Loopback(_) | Self::Pzx(_) | Self::Csw(_) | Self::Empty(_) => {
+                None
+            }
+        }
+    }
+
+    /// Restores playback position, ignored if tape is not inserted or it is an instant, pzx or
+    /// csw tape
+    pub fn set_position(&mut self, position: TapPosition) -> Result<()> {
+        match self {
+            Self::Tap(tap) => tap.set_position(position),
+            Self::Instant(_) | Self::Loopback(_) | Self::Pzx(_) | Self::Csw(_) | Self::Empty(_) => {
+                Ok(())
+            }
+        }
+    }
+}
+
 impl<A: LoadableAsset + SeekableAsset> Default for ZXTape<A> {
     fn default() -> Self {
         Self::Empty(Empty)
     }
 }
 
+/// Tape transport state summary, e.g. for the frontend HUD
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TransportStatus {
+    pub playing: bool,
+    /// Zero-based index of the current block
+    pub block: usize,
+    pub total_blocks: usize,
+    /// Fraction of the current block which has been played, `0.0..=1.0`
+    pub block_progress: f32,
+}
+
 #[enum_dispatch]
 pub trait TapeImpl {
     fn can_fast_load(&self) -> bool;
@@ -33,10 +93,59 @@ pub trait TapeImpl {
     fn next_block(&mut self) -> Result<bool>;
     /// Returns current tape (`ear`) bit
     fn current_bit(&self) -> bool;
+    /// Returns count of tape bit changes since the last rewind, useful for loading diagnostics
+    fn edge_count(&self) -> u64;
     /// Perform tape processing emulation within `clocks` time limit
     fn process_clocks(&mut self, clocks: usize) -> Result<()>;
     fn stop(&mut self);
     fn play(&mut self);
+    /// Returns true if tape is currently playing
+    fn is_playing(&self) -> bool;
+    /// Sets multiplier of the pauses between blocks, does not affect timings of the blocks
+    /// themselves. `0.0` removes pauses completely
+    fn set_pause_scale(&mut self, scale: f32);
+    /// Returns transport state summary
+    fn transport_status(&self) -> TransportStatus;
     /// Rewinds tape content to the beginning
     fn rewind(&mut self) -> Result<()>;
+    /// Rewinds tape to the beginning of the current block, previous blocks are not replayed
+    fn restart_block(&mut self) -> Result<()>;
+    /// Moves tape to the beginning of the `block`, which is clamped to the last block. Tape
+    /// keeps playing if it was playing. Returns `EmulationError::TapeNotSeekable` if the tape
+    /// has no blocks to seek between
+    fn seek_block(&mut self, block: usize) -> Result<()>;
+    /// Returns the oldest diagnostic event which was not taken yet, if any. Only tap tapes
+    /// report block checksums, other formats store pulses instead of the block bytes
+    fn take_event(&mut self) -> Option<TapeEvent> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{host::BufferCursor, zx::machine::ZXMachine};
+    use alloc::vec;
+
+    #[test]
+    fn ejected_tape_is_inert() {
+        let asset = BufferCursor::new(vec![0x02, 0x00, 0xFF, 0x00]);
+        let mut tape: ZXTape<_> = Tap::from_asset(asset, ZXMachine::Sinclair48K)
+            .unwrap()
+            .into();
+        tape.play();
+        tape.process_clocks(1).unwrap();
+        assert!(tape.current_bit());
+
+        tape.eject();
+        assert!(matches!(tape, ZXTape::Empty(_)));
+        assert!(!tape.current_bit());
+        tape.play();
+        for _ in 0..10 {
+            tape.process_clocks(100_000).unwrap();
+            assert!(!tape.is_playing());
+            assert!(!tape.current_bit());
+        }
+        assert!(!tape.next_block().unwrap());
+    }
 }
@@ -0,0 +1,131 @@
+//! Playback of the tapes which are represented as a sequence of edges, shared by the tape
+//! formats which are played from memory
+use crate::{zx::tape::TapeImpl, Result};
+
+/// Next step of the edge sequence
+pub(crate) enum Edge {
+    /// Tape level and its duration in clocks
+    Level(bool, usize),
+    /// Tape should be stopped, playback continues from the following edge
+    Stop,
+    /// End of the tape is reached
+    End,
+}
+
+/// Playback state of the edge sequence
+#[derive(Default)]
+pub(crate) struct EdgePlayer {
+    pub(super) playing: bool,
+    pub(super) curr_bit: bool,
+    pub(super) edge_count: u64,
+    // Clocks left until the next edge
+    pub(super) delay: usize,
+    // Level and length of the current edge and tape level before it
+    edge: (bool, usize),
+    prev_bit: bool,
+    // Current edge was interrupted by the stop and should be played again
+    replay_edge: bool,
+}
+
+impl EdgePlayer {
+    /// Drops current edge and resets tape level, edge counter is kept
+    pub fn restart(&mut self) {
+        self.curr_bit = false;
+        self.prev_bit = false;
+        self.delay = 0;
+        self.replay_edge = false;
+    }
+
+    /// Stops playback. Edge which was interrupted is replayed from its start on resume, as
+    /// resuming in the middle of the edge would corrupt its timing for the loader
+    pub fn stop(&mut self) {
+        if self.playing && self.delay != 0 {
+            if self.curr_bit != self.prev_bit {
+                self.edge_count -= 1;
+            }
+            self.curr_bit = self.prev_bit;
+            self.delay = 0;
+            self.replay_edge = true;
+        }
+        self.playing = false;
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    /// Drops current edge and resets tape level and edge counter
+    pub fn rewind(&mut self) {
+        self.restart();
+        self.edge_count = 0;
+    }
+}
+
+/// Tape which is played by the [EdgePlayer]
+pub(crate) trait EdgeSource: TapeImpl {
+    fn player(&mut self) -> &mut EdgePlayer;
+
+    /// Returns next step of the edge sequence
+    fn next_edge(&mut self) -> Result<Edge>;
+
+    /// Switches tape level to the next edge, stops the tape at its end
+    fn advance_edge(&mut self) -> Result<()> {
+        let edge = if self.player().replay_edge {
+            self.player().replay_edge = false;
+            let (level, length) = self.player().edge;
+            Edge::Level(level, length)
+        } else {
+            self.next_edge()?
+        };
+        match edge {
+            Edge::Level(level, length) => {
+                let player = self.player();
+                if level != player.curr_bit {
+                    player.edge_count += 1;
+                }
+                player.prev_bit = player.curr_bit;
+                player.curr_bit = level;
+                player.delay = length;
+                player.edge = (level, length);
+            }
+            Edge::Stop => self.player().playing = false,
+            Edge::End => {
+                // Reset tape but leave in stopped state
+                self.rewind()?;
+                self.player().playing = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Perform edges playback within `clocks` time limit
+    fn play_edges(&mut self, mut clocks: usize) -> Result<()> {
+        if !self.player().playing {
+            return Ok(());
+        }
+
+        if self.player().delay == 0 {
+            self.advance_edge()?;
+            clocks = clocks.saturating_sub(1);
+        }
+
+        // Same timing as `Tap`: switching to the next edge takes a single clock and the
+        // remaining clocks are carried over to the following edges
+        while self.player().playing {
+            let player = self.player();
+            if player.delay > clocks {
+                player.delay -= clocks;
+                break;
+            }
+            clocks -= player.delay;
+            player.delay = 0;
+            if clocks == 0 {
+                break;
+            }
+            clocks -= 1;
+            self.advance_edge()?;
+        }
+
+        Ok(())
+    }
+}
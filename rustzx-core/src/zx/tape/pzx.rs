@@ -0,0 +1,635 @@
+//! PZX tape format support. Whole file is parsed to the list of pulse blocks at load time, as
+//! PZX blocks are already described in terms of the pulses. Fast loading is not supported.
+//!
+//! Format specification: <http://zxds.raxoft.cz/docs/pzx.txt>
+use crate::{
+    error::TapeLoadError,
+    host::LoadableAsset,
+    zx::{
+        machine::ZXMachine,
+        tape::{
+            player::{Edge, EdgePlayer, EdgeSource},
+            TapeImpl, TransportStatus,
+        },
+    },
+    Result,
+};
+use alloc::vec::Vec;
+
+const CHUNK_HEADER_SIZE: usize = 8;
+const SUPPORTED_MAJOR_VERSION: u8 = 1;
+
+enum PzxBlock {
+    /// `PULS` block, pairs of the repeat count and pulse duration
+    Pulses(Vec<(usize, usize)>),
+    /// `DATA` block
+    Data {
+        initial_level: bool,
+        bit_count: usize,
+        tail: usize,
+        bit_zero: Vec<usize>,
+        bit_one: Vec<usize>,
+        data: Vec<u8>,
+    },
+    /// `PAUS` block
+    Pause { level: bool, duration: usize },
+    /// `STOP` block, `only_48k` is set if tape should be stopped only in the 48K mode
+    Stop { only_48k: bool },
+}
+
+impl PzxBlock {
+    fn initial_level(&self) -> bool {
+        match self {
+            Self::Data { initial_level, .. } => *initial_level,
+            Self::Pause { level, .. } => *level,
+            Self::Pulses(_) | Self::Stop { .. } => false,
+        }
+    }
+
+    /// Returns duration of the pulse at the `cursor` position or `None` if block has ended
+    fn pulse(&self, cursor: &Cursor) -> Option<usize> {
+        match self {
+            Self::Pulses(pulses) => pulses.get(cursor.item).map(|(_, duration)| *duration),
+            Self::Data {
+                bit_count,
+                tail,
+                bit_zero,
+                bit_one,
+                data,
+                ..
+            } => {
+                if cursor.item < *bit_count {
+                    let byte = data[cursor.item / 8];
+                    let sequence = if byte & (0x80 >> (cursor.item % 8)) != 0 {
+                        bit_one
+                    } else {
+                        bit_zero
+                    };
+                    sequence.get(cursor.repeat).copied()
+                } else if cursor.item == *bit_count && *tail != 0 {
+                    Some(*tail)
+                } else {
+                    None
+                }
+            }
+            Self::Pause { duration, .. } => (cursor.item == 0).then_some(*duration),
+            Self::Stop { .. } => None,
+        }
+    }
+
+    /// Moves `cursor` to the pulse which follows the current one
+    fn advance(&self, cursor: &mut Cursor) {
+        cursor.repeat += 1;
+        let item_pulses = match self {
+            Self::Pulses(pulses) => pulses[cursor.item].0,
+            Self::Data {
+                bit_count,
+                bit_zero,
+                bit_one,
+                data,
+                ..
+            } if cursor.item < *bit_count => {
+                if data[cursor.item / 8] & (0x80 >> (cursor.item % 8)) != 0 {
+                    bit_one.len()
+                } else {
+                    bit_zero.len()
+                }
+            }
+            _ => 1,
+        };
+        if cursor.repeat >= item_pulses {
+            cursor.item += 1;
+            cursor.repeat = 0;
+        }
+    }
+
+    /// Returns count of the items (pulse entries or bits) in the block
+    fn items_count(&self) -> usize {
+        match self {
+            Self::Pulses(pulses) => pulses.len(),
+            Self::Data { bit_count, .. } => *bit_count,
+            Self::Pause { .. } | Self::Stop { .. } => 1,
+        }
+    }
+}
+
+/// Position within the block, `item` is the index of the pulse entry or data bit and `repeat`
+/// is the index of the pulse within it
+#[derive(Default, Clone, Copy)]
+struct Cursor {
+    item: usize,
+    repeat: usize,
+}
+
+pub struct Pzx {
+    blocks: Vec<PzxBlock>,
+    machine: ZXMachine,
+    block: usize,
+    cursor: Cursor,
+    // Level of the next pulse
+    level: bool,
+    player: EdgePlayer,
+    // Multiplier of the `PAUS` blocks duration
+    pause_scale: f32,
+}
+
+impl Pzx {
+    pub fn from_asset(mut asset: impl LoadableAsset, machine: ZXMachine) -> Result<Self> {
+        let mut file = Vec::new();
+        asset.read_to_end(&mut file)?;
+
+        let mut tape = Self {
+            blocks: Self::parse(&file)?,
+            machine,
+            block: 0,
+            cursor: Cursor::default(),
+            level: false,
+            player: EdgePlayer::default(),
+            pause_scale: 1.0,
+        };
+        tape.level = tape.block_initial_level();
+        Ok(tape)
+    }
+
+    fn parse(file: &[u8]) -> Result<Vec<PzxBlock>> {
+        if file.is_empty() {
+            return Err(TapeLoadError::InvalidPzxHeader.into());
+        }
+
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        while offset < file.len() {
+            let truncated = || TapeLoadError::TruncatedPzxChunk { offset };
+            let body_start = offset + CHUNK_HEADER_SIZE;
+            let header = file.get(offset..body_start).ok_or_else(truncated)?;
+            let tag = &header[0..4];
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            // Size of the malformed chunk may overflow `usize` on 32-bit targets
+            let body_end = body_start.checked_add(size).ok_or_else(truncated)?;
+            let body = file.get(body_start..body_end).ok_or_else(truncated)?;
+
+            if offset == 0 {
+                if tag != b"PZXT" {
+                    return Err(TapeLoadError::InvalidPzxHeader.into());
+                }
+                let major = body.first().copied().unwrap_or(0);
+                if major != SUPPORTED_MAJOR_VERSION {
+                    return Err(TapeLoadError::UnsupportedPzxVersion { major }.into());
+                }
+            }
+
+            let mut reader = ChunkReader { body, pos: 0 };
+            match tag {
+                b"PULS" => {
+                    let mut pulses = Vec::new();
+                    while !reader.is_empty() {
+                        let mut count = 1;
+                        let mut duration = reader.u16().ok_or_else(truncated)? as usize;
+                        if duration > 0x8000 {
+                            count = duration & 0x7FFF;
+                            duration = reader.u16().ok_or_else(truncated)? as usize;
+                        }
+                        if duration >= 0x8000 {
+                            let low = reader.u16().ok_or_else(truncated)? as usize;
+                            duration = ((duration & 0x7FFF) << 16) | low;
+                        }
+                        pulses.push((count, duration));
+                    }
+                    blocks.push(PzxBlock::Pulses(pulses));
+                }
+                b"DATA" => {
+                    let bits = reader.u32().ok_or_else(truncated)?;
+                    let tail = reader.u16().ok_or_else(truncated)? as usize;
+                    let zero_pulses = reader.u8().ok_or_else(truncated)? as usize;
+                    let one_pulses = reader.u8().ok_or_else(truncated)? as usize;
+                    let mut sequence = |len| {
+                        (0..len)
+                            .map(|_| reader.u16().map(|pulse| pulse as usize))
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or_else(truncated)
+                    };
+                    let bit_zero = sequence(zero_pulses)?;
+                    let bit_one = sequence(one_pulses)?;
+                    let bit_count = (bits & 0x7FFF_FFFF) as usize;
+                    let data = reader.take(bit_count.div_ceil(8)).ok_or_else(truncated)?;
+                    blocks.push(PzxBlock::Data {
+                        initial_level: bits & 0x8000_0000 != 0,
+                        bit_count,
+                        tail,
+                        bit_zero,
+                        bit_one,
+                        data: data.to_vec(),
+                    });
+                }
+                b"PAUS" => {
+                    let value = reader.u32().ok_or_else(truncated)?;
+                    blocks.push(PzxBlock::Pause {
+                        level: value & 0x8000_0000 != 0,
+                        duration: (value & 0x7FFF_FFFF) as usize,
+                    });
+                }
+                b"STOP" => {
+                    let flags = reader.u16().ok_or_else(truncated)?;
+                    blocks.push(PzxBlock::Stop {
+                        only_48k: flags == 1,
+                    });
+                }
+                // `PZXT` header and `BRWS` browse points do not affect playback
+                b"PZXT" | b"BRWS" => {}
+                _ => log_warn!(
+                    "Skipping unsupported PZX block {:?} at offset {}",
+                    core::str::from_utf8(tag).unwrap_or("????"),
+                    offset
+                ),
+            }
+
+            offset = body_end;
+        }
+
+        Ok(blocks)
+    }
+
+    /// Merges consecutive `PAUS` blocks of the same level into a single pause with their
+    /// total duration and rewinds the tape. Other blocks are kept intact
+    pub fn join_pauses(&mut self) {
+        let mut blocks: Vec<PzxBlock> = Vec::with_capacity(self.blocks.len());
+        for block in self.blocks.drain(..) {
+            if let (
+                Some(PzxBlock::Pause { level, duration }),
+                PzxBlock::Pause {
+                    level: next_level,
+                    duration: next_duration,
+                },
+            ) = (blocks.last_mut(), &block)
+            {
+                if level == next_level {
+                    *duration += next_duration;
+                    continue;
+                }
+            }
+            blocks.push(block);
+        }
+        self.blocks = blocks;
+        self.start_block(0);
+    }
+
+    fn block_initial_level(&self) -> bool {
+        self.blocks
+            .get(self.block)
+            .map(PzxBlock::initial_level)
+            .unwrap_or(false)
+    }
+
+    fn start_block(&mut self, block: usize) {
+        self.block = block;
+        self.cursor = Cursor::default();
+        self.level = self.block_initial_level();
+    }
+}
+
+impl EdgeSource for Pzx {
+    fn player(&mut self) -> &mut EdgePlayer {
+        &mut self.player
+    }
+
+    /// Returns the next pulse, skipping zero-length pulses and moving through the blocks.
+    /// Tape is stopped at `STOP` blocks
+    fn next_edge(&mut self) -> Result<Edge> {
+        loop {
+            let block = match self.blocks.get(self.block) {
+                Some(block) => block,
+                None => return Ok(Edge::End),
+            };
+
+            match block.pulse(&self.cursor) {
+                Some(duration) => {
+                    let is_pause = matches!(block, PzxBlock::Pause { .. });
+                    block.advance(&mut self.cursor);
+                    let level = self.level;
+                    self.level = !level;
+                    let duration = if is_pause {
+                        (duration as f32 * self.pause_scale) as usize
+                    } else {
+                        duration
+                    };
+                    if duration != 0 {
+                        return Ok(Edge::Level(level, duration));
+                    }
+                }
+                None => {
+                    let stop = match block {
+                        PzxBlock::Stop { only_48k } => {
+                            !only_48k || self.machine == ZXMachine::Sinclair48K
+                        }
+                        _ => false,
+                    };
+                    self.start_block(self.block + 1);
+                    if stop {
+                        return Ok(Edge::Stop);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl TapeImpl for Pzx {
+    fn can_fast_load(&self) -> bool {
+        false
+    }
+
+    fn next_block_byte(&mut self) -> Result<Option<u8>> {
+        Ok(None)
+    }
+
+    fn next_block(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn current_bit(&self) -> bool {
+        self.player.curr_bit
+    }
+
+    fn edge_count(&self) -> u64 {
+        self.player.edge_count
+    }
+
+    fn process_clocks(&mut self, clocks: usize) -> Result<()> {
+        self.play_edges(clocks)
+    }
+
+    fn stop(&mut self) {
+        self.player.stop();
+    }
+
+    fn play(&mut self) {
+        self.player.play();
+    }
+
+    fn is_playing(&self) -> bool {
+        self.player.playing
+    }
+
+    fn set_pause_scale(&mut self, scale: f32) {
+        self.pause_scale = scale.max(0.0);
+    }
+
+    fn transport_status(&self) -> TransportStatus {
+        let block_progress = self
+            .blocks
+            .get(self.block)
+            .map(|block| self.cursor.item as f32 / block.items_count().max(1) as f32)
+            .unwrap_or(0.0)
+            .min(1.0);
+        TransportStatus {
+            playing: self.player.playing,
+            block: self.block,
+            total_blocks: self.blocks.len(),
+            block_progress,
+        }
+    }
+
+    fn rewind(&mut self) -> Result<()> {
+        self.start_block(0);
+        self.player.rewind();
+        Ok(())
+    }
+
+    fn restart_block(&mut self) -> Result<()> {
+        self.start_block(self.block);
+        self.player.restart();
+        Ok(())
+    }
+
+    fn seek_block(&mut self, block: usize) -> Result<()> {
+        if !self.blocks.is_empty() {
+            self.block = block.min(self.blocks.len() - 1);
+            self.restart_block()?;
+        }
+        Ok(())
+    }
+}
+
+/// Little-endian reader of the chunk body
+struct ChunkReader<'a> {
+    body: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ChunkReader<'a> {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.body.len()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let bytes = self.body.get(self.pos..end)?;
+        self.pos = end;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::Error,
+        host::BufferCursor,
+        zx::{
+            tape::Tap,
+            testing::{push_pzx_chunk, sample_levels, tap_image},
+        },
+    };
+    use alloc::vec;
+
+    /// Encodes tap `blocks` as PZX file with standard ROM loader timings
+    fn pzx_image(blocks: &[&[u8]]) -> Vec<u8> {
+        let mut image = Vec::new();
+        push_pzx_chunk(&mut image, b"PZXT", &[1, 0]);
+        for block in blocks {
+            let pilot_pulses: u16 = if block[0] & 0x80 == 0 { 8063 } else { 3223 };
+            let mut pulses = Vec::new();
+            // Zero pulse makes pilot start with high level, as in `Tap`
+            for value in [0, 0x8000 | pilot_pulses, 2168, 667, 735] {
+                pulses.extend_from_slice(&value.to_le_bytes());
+            }
+            push_pzx_chunk(&mut image, b"PULS", &pulses);
+
+            let mut data = Vec::new();
+            data.extend_from_slice(&(block.len() as u32 * 8).to_le_bytes());
+            data.extend_from_slice(&0u16.to_le_bytes());
+            data.extend_from_slice(&[2, 2]);
+            for pulse in [855u16, 855, 1710, 1710] {
+                data.extend_from_slice(&pulse.to_le_bytes());
+            }
+            data.extend_from_slice(block);
+            push_pzx_chunk(&mut image, b"DATA", &data);
+
+            push_pzx_chunk(&mut image, b"BRWS", b"block\0");
+            push_pzx_chunk(&mut image, b"PAUS", &3_500_000u32.to_le_bytes());
+        }
+        image
+    }
+
+    #[test]
+    fn pzx_edges_match_equivalent_tap() {
+        let blocks = [
+            &[0x00, 0x03, 0x41, 0x42][..],
+            &[0xFF, 0x00, 0xA5, 0x5A, 0xFF],
+        ];
+        let mut tap = Tap::from_asset(
+            BufferCursor::new(tap_image(&blocks)),
+            ZXMachine::Sinclair48K,
+        )
+        .unwrap();
+        let mut pzx = Pzx::from_asset(
+            BufferCursor::new(pzx_image(&blocks)),
+            ZXMachine::Sinclair48K,
+        )
+        .unwrap();
+        assert_eq!(pzx.transport_status().total_blocks, 6);
+        assert_eq!(sample_levels(&mut pzx, 97), sample_levels(&mut tap, 97));
+        assert_eq!(pzx.edge_count(), 0);
+
+        pzx.play();
+        tap.play();
+        for _ in 0..1000 {
+            pzx.process_clocks(1000).unwrap();
+            tap.process_clocks(1000).unwrap();
+        }
+        assert_eq!(pzx.edge_count(), tap.edge_count());
+    }
+
+    #[test]
+    fn tone_block_is_followed_by_data_without_pause() {
+        let mut image = Vec::new();
+        push_pzx_chunk(&mut image, b"PZXT", &[1, 0]);
+        // Pure tone of 3 pulses
+        push_pzx_chunk(&mut image, b"PULS", &[0x03, 0x80, 0x00, 0x01]);
+        let mut data = Vec::new();
+        data.extend_from_slice(&8u32.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&[1, 1, 0x10, 0x00, 0x20, 0x00, 0xF0]);
+        push_pzx_chunk(&mut image, b"DATA", &data);
+
+        let mut pzx = Pzx::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K).unwrap();
+        pzx.play();
+        let mut pulses = Vec::new();
+        while pzx.is_playing() {
+            pzx.advance_edge().unwrap();
+            if pzx.is_playing() {
+                pulses.push(pzx.player.delay);
+            }
+        }
+        let mut expected = vec![0x100; 3];
+        expected.extend_from_slice(&[0x20; 4]);
+        expected.extend_from_slice(&[0x10; 4]);
+        assert_eq!(pulses, expected);
+    }
+
+    #[test]
+    fn stop_block_stops_tape() {
+        let mut image = Vec::new();
+        push_pzx_chunk(&mut image, b"PZXT", &[1, 0]);
+        push_pzx_chunk(&mut image, b"PULS", &[0x10, 0x00, 0x10, 0x00]);
+        push_pzx_chunk(&mut image, b"STOP", &[0x00, 0x00]);
+        push_pzx_chunk(&mut image, b"PULS", &[0x10, 0x00]);
+
+        let mut pzx = Pzx::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K).unwrap();
+        pzx.play();
+        pzx.process_clocks(1000).unwrap();
+        assert!(!pzx.is_playing());
+        assert_eq!(pzx.transport_status().block, 2);
+        assert_eq!(pzx.edge_count(), 1);
+
+        pzx.play();
+        pzx.process_clocks(1000).unwrap();
+        assert!(!pzx.is_playing());
+        assert_eq!(pzx.transport_status().block, 0);
+    }
+
+    #[test]
+    fn stopped_edge_is_replayed_from_its_start() {
+        let mut image = Vec::new();
+        push_pzx_chunk(&mut image, b"PZXT", &[1, 0]);
+        // Pulses of 100, 200 and 300 clocks, starting with low level
+        push_pzx_chunk(&mut image, b"PULS", &[0x64, 0x00, 0xC8, 0x00, 0x2C, 0x01]);
+
+        let mut pzx = Pzx::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K).unwrap();
+        pzx.play();
+        // Switch to the next edge takes a single clock, stop in the middle of the second edge
+        pzx.process_clocks(1 + 100 + 1 + 50).unwrap();
+        assert_eq!((pzx.current_bit(), pzx.edge_count()), (true, 1));
+        pzx.stop();
+        assert_eq!((pzx.current_bit(), pzx.edge_count()), (false, 0));
+
+        pzx.play();
+        let clocks = (1..)
+            .find(|_| {
+                pzx.process_clocks(1).unwrap();
+                !pzx.current_bit()
+            })
+            .unwrap();
+        assert_eq!(clocks, 1 + 200 + 1);
+        assert_eq!(pzx.edge_count(), 2);
+    }
+
+    #[test]
+    fn adjacent_pauses_are_joined() {
+        // 500ms at 3.5MHz
+        let pause = 1_750_000u32.to_le_bytes();
+        let mut image = Vec::new();
+        push_pzx_chunk(&mut image, b"PZXT", &[1, 0]);
+        push_pzx_chunk(&mut image, b"PULS", &[0x10, 0x00]);
+        push_pzx_chunk(&mut image, b"PAUS", &pause);
+        push_pzx_chunk(&mut image, b"PAUS", &pause);
+        push_pzx_chunk(&mut image, b"PULS", &[0x10, 0x00]);
+        let load = || Pzx::from_asset(BufferCursor::new(image.clone()), ZXMachine::Sinclair48K);
+
+        let mut pzx = load().unwrap();
+        assert_eq!(pzx.transport_status().total_blocks, 4);
+        let exact = sample_levels(&mut pzx, 97);
+
+        pzx = load().unwrap();
+        pzx.join_pauses();
+        assert_eq!(pzx.transport_status().total_blocks, 3);
+        assert!(matches!(
+            pzx.blocks[1],
+            PzxBlock::Pause {
+                level: false,
+                duration: 3_500_000
+            }
+        ));
+        assert_eq!(sample_levels(&mut pzx, 97), exact);
+    }
+
+    #[test]
+    fn invalid_pzx_is_rejected() {
+        let load =
+            |image: Vec<u8>| Pzx::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K);
+        assert!(load(Vec::new()).is_err());
+        assert!(load(b"TAPE\x02\x00\x00\x00\x01\x00".to_vec()).is_err());
+        assert!(load(b"PZXT\x02\x00\x00\x00\x02\x00".to_vec()).is_err());
+        assert!(load(b"PZXT\x02\x00\x00\x00\x01\x00PULS\x04\x00\x00\x00\x10".to_vec()).is_err());
+        assert!(matches!(
+            load(b"PZXT\x02\x00\x00\x00\x01\x00PULS\xFF\xFF\xFF\xFF".to_vec()),
+            Err(Error::TapeLoad(TapeLoadError::TruncatedPzxChunk {
+                offset: 10
+            }))
+        ));
+    }
+}
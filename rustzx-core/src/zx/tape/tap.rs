@@ -1,10 +1,15 @@
 use crate::{
-    error::TapeLoadError,
-    host::{LoadableAsset, SeekFrom, SeekableAsset},
-    zx::tape::TapeImpl,
+    error::{IoError, TapeLoadError},
+    host::{LoadableAsset, SeekFrom, SeekableAsset, TapeEvent},
+    zx::{
+        machine::ZXMachine,
+        tape::{InstantTape, TapeImpl, TransportStatus},
+    },
     Result,
 };
 
+use alloc::{collections::VecDeque, vec, vec::Vec};
+
 const PILOT_LENGTH: usize = 2168;
 const PILOT_PULSES_HEADER: usize = 8063;
 const PILOT_PULSES_DATA: usize = 3223;
@@ -12,10 +17,109 @@ const SYNC1_LENGTH: usize = 667;
 const SYNC2_LENGTH: usize = 735;
 const BIT_ONE_LENGTH: usize = 1710;
 const BIT_ZERO_LENGTH: usize = 855;
-const PAUSE_LENGTH: usize = 3_500_000;
-const BUFFER_SIZE: usize = 128;
+// Block size stored before each block
+const BLOCK_HEADER_SIZE: usize = 2;
+/// Default size of the tape read buffer
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Pulse lengths (in clocks) and pilot pulse counts used to play tap blocks. Defaults to the
+/// timings of the standard ROM loader.
+///
+/// This is an advanced escape hatch for copy-protected tapes which refuse to load with the
+/// standard timings, normal tapes never need it. Use [TapeTimingsBuilder] to create custom
+/// timings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapeTimings {
+    pilot_length: usize,
+    pilot_pulses_header: usize,
+    pilot_pulses_data: usize,
+    sync1_length: usize,
+    sync2_length: usize,
+    bit_zero_length: usize,
+    bit_one_length: usize,
+    fast_load_tolerance: usize,
+}
+
+impl Default for TapeTimings {
+    fn default() -> Self {
+        Self {
+            pilot_length: PILOT_LENGTH,
+            pilot_pulses_header: PILOT_PULSES_HEADER,
+            pilot_pulses_data: PILOT_PULSES_DATA,
+            sync1_length: SYNC1_LENGTH,
+            sync2_length: SYNC2_LENGTH,
+            bit_zero_length: BIT_ZERO_LENGTH,
+            bit_one_length: BIT_ONE_LENGTH,
+            fast_load_tolerance: 0,
+        }
+    }
+}
+
+impl TapeTimings {
+    /// Returns true if all pulse lengths are within the fast load tolerance window of the
+    /// standard ROM loader timings, so the fast loader produces the same result as the ROM
+    fn matches_rom_loader(&self) -> bool {
+        [
+            (self.pilot_length, PILOT_LENGTH),
+            (self.sync1_length, SYNC1_LENGTH),
+            (self.sync2_length, SYNC2_LENGTH),
+            (self.bit_zero_length, BIT_ZERO_LENGTH),
+            (self.bit_one_length, BIT_ONE_LENGTH),
+        ]
+        .iter()
+        .all(|(actual, standard)| actual.abs_diff(*standard) <= self.fast_load_tolerance)
+    }
+}
+
+/// Builder of the custom [TapeTimings], starts with the standard ROM loader timings
+#[derive(Default)]
+pub struct TapeTimingsBuilder {
+    timings: TapeTimings,
+}
+
+impl TapeTimingsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds new TapeTimings
+    pub fn build(self) -> TapeTimings {
+        self.timings
+    }
+
+    /// Changes pilot pulse length and pulse counts of the header and data blocks pilots
+    pub fn pilot(mut self, length: usize, header_pulses: usize, data_pulses: usize) -> Self {
+        self.timings.pilot_length = length;
+        self.timings.pilot_pulses_header = header_pulses.max(1);
+        self.timings.pilot_pulses_data = data_pulses.max(1);
+        self
+    }
+
+    /// Changes lengths of the first and the second sync pulses
+    pub fn sync(mut self, sync1: usize, sync2: usize) -> Self {
+        self.timings.sync1_length = sync1;
+        self.timings.sync2_length = sync2;
+        self
+    }
+
+    /// Changes lengths of the half-bit pulses for reset and set bits
+    pub fn bits(mut self, zero: usize, one: usize) -> Self {
+        self.timings.bit_zero_length = zero;
+        self.timings.bit_one_length = one;
+        self
+    }
+
+    /// Changes maximal difference in clocks between custom and standard pulse lengths for
+    /// which the fast loading is still performed. Tapes with bigger difference are always
+    /// loaded in real time, as ROM loader would not accept them
+    pub fn fast_load_tolerance(mut self, clocks: usize) -> Self {
+        self.timings.fast_load_tolerance = clocks;
+        self
+    }
+}
 
 #[derive(PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum TapeState {
     Stop,
     Play,
@@ -27,122 +131,197 @@ enum TapeState {
     Pause,
 }
 
+/// Tape playback position. Tape asset itself is not the part of the position, therefore
+/// position could be restored only with the same tape inserted
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TapPosition {
+    state: TapeState,
+    prev_state: TapeState,
+    block_offset: usize,
+    block_bytes_read: usize,
+    current_block_size: Option<usize>,
+    tape_ended: bool,
+    curr_bit: bool,
+    curr_byte: u8,
+    delay: usize,
+}
+
 pub struct Tap<A: LoadableAsset + SeekableAsset> {
     asset: A,
     state: TapeState,
     prev_state: TapeState,
-    buffer: [u8; BUFFER_SIZE],
+    buffer: Vec<u8>,
     buffer_offset: usize,
     block_bytes_read: usize,
     current_block_size: Option<usize>,
+    // Asset offset of the current block header
+    block_offset: usize,
+    // Total asset size, used to validate declared block sizes
+    asset_len: usize,
+    // Asset offsets of all block headers and declared block sizes
+    blocks: Vec<(usize, usize)>,
+    // XOR of the current block bytes read so far
+    block_checksum: u8,
+    // Diagnostic events which were not taken yet, several blocks may be skipped at once
+    events: VecDeque<TapeEvent>,
     tape_ended: bool,
     // Non-fastload related fields
     curr_bit: bool,
+    edge_count: u64,
     curr_byte: u8,
     delay: usize,
+    // One second pause between blocks in machine clocks
+    pause_length: usize,
+    // Multiplier of the pause between blocks
+    pause_scale: f32,
+    timings: TapeTimings,
+    // Raw pulses which are played before the next state machine step
+    #[cfg(test)]
+    injected_pulses: VecDeque<usize>,
 }
 
 impl<A: LoadableAsset + SeekableAsset> Tap<A> {
-    pub fn from_asset(asset: A) -> Result<Self> {
+    pub fn from_asset(asset: A, machine: ZXMachine) -> Result<Self> {
+        Self::with_buffer_size(asset, machine, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates tape which reads blocks from the asset by chunks of `buffer_size` bytes.
+    /// Bigger buffer reduces count of asset reads, which may be expensive on some hosts
+    pub fn with_buffer_size(mut asset: A, machine: ZXMachine, buffer_size: usize) -> Result<Self> {
+        let asset_len = asset.seek(SeekFrom::End(0))?;
+        let blocks = Self::scan_blocks(&mut asset, asset_len)?;
         let tap = Self {
             prev_state: TapeState::Stop,
             state: TapeState::Stop,
             curr_bit: false,
+            edge_count: 0,
             curr_byte: 0x00,
-            buffer: [0u8; BUFFER_SIZE],
+            buffer: vec![0u8; buffer_size.max(1)],
             buffer_offset: 0,
             block_bytes_read: 0,
             current_block_size: None,
+            block_offset: 0,
+            asset_len,
+            blocks,
+            block_checksum: 0,
+            events: VecDeque::new(),
             delay: 0,
+            pause_length: machine.specs().freq_cpu,
+            pause_scale: 1.0,
+            timings: TapeTimings::default(),
             asset,
             tape_ended: false,
+            #[cfg(test)]
+            injected_pulses: VecDeque::new(),
         };
         Ok(tap)
     }
-}
 
-impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
-    fn can_fast_load(&self) -> bool {
-        self.state == TapeState::Stop
+    /// Returns offsets of all block headers in the asset with declared block sizes, leaving
+    /// asset at the start. Block headers are not read again during playback
+    fn scan_blocks(asset: &mut A, asset_len: usize) -> Result<Vec<(usize, usize)>> {
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        let mut block_size_buffer = [0u8; BLOCK_HEADER_SIZE];
+        while offset + BLOCK_HEADER_SIZE <= asset_len {
+            asset.seek(SeekFrom::Start(offset))?;
+            asset.read_exact(&mut block_size_buffer)?;
+            let block_size = u16::from_le_bytes(block_size_buffer) as usize;
+            blocks.push((offset, block_size));
+            offset += BLOCK_HEADER_SIZE + block_size;
+        }
+        asset.seek(SeekFrom::Start(0))?;
+        Ok(blocks)
     }
 
-    fn next_block_byte(&mut self) -> Result<Option<u8>> {
-        if self.tape_ended {
-            return Ok(None);
+    /// Replaces pulse timings used for the subsequently played pulses, see [TapeTimings]
+    pub fn override_timings(&mut self, timings: TapeTimings) {
+        self.timings = timings;
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn position(&self) -> TapPosition {
+        TapPosition {
+            state: self.state,
+            prev_state: self.prev_state,
+            block_offset: self.block_offset,
+            block_bytes_read: self.block_bytes_read,
+            current_block_size: self.current_block_size,
+            tape_ended: self.tape_ended,
+            curr_bit: self.curr_bit,
+            curr_byte: self.curr_byte,
+            delay: self.delay,
         }
+    }
 
-        if let Some(block_size) = self.current_block_size {
-            if self.block_bytes_read >= block_size {
-                return Ok(None);
+    /// Restores tape position by re-reading current block up to the saved byte
+    #[cfg(feature = "serde")]
+    pub fn set_position(&mut self, position: TapPosition) -> Result<()> {
+        self.rewind()?;
+        self.asset.seek(SeekFrom::Start(position.block_offset))?;
+        self.block_offset = position.block_offset;
+        if position.current_block_size.is_some() {
+            self.next_block()?;
+            for _ in 0..position.block_bytes_read {
+                self.next_block_byte()?;
             }
+        }
+        self.state = position.state;
+        self.prev_state = position.prev_state;
+        self.tape_ended = position.tape_ended;
+        self.curr_bit = position.curr_bit;
+        self.curr_byte = position.curr_byte;
+        self.delay = position.delay;
+        Ok(())
+    }
 
-            let mut buffer_read_pos = self.block_bytes_read - self.buffer_offset;
+    /// Decodes all tape blocks to the pulses list, see [InstantTape]. Current pause scale is
+    /// preserved
+    pub fn decode(mut self) -> Result<InstantTape> {
+        let mut edges = Vec::new();
+        let mut block_starts = Vec::new();
+        let mut pauses = Vec::new();
 
-            // Read new buffer if required
-            if buffer_read_pos >= BUFFER_SIZE {
-                let bytes_to_read =
-                    (block_size - self.buffer_offset - BUFFER_SIZE).min(BUFFER_SIZE);
-                self.asset.read_exact(&mut self.buffer[0..bytes_to_read])?;
-                self.buffer_offset += BUFFER_SIZE;
-                buffer_read_pos = 0;
+        self.rewind()?;
+        self.state = TapeState::Play;
+        loop {
+            let prev_state = self.state;
+            // Each state machine step produces exactly one edge
+            self.step()?;
+            if self.state == TapeState::Stop {
+                break;
             }
-
-            // Check last byte in block
-            if self.block_bytes_read >= block_size {
-                self.current_block_size = None;
-                self.block_bytes_read = 0;
-                return Ok(None);
+            if prev_state == TapeState::Play {
+                block_starts.push(edges.len());
+            } else if self.state == TapeState::Play {
+                // Pause is the only step after which the next block is requested
+                pauses.push(edges.len());
             }
-
-            // Perform actual read and advance position
-            let result = self.buffer[buffer_read_pos];
-            self.block_bytes_read += 1;
-            return Ok(Some(result));
+            edges.push((self.curr_bit, self.delay));
         }
 
-        Ok(None)
+        let mut tape = InstantTape::new(edges, block_starts, pauses, self.pause_length);
+        tape.set_pause_scale(self.pause_scale);
+        Ok(tape)
     }
 
-    fn next_block(&mut self) -> Result<bool> {
-        if self.tape_ended {
-            return Ok(false);
-        }
-
-        // Skip leftovers from the previous block
-        while self.next_block_byte()?.is_some() {}
-
-        let mut block_size_buffer = [0u8; 2];
-        if self.asset.read_exact(&mut block_size_buffer).is_err() {
-            self.tape_ended = true;
-            return Ok(false);
-        }
-        let block_size = u16::from_le_bytes(block_size_buffer) as usize;
-        let block_bytes_to_read = block_size.min(BUFFER_SIZE);
+    /// Reads `len` bytes of the current block to the start of the buffer
+    fn read_block_data(&mut self, len: usize) -> Result<()> {
+        let offset = self.block_offset;
         self.asset
-            .read_exact(&mut self.buffer[0..block_bytes_to_read])?;
-
-        self.buffer_offset = 0;
-        self.block_bytes_read = 0;
-        self.current_block_size = Some(block_size);
-
-        Ok(true)
+            .read_exact(&mut self.buffer[0..len])
+            .map_err(|e| match e {
+                IoError::UnexpectedEof => TapeLoadError::TruncatedBlock { offset }.into(),
+                e => e.into(),
+            })
     }
 
-    fn current_bit(&self) -> bool {
-        self.curr_bit
-    }
-
-    fn process_clocks(&mut self, clocks: usize) -> Result<()> {
-        if self.state == TapeState::Stop {
-            return Ok(());
-        }
-
-        if self.delay > 0 {
-            if clocks > self.delay {
-                self.delay = 0;
-            } else {
-                self.delay -= clocks;
-            }
+    /// Runs a single state machine step, which emits the next edge
+    fn step(&mut self) -> Result<()> {
+        #[cfg(test)]
+        if let Some(length) = self.injected_pulses.pop_front() {
+            self.emit_pulse(length);
             return Ok(());
         }
 
@@ -158,38 +337,40 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
                     if !self.next_block()? {
                         self.state = TapeState::Stop;
                     } else {
-                        let first_byte = self
-                            .next_block_byte()?
-                            .ok_or(TapeLoadError::InvalidTapFile)?;
+                        let first_byte =
+                            self.next_block_byte()?.ok_or(TapeLoadError::EmptyBlock {
+                                offset: self.block_offset,
+                            })?;
 
                         // Select appropriate pulse count for Pilot sequence
                         let pulses_left = if first_byte == 0x00 {
-                            PILOT_PULSES_HEADER
+                            self.timings.pilot_pulses_header
                         } else {
-                            PILOT_PULSES_DATA
+                            self.timings.pilot_pulses_data
                         };
                         self.curr_byte = first_byte;
+                        if !self.curr_bit {
+                            self.edge_count += 1;
+                        }
                         self.curr_bit = true;
-                        self.delay = PILOT_LENGTH;
+                        self.delay = self.timings.pilot_length;
                         self.state = TapeState::Pilot { pulses_left };
                         break 'state_machine;
                     }
                 }
                 TapeState::Pilot { mut pulses_left } => {
-                    self.curr_bit = !self.curr_bit;
                     pulses_left -= 1;
                     if pulses_left == 0 {
-                        self.delay = SYNC1_LENGTH;
+                        self.emit_pulse(self.timings.sync1_length);
                         self.state = TapeState::Sync;
                     } else {
-                        self.delay = PILOT_LENGTH;
+                        self.emit_pulse(self.timings.pilot_length);
                         self.state = TapeState::Pilot { pulses_left };
                     }
                     break 'state_machine;
                 }
                 TapeState::Sync => {
-                    self.curr_bit = !self.curr_bit;
-                    self.delay = SYNC2_LENGTH;
+                    self.emit_pulse(self.timings.sync2_length);
                     self.state = TapeState::NextBit { mask: 0x80 };
                     break 'state_machine;
                 }
@@ -202,19 +383,15 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
                     }
                 }
                 TapeState::NextBit { mask } => {
-                    self.curr_bit = !self.curr_bit;
-                    if (self.curr_byte & mask) == 0 {
-                        self.delay = BIT_ZERO_LENGTH;
-                        self.state = TapeState::BitHalf {
-                            half_bit_delay: BIT_ZERO_LENGTH,
-                            mask,
-                        };
+                    let half_bit_delay = if (self.curr_byte & mask) == 0 {
+                        self.timings.bit_zero_length
                     } else {
-                        self.delay = BIT_ONE_LENGTH;
-                        self.state = TapeState::BitHalf {
-                            half_bit_delay: BIT_ONE_LENGTH,
-                            mask,
-                        };
+                        self.timings.bit_one_length
+                    };
+                    self.emit_pulse(half_bit_delay);
+                    self.state = TapeState::BitHalf {
+                        half_bit_delay,
+                        mask,
                     };
                     break 'state_machine;
                 }
@@ -222,8 +399,7 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
                     half_bit_delay,
                     mut mask,
                 } => {
-                    self.curr_bit = !self.curr_bit;
-                    self.delay = half_bit_delay;
+                    self.emit_pulse(half_bit_delay);
                     mask >>= 1;
                     self.state = if mask == 0 {
                         TapeState::NextByte
@@ -233,8 +409,7 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
                     break 'state_machine;
                 }
                 TapeState::Pause => {
-                    self.curr_bit = !self.curr_bit;
-                    self.delay = PAUSE_LENGTH;
+                    self.emit_pulse((self.pause_length as f32 * self.pause_scale) as usize);
                     // Next block or end of the tape
                     self.state = TapeState::Play;
                     break 'state_machine;
@@ -245,8 +420,175 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
         Ok(())
     }
 
+    /// Returns index of the block at the current asset position
+    fn current_block_index(&self) -> usize {
+        self.blocks
+            .partition_point(|(start, _)| *start <= self.block_offset)
+            .saturating_sub(1)
+    }
+
+    /// Toggles tape bit and holds it for `length` clocks
+    fn emit_pulse(&mut self, length: usize) {
+        self.curr_bit = !self.curr_bit;
+        self.edge_count += 1;
+        self.delay = length;
+    }
+
+    /// Plays raw pulses of given lengths (in clocks) before continuing with the tape blocks
+    #[cfg(test)]
+    pub(crate) fn inject_pulses(&mut self, pulses: &[usize]) {
+        self.injected_pulses.extend(pulses);
+        if self.state == TapeState::Stop {
+            self.state = TapeState::Play;
+        }
+    }
+}
+
+impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
+    fn can_fast_load(&self) -> bool {
+        self.state == TapeState::Stop && self.timings.matches_rom_loader()
+    }
+
+    fn next_block_byte(&mut self) -> Result<Option<u8>> {
+        if self.tape_ended {
+            return Ok(None);
+        }
+
+        if let Some(block_size) = self.current_block_size {
+            if self.block_bytes_read >= block_size {
+                return Ok(None);
+            }
+
+            // Current buffer window is exhausted, read the next window of the block
+            let buffer_size = self.buffer.len();
+            if self.block_bytes_read - self.buffer_offset >= buffer_size {
+                self.buffer_offset += buffer_size;
+                let bytes_to_read = (block_size - self.buffer_offset).min(buffer_size);
+                self.read_block_data(bytes_to_read)?;
+            }
+
+            // Perform actual read and advance position
+            let result = self.buffer[self.block_bytes_read - self.buffer_offset];
+            self.block_bytes_read += 1;
+            self.block_checksum ^= result;
+            // Last byte of the block is the checksum, XOR of all block bytes including it
+            // should be zero
+            if self.block_bytes_read == block_size && block_size > 1 && self.block_checksum != 0 {
+                log_warn!(
+                    "Tap block {} checksum mismatch: expected {:#04X}, got {:#04X}",
+                    self.current_block_index(),
+                    result,
+                    self.block_checksum ^ result
+                );
+                self.events.push_back(TapeEvent::ChecksumMismatch {
+                    block: self.current_block_index(),
+                    expected: result,
+                    got: self.block_checksum ^ result,
+                });
+            }
+            return Ok(Some(result));
+        }
+
+        Ok(None)
+    }
+
+    fn next_block(&mut self) -> Result<bool> {
+        if self.tape_ended {
+            return Ok(false);
+        }
+
+        // Skip leftovers from the previous block
+        while self.next_block_byte()?.is_some() {}
+
+        self.block_offset = self.asset.seek(SeekFrom::Current(0))?;
+        let offset = self.block_offset;
+        // Block sizes are known from the headers scan, so only block data is read here
+        let block_size = match self
+            .blocks
+            .binary_search_by_key(&offset, |(start, _)| *start)
+        {
+            Ok(index) => self.blocks[index].1,
+            Err(_) if offset >= self.asset_len => {
+                self.tape_ended = true;
+                return Ok(false);
+            }
+            Err(_) => return Err(TapeLoadError::TruncatedBlockHeader { offset }.into()),
+        };
+        // Do not trust the declared size if the block does not fit into the asset
+        let data_offset = offset + BLOCK_HEADER_SIZE;
+        if data_offset + block_size > self.asset_len {
+            return Err(TapeLoadError::TruncatedBlock { offset }.into());
+        }
+        self.asset.seek(SeekFrom::Start(data_offset))?;
+        self.read_block_data(block_size.min(self.buffer.len()))?;
+
+        self.buffer_offset = 0;
+        self.block_bytes_read = 0;
+        self.block_checksum = 0;
+        self.current_block_size = Some(block_size);
+
+        Ok(true)
+    }
+
+    fn current_bit(&self) -> bool {
+        self.curr_bit
+    }
+
+    fn edge_count(&self) -> u64 {
+        self.edge_count
+    }
+
+    fn process_clocks(&mut self, mut clocks: usize) -> Result<()> {
+        if self.state == TapeState::Stop {
+            return Ok(());
+        }
+
+        if self.delay == 0 {
+            self.step()?;
+            clocks = clocks.saturating_sub(1);
+        }
+
+        // Each state machine step takes a single clock. Clocks left after the current pulse
+        // are carried over to the following steps, so edges are not skipped when `clocks`
+        // span several pulses
+        while self.state != TapeState::Stop {
+            if self.delay > clocks {
+                self.delay -= clocks;
+                break;
+            }
+            clocks -= self.delay;
+            self.delay = 0;
+            if clocks == 0 {
+                break;
+            }
+            clocks -= 1;
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
     fn stop(&mut self) {
-        let state = self.state;
+        // Bit which was interrupted is replayed from its start on resume, as resuming in the
+        // middle of the bit would corrupt its pulses for the loader. `NextBit` and `NextByte`
+        // states are waiting for the end of the second half of the previous bit here. First
+        // bit of the byte is started in the same step as the byte is read, so `NextBit` with
+        // the first bit mask is waiting for the end of the second sync pulse, which is replayed
+        let (state, edges) = match self.state {
+            TapeState::BitHalf { mask, .. } => (TapeState::NextBit { mask }, 1),
+            TapeState::NextBit { mask: 0x80 } => (TapeState::Sync, 1),
+            TapeState::NextBit { mask } => (TapeState::NextBit { mask: mask << 1 }, 2),
+            TapeState::NextByte => (TapeState::NextBit { mask: 0x01 }, 2),
+            state => (state, 0),
+        };
+        if edges != 0 {
+            // Level before the first edge of the bit
+            if edges % 2 != 0 {
+                self.curr_bit = !self.curr_bit;
+            }
+            self.edge_count -= edges;
+            self.delay = 0;
+        }
         self.prev_state = state;
         self.state = TapeState::Stop;
     }
@@ -261,15 +603,524 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
         }
     }
 
+    fn is_playing(&self) -> bool {
+        self.state != TapeState::Stop
+    }
+
+    fn set_pause_scale(&mut self, scale: f32) {
+        self.pause_scale = scale.max(0.0);
+    }
+
+    fn restart_block(&mut self) -> Result<()> {
+        self.asset.seek(SeekFrom::Start(self.block_offset))?;
+        self.curr_bit = false;
+        self.curr_byte = 0x00;
+        self.block_bytes_read = 0;
+        self.buffer_offset = 0;
+        self.current_block_size = None;
+        self.delay = 0;
+        self.tape_ended = false;
+        // Block is replayed starting from the pilot tone
+        if self.state != TapeState::Stop {
+            self.state = TapeState::Play;
+        } else if self.prev_state != TapeState::Stop {
+            self.prev_state = TapeState::Play;
+        }
+        Ok(())
+    }
+
+    fn seek_block(&mut self, block: usize) -> Result<()> {
+        let last_block = self.blocks.len().saturating_sub(1);
+        if let Some(&(offset, _)) = self.blocks.get(block.min(last_block)) {
+            self.block_offset = offset;
+            self.restart_block()?;
+        }
+        Ok(())
+    }
+
+    fn transport_status(&self) -> TransportStatus {
+        let block_progress = match self.current_block_size {
+            Some(size) if size > 0 => self.block_bytes_read as f32 / size as f32,
+            _ => 0.0,
+        };
+        TransportStatus {
+            playing: self.is_playing(),
+            block: self.current_block_index(),
+            total_blocks: self.blocks.len(),
+            block_progress,
+        }
+    }
+
     fn rewind(&mut self) -> Result<()> {
         self.curr_bit = false;
+        self.edge_count = 0;
         self.curr_byte = 0x00;
         self.block_bytes_read = 0;
         self.buffer_offset = 0;
         self.current_block_size = None;
+        self.block_offset = 0;
         self.delay = 0;
         self.asset.seek(SeekFrom::Start(0))?;
         self.tape_ended = false;
         Ok(())
     }
+
+    fn take_event(&mut self) -> Option<TapeEvent> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, host::BufferCursor, zx::testing::tap_image};
+
+    /// Runs tape clock-by-clock and returns clocks and levels at which tape bit has changed
+    fn collect_edges(tap: &mut Tap<BufferCursor<Vec<u8>>>, clocks: usize) -> Vec<(usize, bool)> {
+        let mut edges = Vec::new();
+        let mut level = tap.current_bit();
+        for clock in 1..=clocks {
+            tap.process_clocks(1).unwrap();
+            if tap.current_bit() != level {
+                level = tap.current_bit();
+                edges.push((clock, level));
+            }
+        }
+        edges
+    }
+
+    #[test]
+    fn large_block_is_read_across_buffer_windows() {
+        let block = (0..300).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let asset = BufferCursor::new(tap_image(&[&block, &[0xAA, 0x55]]));
+        let mut tap = Tap::with_buffer_size(asset, ZXMachine::Sinclair48K, 128).unwrap();
+
+        assert!(tap.next_block().unwrap());
+        let mut actual = Vec::new();
+        while let Some(byte) = tap.next_block_byte().unwrap() {
+            actual.push(byte);
+        }
+        assert_eq!(actual, block);
+
+        // Next block should start right after the large one
+        assert!(tap.next_block().unwrap());
+        assert_eq!(tap.next_block_byte().unwrap(), Some(0xAA));
+        assert_eq!(tap.next_block_byte().unwrap(), Some(0x55));
+        assert_eq!(tap.next_block_byte().unwrap(), None);
+        assert!(!tap.next_block().unwrap());
+    }
+
+    /// Asset which counts read requests
+    struct CountingAsset {
+        cursor: BufferCursor<Vec<u8>>,
+        reads: usize,
+    }
+
+    impl LoadableAsset for CountingAsset {
+        fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, IoError> {
+            self.reads += 1;
+            self.cursor.read(buf)
+        }
+    }
+
+    impl SeekableAsset for CountingAsset {
+        fn seek(&mut self, pos: SeekFrom) -> core::result::Result<usize, IoError> {
+            self.cursor.seek(pos)
+        }
+    }
+
+    fn reads_for_block(block_size: usize, buffer_size: usize) -> usize {
+        let block = vec![0x55; block_size];
+        let asset = CountingAsset {
+            cursor: BufferCursor::new(tap_image(&[&block])),
+            reads: 0,
+        };
+        let mut tap = Tap::with_buffer_size(asset, ZXMachine::Sinclair48K, buffer_size).unwrap();
+        assert!(tap.next_block().unwrap());
+        let mut bytes = 0;
+        while tap.next_block_byte().unwrap().is_some() {
+            bytes += 1;
+        }
+        assert_eq!(bytes, block_size);
+        tap.asset.reads
+    }
+
+    #[test]
+    fn asset_reads_scale_with_buffer_size() {
+        let block_size: usize = 60000;
+        for buffer_size in [128, 1024, DEFAULT_BUFFER_SIZE] {
+            // Block headers scan and one read per buffer window
+            let expected = 1 + block_size.div_ceil(buffer_size);
+            assert_eq!(reads_for_block(block_size, buffer_size), expected);
+        }
+    }
+
+    /// Runs single state machine step from `state` and returns the resulting delay
+    fn state_delay(tap: &mut Tap<BufferCursor<Vec<u8>>>, state: TapeState) -> usize {
+        tap.state = state;
+        tap.delay = 0;
+        tap.process_clocks(1).unwrap();
+        tap.delay
+    }
+
+    #[test]
+    fn long_pause_fits_32_bit_delay() {
+        // Longest pause representable in TZX files, in seconds
+        const LONG_PAUSE_SECONDS: usize = 65;
+
+        let asset = BufferCursor::new(tap_image(&[&[0xFF, 0x80]]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        let pause_length = ZXMachine::Sinclair48K.specs().freq_cpu;
+        tap.set_pause_scale(LONG_PAUSE_SECONDS as f32);
+
+        let delay = state_delay(&mut tap, TapeState::Pause);
+        assert_eq!(delay, pause_length * LONG_PAUSE_SECONDS);
+        assert!(u32::try_from(delay).is_ok());
+    }
+
+    #[test]
+    fn edge_count_matches_standard_block_structure() {
+        let block = [0x00, 0x03, 0x41, 0x42, 0x43];
+        let asset = BufferCursor::new(tap_image(&[&block, &[0xFF, 0x55]]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        tap.play();
+
+        // Pilot tone, two sync pulses, two pulses per data bit and pause
+        let header_edges = (PILOT_PULSES_HEADER + 2 + block.len() * 16 + 1) as u64;
+        let data_edges = (PILOT_PULSES_DATA + 2 + 2 * 16 + 1) as u64;
+        tap.step().unwrap();
+        while tap.state != TapeState::Play {
+            tap.step().unwrap();
+        }
+        assert_eq!(tap.edge_count(), header_edges);
+        tap.step().unwrap();
+        while tap.state != TapeState::Play {
+            tap.step().unwrap();
+        }
+        assert_eq!(tap.edge_count(), header_edges + data_edges);
+
+        tap.rewind().unwrap();
+        assert_eq!(tap.edge_count(), 0);
+    }
+
+    #[test]
+    fn pause_scale_affects_only_pauses() {
+        let asset = BufferCursor::new(tap_image(&[&[0xFF, 0x80]]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        let pause_length = ZXMachine::Sinclair48K.specs().freq_cpu;
+        tap.curr_byte = 0x80;
+
+        assert_eq!(state_delay(&mut tap, TapeState::Pause), pause_length);
+        tap.set_pause_scale(0.5);
+        assert_eq!(state_delay(&mut tap, TapeState::Pause), pause_length / 2);
+
+        tap.set_pause_scale(0.0);
+        assert_eq!(state_delay(&mut tap, TapeState::Pause), 0);
+        let pilot = TapeState::Pilot { pulses_left: 10 };
+        assert_eq!(state_delay(&mut tap, pilot), PILOT_LENGTH);
+        assert_eq!(state_delay(&mut tap, TapeState::Sync), SYNC2_LENGTH);
+        let one = TapeState::NextBit { mask: 0x80 };
+        assert_eq!(state_delay(&mut tap, one), BIT_ONE_LENGTH);
+        let zero = TapeState::NextBit { mask: 0x40 };
+        assert_eq!(state_delay(&mut tap, zero), BIT_ZERO_LENGTH);
+    }
+
+    #[test]
+    fn overridden_timings_change_pulse_lengths() {
+        let asset = BufferCursor::new(tap_image(&[&[0xFF, 0x80]]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        tap.curr_byte = 0x80;
+        let one = TapeState::NextBit { mask: 0x80 };
+        let zero = TapeState::NextBit { mask: 0x40 };
+        assert!(tap.can_fast_load());
+
+        tap.override_timings(TapeTimingsBuilder::new().bits(800, 1600).build());
+        assert_eq!(state_delay(&mut tap, one), 1600);
+        assert_eq!(state_delay(&mut tap, zero), 800);
+        let pilot = TapeState::Pilot { pulses_left: 10 };
+        assert_eq!(state_delay(&mut tap, pilot), PILOT_LENGTH);
+        tap.state = TapeState::Stop;
+        assert!(!tap.can_fast_load());
+
+        let timings = TapeTimingsBuilder::new()
+            .bits(800, 1600)
+            .fast_load_tolerance(BIT_ONE_LENGTH - 1600)
+            .build();
+        tap.override_timings(timings);
+        assert!(tap.can_fast_load());
+    }
+
+    #[test]
+    fn stopped_bit_is_replayed_from_its_start() {
+        let asset = BufferCursor::new(tap_image(&[&[0xFF, 0x0F]]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+
+        // Returns levels and lengths of the next `count` pulses
+        let pulses = |tap: &mut Tap<BufferCursor<Vec<u8>>>, count: usize| {
+            (0..count)
+                .map(|_| {
+                    tap.step().unwrap();
+                    (tap.curr_bit, tap.delay)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Stop during the second sync pulse, which precedes the first flag bit
+        tap.play();
+        while tap.state != (TapeState::NextBit { mask: 0x80 }) {
+            tap.step().unwrap();
+        }
+        let edge_count = tap.edge_count();
+        let sync_level = tap.curr_bit;
+        tap.stop();
+        assert_eq!(tap.edge_count(), edge_count - 1);
+        tap.play();
+        let [sync, (level, length)] = pulses(&mut tap, 2)[..] else {
+            unreachable!()
+        };
+        assert_eq!(sync, (sync_level, SYNC2_LENGTH));
+        assert_eq!(length, BIT_ONE_LENGTH);
+
+        // Stop in the first half of the first flag bit
+        let edge_count = tap.edge_count();
+        tap.stop();
+        assert_eq!(tap.edge_count(), edge_count - 1);
+        tap.play();
+        assert_eq!(
+            pulses(&mut tap, 2),
+            [(level, BIT_ONE_LENGTH), (!level, BIT_ONE_LENGTH)]
+        );
+        assert_eq!(tap.edge_count(), edge_count + 1);
+
+        // Stop in the second half of the last bit of the flag byte
+        while tap.state != TapeState::NextByte {
+            tap.step().unwrap();
+        }
+        let level = tap.curr_bit;
+        tap.stop();
+        tap.play();
+        assert_eq!(
+            pulses(&mut tap, 4),
+            [
+                (!level, BIT_ONE_LENGTH),
+                (level, BIT_ONE_LENGTH),
+                (!level, BIT_ZERO_LENGTH),
+                (level, BIT_ZERO_LENGTH)
+            ]
+        );
+    }
+
+    #[test]
+    fn restart_block_replays_only_current_block() {
+        let asset = BufferCursor::new(tap_image(&[&[0x00, 0x11], &[0xFF, 0x22]]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        let second_block_offset = 4;
+
+        // Play until the data of the second block
+        tap.play();
+        while !(tap.block_offset == second_block_offset
+            && matches!(tap.state, TapeState::BitHalf { .. }))
+        {
+            tap.step().unwrap();
+        }
+
+        tap.restart_block().unwrap();
+        let mut started_blocks = Vec::new();
+        while tap.is_playing() {
+            let prev_state = tap.state;
+            tap.step().unwrap();
+            if prev_state == TapeState::Play && matches!(tap.state, TapeState::Pilot { .. }) {
+                started_blocks.push((tap.block_offset, tap.curr_byte));
+            }
+        }
+        assert_eq!(started_blocks, [(second_block_offset, 0xFF)]);
+    }
+
+    #[test]
+    fn malformed_blocks_yield_specific_errors() {
+        let play_until_error = |image: Vec<u8>| {
+            let mut tap =
+                Tap::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K).unwrap();
+            tap.play();
+            loop {
+                match tap.process_clocks(PILOT_LENGTH) {
+                    Ok(()) if tap.is_playing() => {}
+                    Ok(()) => return None,
+                    Err(Error::TapeLoad(e)) => return Some(e),
+                    Err(e) => panic!("Unexpected error: {}", e),
+                }
+            }
+        };
+
+        let mut truncated = tap_image(&[&[0x00, 0x11]]);
+        truncated.extend_from_slice(&[0x03, 0x00, 0xFF]);
+        assert!(matches!(
+            play_until_error(truncated),
+            Some(TapeLoadError::TruncatedBlock { offset: 4 })
+        ));
+
+        let mut truncated_header = tap_image(&[&[0x00, 0x11]]);
+        truncated_header.push(0x03);
+        assert!(matches!(
+            play_until_error(truncated_header),
+            Some(TapeLoadError::TruncatedBlockHeader { offset: 4 })
+        ));
+
+        assert!(matches!(
+            play_until_error(tap_image(&[&[]])),
+            Some(TapeLoadError::EmptyBlock { offset: 0 })
+        ));
+
+        assert!(play_until_error(tap_image(&[&[0xFF, 0x22]])).is_none());
+    }
+
+    #[test]
+    fn block_size_past_end_of_file_is_rejected() {
+        let mut image = tap_image(&[&[0x00, 0x11]]);
+        image.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0x01, 0x02]);
+        let mut tap = Tap::from_asset(BufferCursor::new(image), ZXMachine::Sinclair48K).unwrap();
+        assert!(tap.next_block().unwrap());
+        assert!(matches!(
+            tap.next_block(),
+            Err(Error::TapeLoad(TapeLoadError::TruncatedBlock { offset: 4 }))
+        ));
+    }
+
+    #[test]
+    fn seek_block_moves_to_block_start() {
+        let blocks: [&[u8]; 3] = [&[0x00, 0x11], &[0xFF, 0x22], &[0xFF, 0x33]];
+        let asset = BufferCursor::new(tap_image(&blocks));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+
+        // Play until the data of the second block
+        tap.play();
+        while !(tap.transport_status().block == 1 && matches!(tap.state, TapeState::BitHalf { .. }))
+        {
+            tap.process_clocks(1000).unwrap();
+        }
+        tap.seek_block(2).unwrap();
+        assert_eq!(tap.block_offset, tap.blocks[2].0);
+        assert!(tap.is_playing());
+        tap.process_clocks(1).unwrap();
+        assert_eq!(tap.curr_byte, 0xFF);
+        assert_eq!(tap.transport_status().block, 2);
+
+        // Seeking past the end stays at the last block
+        tap.seek_block(3).unwrap();
+        assert_eq!(tap.block_offset, tap.blocks[2].0);
+
+        tap.stop();
+        tap.seek_block(0).unwrap();
+        assert_eq!(tap.block_offset, 0);
+        assert!(!tap.is_playing());
+    }
+
+    #[test]
+    fn corrupted_checksum_is_reported() {
+        // Second block has checksum 0x00 instead of 0xFF ^ 0x12 ^ 0x34 = 0xD9
+        let asset = BufferCursor::new(tap_image(&[&[0x00, 0x11, 0x11], &[0xFF, 0x12, 0x34, 0x00]]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        tap.play();
+        let mut events = Vec::new();
+        while tap.is_playing() {
+            tap.process_clocks(1000).unwrap();
+            events.extend(tap.take_event());
+        }
+        assert_eq!(
+            events,
+            [TapeEvent::ChecksumMismatch {
+                block: 1,
+                expected: 0x00,
+                got: 0xD9,
+            }]
+        );
+    }
+
+    #[test]
+    fn checksums_of_skipped_blocks_are_reported() {
+        let asset = BufferCursor::new(tap_image(&[
+            &[0x00, 0x11, 0x00],
+            &[0xFF, 0x12, 0x34, 0x00],
+            &[0xFF, 0x55, 0xAA],
+        ]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        // Skipping blocks reads all of their bytes at once
+        while tap.next_block().unwrap() {}
+        let events: Vec<_> = core::iter::from_fn(|| tap.take_event()).collect();
+        assert_eq!(
+            events,
+            [
+                TapeEvent::ChecksumMismatch {
+                    block: 0,
+                    expected: 0x00,
+                    got: 0x11,
+                },
+                TapeEvent::ChecksumMismatch {
+                    block: 1,
+                    expected: 0x00,
+                    got: 0xD9,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn transport_status_reports_block_progress() {
+        let data = vec![0x55; 100];
+        let asset = BufferCursor::new(tap_image(&[&[0x00, 0x11], &data]));
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        let status = tap.transport_status();
+        assert!(!status.playing);
+        assert_eq!((status.block, status.total_blocks), (0, 2));
+        assert_eq!(status.block_progress, 0.0);
+
+        assert!(tap.next_block().unwrap());
+        assert!(tap.next_block().unwrap());
+        for _ in 0..50 {
+            tap.next_block_byte().unwrap();
+        }
+        tap.play();
+        let status = tap.transport_status();
+        assert!(status.playing);
+        assert_eq!((status.block, status.total_blocks), (1, 2));
+        assert!((status.block_progress - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn injected_pulses_produce_exact_edges() {
+        let asset = BufferCursor::new(vec![]);
+        let mut tap = Tap::from_asset(asset, ZXMachine::Sinclair48K).unwrap();
+        tap.inject_pulses(&[100, 200, 300]);
+
+        // Each edge is emitted on the next step after the previous pulse has elapsed
+        // After the last pulse the end of the empty tape is reached, which rewinds the tape and
+        // resets the tape bit
+        let edges = collect_edges(&mut tap, 1000);
+        assert_eq!(edges, [(1, true), (102, false), (303, true), (604, false)]);
+        assert!(!tap.is_playing());
+    }
+
+    #[test]
+    fn large_clocks_step_emits_all_covered_edges() {
+        let new_tap = || {
+            let mut tap =
+                Tap::from_asset(BufferCursor::new(vec![]), ZXMachine::Sinclair48K).unwrap();
+            tap.inject_pulses(&[100, 200, 300, 400]);
+            tap
+        };
+
+        // Edges at clocks 1, 102 and 303 are covered by the single step, only the last pulse
+        // is left in the queue
+        let mut tap = new_tap();
+        tap.process_clocks(350).unwrap();
+        assert_eq!(tap.injected_pulses.len(), 1);
+        assert!(tap.current_bit());
+        assert_eq!(tap.delay, 300 - (350 - 303));
+
+        // Same state is reached when stepping clock by clock
+        let mut reference = new_tap();
+        assert_eq!(collect_edges(&mut reference, 350).len(), 3);
+        assert_eq!(reference.delay, tap.delay);
+        assert_eq!(reference.injected_pulses, tap.injected_pulses);
+    }
 }
@@ -0,0 +1,134 @@
+//! Helpers for unit tests which require complete ZX Spectrum controller or tape images
+use crate::{
+    host::{
+        BufferCursor, Duration, Host, HostContext, Stopwatch, StubDebugInterface, StubIoExtender,
+        StubTraceSink, TapeListener,
+    },
+    settings::RustzxSettings,
+    utils::EmulationMode,
+    zx::{
+        controller::ZXController, machine::ZXMachine, tape::TapeImpl,
+        video::testing::TestFrameBuffer,
+    },
+};
+use alloc::vec::Vec;
+
+pub(crate) struct TestStopwatch;
+
+impl Stopwatch for TestStopwatch {
+    fn new() -> Self {
+        Self
+    }
+
+    fn measure(&self) -> Duration {
+        Duration::from_millis(0)
+    }
+}
+
+pub(crate) struct TestContext;
+
+impl HostContext<TestHost> for TestContext {
+    fn frame_buffer_context(&self) {}
+}
+
+pub(crate) struct TestHost;
+
+impl Host for TestHost {
+    type Context = TestContext;
+    type TapeAsset = BufferCursor<Vec<u8>>;
+    type FrameBuffer = TestFrameBuffer;
+    type EmulationStopwatch = TestStopwatch;
+    type IoExtender = StubIoExtender;
+    type DebugInterface = StubDebugInterface;
+    type TapeListener = TestTapeListener;
+    type TraceSink = StubTraceSink;
+}
+
+/// Tape listener which counts received notifications
+#[derive(Default)]
+pub(crate) struct TestTapeListener {
+    pub stops: usize,
+}
+
+impl TapeListener for TestTapeListener {
+    fn on_tape_stopped(&mut self) {
+        self.stops += 1;
+    }
+}
+
+/// Returns settings with all optional devices disabled
+pub(crate) fn test_settings(machine: ZXMachine) -> RustzxSettings {
+    RustzxSettings {
+        machine,
+        timings: Default::default(),
+        issue: Default::default(),
+        emulation_mode: EmulationMode::FrameCount(1),
+        tape_fastload_enabled: false,
+        tape_auto_control_enabled: false,
+        reset_rewinds_tape: true,
+        kempston_enabled: false,
+        fuller_enabled: false,
+        mouse_enabled: false,
+        ula_snow_enabled: false,
+        fast_boot: false,
+        #[cfg(feature = "precise-border")]
+        border_size: crate::zx::video::BorderSize::Small,
+        #[cfg(all(feature = "sound", feature = "ay"))]
+        ay_mode: crate::zx::sound::ay::ZXAYMode::ABC,
+        #[cfg(all(feature = "sound", feature = "ay"))]
+        ay_enabled: false,
+        #[cfg(all(feature = "sound", feature = "ay"))]
+        turbosound_enabled: false,
+        #[cfg(feature = "sound")]
+        beeper_enabled: false,
+        #[cfg(feature = "sound")]
+        specdrum_port: None,
+        #[cfg(feature = "sound")]
+        covox_port: None,
+        #[cfg(feature = "sound")]
+        sound_enabled: false,
+        #[cfg(feature = "sound")]
+        sound_volume: 100,
+        #[cfg(feature = "sound")]
+        sound_sample_rate: 44100,
+        #[cfg(feature = "sound")]
+        sound_channels_enabled: false,
+        #[cfg(feature = "embedded-roms")]
+        load_default_rom: false,
+        #[cfg(feature = "autoload")]
+        autoload_enabled: false,
+    }
+}
+
+/// Creates controller for the given settings
+pub(crate) fn test_controller(settings: &RustzxSettings) -> ZXController<TestHost> {
+    ZXController::new(settings, TestContext)
+}
+
+/// Builds TAP image from the given blocks
+pub(crate) fn tap_image(blocks: &[&[u8]]) -> Vec<u8> {
+    let mut image = Vec::new();
+    for block in blocks {
+        image.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        image.extend_from_slice(block);
+    }
+    image
+}
+
+/// Appends PZX chunk with the given `tag` and `body` to the `image`
+pub(crate) fn push_pzx_chunk(image: &mut Vec<u8>, tag: &[u8; 4], body: &[u8]) {
+    image.extend_from_slice(tag);
+    image.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    image.extend_from_slice(body);
+}
+
+/// Plays tape by `chunk` clocks and returns tape level after each chunk
+pub(crate) fn sample_levels(tape: &mut impl TapeImpl, chunk: usize) -> Vec<bool> {
+    let mut levels = Vec::new();
+    tape.play();
+    while tape.is_playing() {
+        tape.process_clocks(chunk).unwrap();
+        levels.push(tape.current_bit());
+    }
+    levels
+}
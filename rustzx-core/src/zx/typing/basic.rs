@@ -0,0 +1,215 @@
+//! Translation of the BASIC listing to the key strokes of the 48K keyword entry mode. 48K
+//! editor expects keywords to be entered with a single key in the current cursor mode:
+//! - `K` mode (start of the statement) - statement keywords on plain letter keys
+//! - `L` mode - letters, symbols and operator keywords on Symbol Shift keys
+//! - `E` mode (Caps Shift + Symbol Shift) - functions on plain or Symbol Shift keys
+//!
+//! `C` mode is never used, uppercase letters are typed with Caps Shift instead. Text of the
+//! string literals and comments after `REM` is typed as is. Colon switches the editor to `K`
+//! mode even inside the comment, therefore colons of the comment are inserted with the cursor
+//! keys after the rest of the comment has been typed.
+use crate::{
+    error::TypingError,
+    zx::{keys::ZXKey, typing::KeyStroke},
+    Result,
+};
+use alloc::vec::Vec;
+
+/// Key entry method of the keyword
+#[derive(Clone, Copy)]
+enum Entry {
+    /// Plain key in `K` mode
+    Statement(ZXKey),
+    /// Plain key in `K` mode, rest of the line is a comment
+    Comment(ZXKey),
+    /// Symbol Shift + key in `L` mode
+    Symbol(ZXKey),
+    /// Symbol Shift + key in `L` mode, followed by the new statement in `K` mode
+    SymbolStatementStart(ZXKey),
+    /// Plain key in `E` mode
+    Extended(ZXKey),
+    /// Symbol Shift + key in `E` mode
+    ExtendedSymbol(ZXKey),
+}
+
+#[rustfmt::skip]
+const KEYWORDS: &[(&str, Entry)] = {
+    use Entry::*;
+    use ZXKey::*;
+    &[
+        // K mode statements
+        ("NEW", Statement(A)), ("BORDER", Statement(B)), ("CONTINUE", Statement(C)), ("DIM", Statement(D)), ("REM", Comment(E)),
+        ("FOR", Statement(F)), ("GO TO", Statement(G)), ("GOTO", Statement(G)), ("GO SUB", Statement(H)), ("GOSUB", Statement(H)),
+        ("INPUT", Statement(I)), ("LOAD", Statement(J)), ("LIST", Statement(K)), ("LET", Statement(L)), ("PAUSE", Statement(M)),
+        ("NEXT", Statement(N)), ("POKE", Statement(O)), ("PRINT", Statement(P)), ("PLOT", Statement(Q)), ("RUN", Statement(R)),
+        ("SAVE", Statement(S)), ("RANDOMIZE", Statement(T)), ("IF", Statement(U)), ("CLS", Statement(V)), ("DRAW", Statement(W)),
+        ("CLEAR", Statement(X)), ("RETURN", Statement(Y)), ("COPY", Statement(Z)),
+        // Symbol shift keywords
+        ("STOP", Symbol(A)), ("NOT", Symbol(S)), ("STEP", Symbol(D)), ("TO", Symbol(F)),
+        ("THEN", SymbolStatementStart(G)), ("AND", Symbol(Y)), ("OR", Symbol(U)), ("AT", Symbol(I)),
+        ("<=", Symbol(Q)), ("<>", Symbol(W)), (">=", Symbol(E)),
+        // E mode functions and statements
+        ("READ", Extended(A)), ("BIN", Extended(B)), ("LPRINT", Extended(C)),
+        ("DATA", Extended(D)), ("TAN", Extended(E)), ("SGN", Extended(F)), ("ABS", Extended(G)),
+        ("SQR", Extended(H)), ("CODE", Extended(I)), ("VAL", Extended(J)), ("LEN", Extended(K)),
+        ("USR", Extended(L)), ("PI", Extended(M)), ("INKEY$", Extended(N)),
+        ("PEEK", Extended(O)), ("TAB", Extended(P)), ("SIN", Extended(Q)), ("INT", Extended(R)),
+        ("RESTORE", Extended(S)), ("RND", Extended(T)), ("CHR$", Extended(U)),
+        ("LLIST", Extended(V)), ("COS", Extended(W)), ("EXP", Extended(X)),
+        ("STR$", Extended(Y)), ("LN", Extended(Z)),
+        // E mode symbol shift functions and statements
+        ("ASN", ExtendedSymbol(Q)), ("ACS", ExtendedSymbol(W)), ("ATN", ExtendedSymbol(E)),
+        ("VERIFY", ExtendedSymbol(R)), ("MERGE", ExtendedSymbol(T)),
+        ("CIRCLE", ExtendedSymbol(H)), ("VAL$", ExtendedSymbol(J)),
+        ("SCREEN$", ExtendedSymbol(K)), ("ATTR", ExtendedSymbol(L)), ("BEEP", ExtendedSymbol(Z)),
+        ("INK", ExtendedSymbol(X)), ("PAPER", ExtendedSymbol(C)), ("FLASH", ExtendedSymbol(V)),
+        ("BRIGHT", ExtendedSymbol(B)), ("OVER", ExtendedSymbol(N)),
+        ("INVERSE", ExtendedSymbol(M)), ("IN", ExtendedSymbol(I)), ("OUT", ExtendedSymbol(O)),
+        ("DEF FN", ExtendedSymbol(N1)), ("FN", ExtendedSymbol(N2)), ("LINE", ExtendedSymbol(N3)),
+        ("OPEN #", ExtendedSymbol(N4)), ("CLOSE #", ExtendedSymbol(N5)),
+        ("MOVE", ExtendedSymbol(N6)), ("ERASE", ExtendedSymbol(N7)),
+        ("POINT", ExtendedSymbol(N8)), ("CAT", ExtendedSymbol(N9)),
+        ("FORMAT", ExtendedSymbol(N0)),
+    ]
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CursorMode {
+    K,
+    L,
+}
+
+/// Returns keyword entry and its length in bytes if `text` starts with the keyword which can
+/// be entered in the given cursor `mode`. Longest matching keyword is selected
+fn match_keyword(text: &str, mode: CursorMode) -> Option<(Entry, usize)> {
+    let is_word_char = |ch: char| ch.is_ascii_alphanumeric() || ch == '$';
+    KEYWORDS
+        .iter()
+        .filter(|(_, entry)| {
+            mode == CursorMode::K || !matches!(entry, Entry::Statement(_) | Entry::Comment(_))
+        })
+        .filter(|(keyword, _)| {
+            let candidate = match text.get(..keyword.len()) {
+                Some(candidate) => candidate,
+                None => return false,
+            };
+            // Alphabetic keywords should not be a part of the longer word (e.g. variable name)
+            let ends_word = !keyword.ends_with(is_word_char)
+                || !text[keyword.len()..].starts_with(is_word_char);
+            candidate.eq_ignore_ascii_case(keyword) && ends_word
+        })
+        .max_by_key(|(keyword, _)| keyword.len())
+        .map(|(keyword, entry)| (*entry, keyword.len()))
+}
+
+/// Returns key stroke for `ch` on the given listing `line` (zero-based)
+fn char_stroke(ch: char, line: usize) -> Result<KeyStroke> {
+    KeyStroke::from_char(ch)
+        .ok_or_else(|| TypingError::UnsupportedCharacter { line: line + 1, ch }.into())
+}
+
+/// Translates BASIC `program` to the key strokes required to enter it in 48K BASIC editor
+pub fn keyword_entry_strokes(program: &str) -> Result<Vec<KeyStroke>> {
+    let mut strokes = Vec::new();
+    let extended_mode = KeyStroke::with_modifier(ZXKey::Shift, ZXKey::SymShift);
+    let cursor_left = KeyStroke::with_modifier(ZXKey::Shift, ZXKey::N5);
+    let colon = KeyStroke::with_modifier(ZXKey::SymShift, ZXKey::Z);
+
+    for (line_index, line) in program.lines().enumerate() {
+        let mut mode = CursorMode::K;
+        let mut in_string = false;
+        let mut in_comment = false;
+        // Count of typed comment characters and positions of the skipped colons in it
+        let mut comment_length = 0;
+        let mut comment_colons = Vec::new();
+        let mut rest = line.trim();
+        while let Some(ch) = rest.chars().next() {
+            let literal = in_string || in_comment;
+            let keyword = if literal {
+                None
+            } else {
+                match_keyword(rest, mode)
+            };
+            if let Some((entry, length)) = keyword {
+                match entry {
+                    Entry::Statement(key) | Entry::Comment(key) => {
+                        strokes.push(KeyStroke::new(key))
+                    }
+                    Entry::Symbol(key) | Entry::SymbolStatementStart(key) => {
+                        strokes.push(KeyStroke::with_modifier(ZXKey::SymShift, key))
+                    }
+                    Entry::Extended(key) => {
+                        strokes.push(extended_mode);
+                        strokes.push(KeyStroke::new(key));
+                    }
+                    Entry::ExtendedSymbol(key) => {
+                        strokes.push(extended_mode);
+                        strokes.push(KeyStroke::with_modifier(ZXKey::SymShift, key));
+                    }
+                }
+                mode = match entry {
+                    Entry::SymbolStatementStart(_) => CursorMode::K,
+                    _ => CursorMode::L,
+                };
+                in_comment = matches!(entry, Entry::Comment(_));
+                // Editor inserts spaces around keywords automatically
+                rest = rest[length..].trim_start();
+                continue;
+            }
+
+            if in_comment {
+                if ch == ':' {
+                    comment_colons.push(comment_length);
+                } else {
+                    strokes.push(char_stroke(ch, line_index)?);
+                    comment_length += 1;
+                }
+                rest = &rest[ch.len_utf8()..];
+                continue;
+            }
+
+            match ch {
+                '"' => in_string = !in_string,
+                ':' if !literal => mode = CursorMode::K,
+                ' ' if !literal => {
+                    // Spaces before keywords are inserted by the editor
+                    let next = rest.trim_start();
+                    if match_keyword(next, mode).is_some() {
+                        rest = next;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            strokes.push(char_stroke(ch, line_index)?);
+            rest = &rest[ch.len_utf8()..];
+        }
+        // Insert colons from the end of the comment, moving the cursor to the left over the
+        // typed characters and the colons inserted before
+        let mut cursor = comment_length;
+        let mut after_colon = false;
+        for &position in comment_colons.iter().rev() {
+            let moves = cursor - position + usize::from(after_colon);
+            strokes.extend(core::iter::repeat_n(cursor_left, moves));
+            strokes.push(colon);
+            cursor = position;
+            after_colon = true;
+        }
+        strokes.push(KeyStroke::new(ZXKey::Enter));
+    }
+
+    Ok(strokes)
+}
+
+/// Translates BASIC `program` to the key strokes required to type it letter by letter, as
+/// expected by 128K BASIC editor
+pub fn letter_entry_strokes(program: &str) -> Result<Vec<KeyStroke>> {
+    let mut strokes = Vec::new();
+    for (line_index, line) in program.lines().enumerate() {
+        for ch in line.trim().chars() {
+            strokes.push(char_stroke(ch, line_index)?);
+        }
+        strokes.push(KeyStroke::new(ZXKey::Enter));
+    }
+    Ok(strokes)
+}
@@ -0,0 +1,191 @@
+//! Programmatic keyboard input. Typed keys are kept in a separate keyboard matrix layer, so
+//! they do not interfere with the live user input
+pub(crate) mod basic;
+
+use crate::zx::keys::ZXKey;
+use alloc::collections::VecDeque;
+
+/// Frames during which the key is held pressed
+const KEY_HOLD_FRAMES: usize = 2;
+/// Frames during which all keys are released before the next key press, required by ROM to
+/// recognize repeated presses of the same key
+const KEY_RELEASE_FRAMES: usize = 5;
+
+/// Single key press, optionally combined with the modifier key
+#[derive(Debug, Clone, Copy)]
+pub struct KeyStroke {
+    pub modifier: Option<ZXKey>,
+    pub key: ZXKey,
+}
+
+impl KeyStroke {
+    pub fn new(key: ZXKey) -> Self {
+        Self {
+            modifier: None,
+            key,
+        }
+    }
+
+    pub fn with_modifier(modifier: ZXKey, key: ZXKey) -> Self {
+        Self {
+            modifier: Some(modifier),
+            key,
+        }
+    }
+
+    /// Translates ASCII character to the ZX Spectrum key stroke. Uppercase letters are typed
+    /// with Caps Shift, symbols are typed with Symbol Shift. Returns `None` if the character
+    /// can't be typed with a single key stroke
+    pub fn from_char(ch: char) -> Option<Self> {
+        use ZXKey::*;
+
+        let letter = |ch: char| {
+            Some(match ch.to_ascii_lowercase() {
+                'a' => A,
+                'b' => B,
+                'c' => C,
+                'd' => D,
+                'e' => E,
+                'f' => F,
+                'g' => G,
+                'h' => H,
+                'i' => I,
+                'j' => J,
+                'k' => K,
+                'l' => L,
+                'm' => M,
+                'n' => N,
+                'o' => O,
+                'p' => P,
+                'q' => Q,
+                'r' => R,
+                's' => S,
+                't' => T,
+                'u' => U,
+                'v' => V,
+                'w' => W,
+                'x' => X,
+                'y' => Y,
+                'z' => Z,
+                _ => return None,
+            })
+        };
+
+        let stroke = match ch {
+            'a'..='z' => Self::new(letter(ch)?),
+            'A'..='Z' => Self::with_modifier(Shift, letter(ch)?),
+            '0' => Self::new(N0),
+            '1' => Self::new(N1),
+            '2' => Self::new(N2),
+            '3' => Self::new(N3),
+            '4' => Self::new(N4),
+            '5' => Self::new(N5),
+            '6' => Self::new(N6),
+            '7' => Self::new(N7),
+            '8' => Self::new(N8),
+            '9' => Self::new(N9),
+            ' ' => Self::new(Space),
+            '\n' => Self::new(Enter),
+            '!' => Self::with_modifier(SymShift, N1),
+            '@' => Self::with_modifier(SymShift, N2),
+            '#' => Self::with_modifier(SymShift, N3),
+            '$' => Self::with_modifier(SymShift, N4),
+            '%' => Self::with_modifier(SymShift, N5),
+            '&' => Self::with_modifier(SymShift, N6),
+            '\'' => Self::with_modifier(SymShift, N7),
+            '(' => Self::with_modifier(SymShift, N8),
+            ')' => Self::with_modifier(SymShift, N9),
+            '_' => Self::with_modifier(SymShift, N0),
+            '<' => Self::with_modifier(SymShift, R),
+            '>' => Self::with_modifier(SymShift, T),
+            ';' => Self::with_modifier(SymShift, O),
+            '"' => Self::with_modifier(SymShift, P),
+            '^' => Self::with_modifier(SymShift, H),
+            '-' => Self::with_modifier(SymShift, J),
+            '+' => Self::with_modifier(SymShift, K),
+            '=' => Self::with_modifier(SymShift, L),
+            ':' => Self::with_modifier(SymShift, Z),
+            '£' => Self::with_modifier(SymShift, X),
+            '?' => Self::with_modifier(SymShift, C),
+            '/' => Self::with_modifier(SymShift, V),
+            '*' => Self::with_modifier(SymShift, B),
+            ',' => Self::with_modifier(SymShift, N),
+            '.' => Self::with_modifier(SymShift, M),
+            _ => return None,
+        };
+        Some(stroke)
+    }
+}
+
+enum TyperState {
+    Idle,
+    Pressed {
+        stroke: KeyStroke,
+        frames_left: usize,
+    },
+    Released {
+        frames_left: usize,
+    },
+}
+
+/// Queue of key strokes which are played back with the frame cadence
+pub struct KeyboardTyper {
+    queue: VecDeque<KeyStroke>,
+    state: TyperState,
+}
+
+impl Default for KeyboardTyper {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            state: TyperState::Idle,
+        }
+    }
+}
+
+impl KeyboardTyper {
+    pub fn push(&mut self, stroke: KeyStroke) {
+        self.queue.push_back(stroke);
+    }
+
+    /// Returns true if there are no pending or currently typed keys
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_empty() && matches!(self.state, TyperState::Idle)
+    }
+
+    /// Advances typing by one frame and returns keyboard matrix layer for the next frame
+    pub fn next_frame(&mut self) -> [u8; 8] {
+        let state = match self.state {
+            TyperState::Pressed {
+                stroke,
+                frames_left,
+            } if frames_left > 1 => TyperState::Pressed {
+                stroke,
+                frames_left: frames_left - 1,
+            },
+            TyperState::Pressed { .. } => TyperState::Released {
+                frames_left: KEY_RELEASE_FRAMES,
+            },
+            TyperState::Released { frames_left } if frames_left > 1 => TyperState::Released {
+                frames_left: frames_left - 1,
+            },
+            TyperState::Released { .. } | TyperState::Idle => match self.queue.pop_front() {
+                Some(stroke) => TyperState::Pressed {
+                    stroke,
+                    frames_left: KEY_HOLD_FRAMES,
+                },
+                None => TyperState::Idle,
+            },
+        };
+        self.state = state;
+
+        let mut matrix = [0xFF; 8];
+        if let TyperState::Pressed { stroke, .. } = self.state {
+            if let Some(modifier) = stroke.modifier {
+                matrix[modifier.row_id()] &= !modifier.mask();
+            }
+            matrix[stroke.key.row_id()] &= !stroke.key.mask();
+        }
+        matrix
+    }
+}
@@ -3,14 +3,58 @@
 use crate::{
     host::{FrameBuffer, FrameBufferSource},
     zx::{
-        constants::{
-            BORDER_COLS, BORDER_ROWS, CLOCKS_PER_COL, PIXELS_PER_CLOCK, SCREEN_HEIGHT, SCREEN_WIDTH,
-        },
+        constants::{CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y, PIXELS_PER_CLOCK},
         machine::ZXMachine,
         video::colors::{ZXBrightness, ZXColor},
     },
 };
 
+// Left/right border width and top/bottom border height of the full border
+const FULL_BORDER_WIDTH: usize = 48;
+const FULL_BORDER_HEIGHT: usize = 48;
+
+/// Size of the border area which is rendered around the canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BorderSize {
+    /// Border is not rendered, frame has the canvas size (256x192)
+    None,
+    /// 32 pixels on the sides and 24 lines on top and bottom of the canvas (320x240)
+    #[default]
+    Small,
+    /// 48 pixels on the sides and 48 lines on top and bottom of the canvas (352x288)
+    Full,
+}
+
+impl BorderSize {
+    /// Returns canvas horizontal position in the frame, which is the width of the left border
+    pub fn canvas_x(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Small => CANVAS_X,
+            Self::Full => FULL_BORDER_WIDTH,
+        }
+    }
+
+    /// Returns canvas vertical position in the frame, which is the height of the top border
+    pub fn canvas_y(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Small => CANVAS_Y,
+            Self::Full => FULL_BORDER_HEIGHT,
+        }
+    }
+
+    /// Returns width of the frame including the border
+    pub fn frame_width(self) -> usize {
+        CANVAS_WIDTH + self.canvas_x() * 2
+    }
+
+    /// Returns height of the frame including the border
+    pub fn frame_height(self) -> usize {
+        CANVAS_HEIGHT + self.canvas_y() * 2
+    }
+}
+
 /// Internal struct, which contains information about beam position and color
 #[derive(Clone, Copy)]
 struct BeamInfo {
@@ -39,25 +83,31 @@ impl BeamInfo {
 /// ZX Spectrum Border Device
 pub struct ZXBorder<FB: FrameBuffer> {
     machine: ZXMachine,
+    size: BorderSize,
+    width: usize,
+    height: usize,
     buffer: FB,
     beam_last: BeamInfo,
     border_changed: bool,
     beam_block: bool,
+    // false if rendering of the current frame is skipped
+    rendering: bool,
 }
 impl<FB: FrameBuffer> ZXBorder<FB> {
     /// Returns new instance of border device
-    pub fn new(machine: ZXMachine, context: FB::Context) -> Self {
+    pub fn new(machine: ZXMachine, size: BorderSize, context: FB::Context) -> Self {
+        let width = size.frame_width();
+        let height = size.frame_height();
         ZXBorder {
             machine,
-            buffer: FB::new(
-                SCREEN_WIDTH,
-                SCREEN_HEIGHT,
-                FrameBufferSource::Border,
-                context,
-            ),
+            size,
+            width,
+            height,
+            buffer: FB::new(width, height, FrameBufferSource::Border, context),
             beam_last: BeamInfo::first_pixel(ZXColor::White),
             border_changed: true,
             beam_block: false,
+            rendering: true,
         }
     }
 
@@ -69,8 +119,8 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         // beginning of the first line (first pixel timing minus border lines
         // minus left border columns)
         let clocks_origin = specs.clocks_first_pixel
-            - 8 * BORDER_ROWS * specs.clocks_line
-            - BORDER_COLS * CLOCKS_PER_COL
+            - self.size.canvas_y() * specs.clocks_line
+            - self.size.canvas_x() / PIXELS_PER_CLOCK
             + specs.clocks_ula_beam_shift;
         // return first pixel pos
         if clocks < clocks_origin {
@@ -83,13 +133,13 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         let mut pixel = ((clocks % specs.clocks_line) + 1) * PIXELS_PER_CLOCK;
         // if beam out of screen on horizontal pos.
         // pixel - 2 because we added 2 on prev line
-        if pixel - PIXELS_PER_CLOCK >= SCREEN_WIDTH {
+        if pixel - PIXELS_PER_CLOCK >= self.width {
             // first pixel of next line
             pixel = 0;
             line += 1;
         }
         // if beam out of screen on vertical pos.
-        if line >= SCREEN_HEIGHT {
+        if line >= self.height {
             (0, 0, true)
         } else {
             (line, pixel, false)
@@ -98,17 +148,23 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
 
     /// fills pixels from last pos to passed by arguments with
     fn fill_to(&mut self, line: usize, pixel: usize) {
+        if !self.rendering {
+            return;
+        }
         let last = self.beam_last;
-        for p in (last.line * SCREEN_WIDTH + last.pixel)..(line * SCREEN_WIDTH + pixel) {
-            self.buffer.set_color(
-                p % SCREEN_WIDTH,
-                p / SCREEN_WIDTH,
-                last.color,
-                ZXBrightness::Normal,
-            );
+        let width = self.width;
+        for p in (last.line * width + last.pixel)..(line * width + pixel) {
+            self.buffer
+                .set_color(p % width, p / width, last.color, ZXBrightness::Normal);
         }
     }
 
+    /// Enables or disables rendering of the next frames. When rendering is disabled, frame
+    /// buffer keeps the last rendered frame
+    pub fn set_rendering(&mut self, rendering: bool) {
+        self.rendering = rendering;
+    }
+
     /// starts new frame
     pub fn new_frame(&mut self) {
         // if border was not changed during prev frame then force change color of whole border
@@ -117,7 +173,7 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         }
         // fill to end of screen if not already filled
         if !self.beam_block {
-            self.fill_to(SCREEN_HEIGHT - 1, SCREEN_WIDTH);
+            self.fill_to(self.height - 1, self.width);
         }
         // move beam to begin and reset flags
         self.beam_last.reset();
@@ -133,7 +189,7 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         if !self.beam_block {
             // if not first pixel then update
             if frame_end {
-                self.fill_to(SCREEN_HEIGHT - 1, SCREEN_WIDTH);
+                self.fill_to(self.height - 1, self.width);
                 self.beam_block = true;
             }
             self.fill_to(line, pixel);
@@ -145,4 +201,86 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
     pub fn frame_buffer(&self) -> &FB {
         &self.buffer
     }
+
+    pub fn size(&self) -> BorderSize {
+        self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zx::{
+        constants::{CLOCKS_PER_COL, SCREEN_HEIGHT, SCREEN_WIDTH},
+        video::testing::TestFrameBuffer,
+    };
+
+    #[test]
+    fn border_changes_are_rendered_at_beam_position() {
+        let machine = ZXMachine::Sinclair48K;
+        let specs = machine.specs();
+        let mut border = ZXBorder::<TestFrameBuffer>::new(machine, BorderSize::Small, ());
+
+        // Clocks of the first visible border pixel
+        let origin = specs.clocks_first_pixel
+            - CANVAS_Y * specs.clocks_line
+            - (CANVAS_X / 8) * CLOCKS_PER_COL
+            + specs.clocks_ula_beam_shift;
+        let line_start = |line: usize| origin + line * specs.clocks_line;
+
+        border.set_border(0, ZXColor::Red);
+        border.set_border(line_start(40), ZXColor::Green);
+        // Mid-line change, ULA draws 2 pixels per clock
+        border.set_border(line_start(60) + 80, ZXColor::Yellow);
+        border.set_border(line_start(100), ZXColor::Blue);
+        border.new_frame();
+
+        let frame = border.frame_buffer();
+        assert_eq!(frame.color(0, 0), ZXColor::Red as u8);
+        assert_eq!(frame.color(SCREEN_WIDTH - 1, 39), ZXColor::Red as u8);
+        assert_eq!(frame.color(10, 41), ZXColor::Green as u8);
+        assert_eq!(frame.color(100, 60), ZXColor::Green as u8);
+        assert_eq!(frame.color(200, 60), ZXColor::Yellow as u8);
+        assert_eq!(frame.color(0, 99), ZXColor::Yellow as u8);
+        assert_eq!(frame.color(10, 101), ZXColor::Blue as u8);
+        assert_eq!(
+            frame.color(SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1),
+            ZXColor::Blue as u8
+        );
+    }
+
+    #[test]
+    fn full_border_frame_carries_last_border_color() {
+        let machine = ZXMachine::Sinclair48K;
+        let mut border = ZXBorder::<TestFrameBuffer>::new(machine, BorderSize::Full, ());
+        assert_eq!(
+            (
+                BorderSize::Full.frame_width(),
+                BorderSize::Full.frame_height()
+            ),
+            (352, 288)
+        );
+        assert_eq!(border.frame_buffer().size(), (352, 288));
+
+        border.set_border(0, ZXColor::Red);
+        border.set_border(machine.specs().clocks_first_pixel, ZXColor::Purple);
+        border.new_frame();
+        let frame = border.frame_buffer();
+        assert_eq!(frame.color(0, 0), ZXColor::Red as u8);
+        assert_eq!(frame.color(351, 287), ZXColor::Purple as u8);
+
+        // Frame without border changes is filled with the last border color
+        border.new_frame();
+        let frame = border.frame_buffer();
+        for (x, y) in [
+            (0, 0),
+            (351, 0),
+            (0, 287),
+            (351, 287),
+            (10, 150),
+            (340, 150),
+        ] {
+            assert_eq!(frame.color(x, y), ZXColor::Purple as u8);
+        }
+    }
 }
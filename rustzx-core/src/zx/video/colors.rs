@@ -8,6 +8,7 @@ pub enum ZXBrightness {
 /// ZX Spectrum color enum
 /// Constructs self from 3-bit value
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ZXColor {
     Black = 0,
     Blue = 1,
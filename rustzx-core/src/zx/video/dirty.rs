@@ -0,0 +1,45 @@
+//! Tracking of the screen areas which were changed since the previous frame
+use crate::zx::constants::{ATTR_COLS, ATTR_ROWS};
+
+/// Map of the 8x8 canvas cells and border which look different from the previously rendered
+/// frame. Hosts can use it to upload only changed parts of the frame buffers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirtyMap {
+    // One bit per column for each character row
+    rows: [u32; ATTR_ROWS],
+    border: bool,
+}
+
+impl DirtyMap {
+    /// Returns true if the cell at (`col`, `row`) has been changed
+    pub fn is_cell_dirty(&self, col: usize, row: usize) -> bool {
+        self.rows[row] & (1 << col) != 0
+    }
+
+    /// Returns true if the border has been changed
+    pub fn is_border_dirty(&self) -> bool {
+        self.border
+    }
+
+    /// Returns true if nothing has been changed
+    pub fn is_clean(&self) -> bool {
+        !self.border && self.rows.iter().all(|row| *row == 0)
+    }
+
+    /// Returns (`col`, `row`) pairs of all changed cells
+    pub fn dirty_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(row, bits)| {
+            (0..ATTR_COLS)
+                .filter(move |col| bits & (1 << col) != 0)
+                .map(move |col| (col, row))
+        })
+    }
+
+    pub(crate) fn mark_cell(&mut self, col: usize, row: usize) {
+        self.rows[row] |= 1 << col;
+    }
+
+    pub(crate) fn set_border_dirty(&mut self, value: bool) {
+        self.border = value;
+    }
+}
@@ -5,3 +5,10 @@ pub(crate) mod border;
 pub(crate) mod screen;
 
 pub mod colors;
+pub mod dirty;
+
+#[cfg(feature = "precise-border")]
+pub use border::BorderSize;
+
+#[cfg(test)]
+pub(crate) mod testing;
@@ -2,14 +2,16 @@
 //! *block* - is 8x1 pxels stripe.
 use crate::{
     host::{FrameBuffer, FrameBufferSource},
-    utils::screen::{attr_col_rel, attr_row_rel, bitmap_col_rel, bitmap_line_rel},
+    utils::screen::{
+        attr_col_rel, attr_row_rel, bitmap_col_rel, bitmap_line_addr, bitmap_line_rel,
+    },
     zx::{
         constants::{
             ATTR_BASE_REL, ATTR_COLS, ATTR_MAX_REL, ATTR_ROWS, BITMAP_MAX_REL, CANVAS_HEIGHT,
             CANVAS_WIDTH, CLOCKS_PER_COL,
         },
         machine::ZXMachine,
-        video::colors::ZXAttribute,
+        video::{colors::ZXAttribute, dirty::DirtyMap},
     },
 };
 use alloc::boxed::Box;
@@ -90,6 +92,15 @@ pub struct ZXScreen<FB: FrameBuffer> {
     back_buffer: FB,
     banks: [ScreenBank; 2],
     active_bank: usize,
+    // false if rendering of the current frame is skipped
+    rendering: bool,
+    // Pixels and colors of each block as it was rendered last time
+    rendered_blocks: Box<[u32; ATTR_COLS * CANVAS_HEIGHT]>,
+    back_dirty: DirtyMap,
+    dirty: DirtyMap,
+    // Border color changes since the start of the current and the last rendered frames
+    border_changed: bool,
+    last_border_changed: bool,
 }
 
 impl<FB: FrameBuffer> ZXScreen<FB> {
@@ -123,6 +134,13 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
                 },
             ],
             active_bank: 0,
+            rendering: true,
+            // Never matches real block, so the first frame is reported as changed
+            rendered_blocks: Box::new([u32::MAX; ATTR_COLS * CANVAS_HEIGHT]),
+            back_dirty: DirtyMap::default(),
+            dirty: DirtyMap::default(),
+            border_changed: false,
+            last_border_changed: false,
         }
     }
 
@@ -135,8 +153,8 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
     fn local_bank(&self, bank: usize) -> Option<usize> {
         match self.machine {
             ZXMachine::Sinclair48K if bank == 0 => Some(0),
-            ZXMachine::Sinclair128K if bank == 5 => Some(0),
-            ZXMachine::Sinclair128K if bank == 7 => Some(1),
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128 if bank == 5 => Some(0),
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128 if bank == 7 => Some(1),
             _ => None,
         }
     }
@@ -155,7 +173,8 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
         let blocks = BlocksCount::from_clocks(clocks, self.machine);
         // so, let's count of 8x1 blocks, which passed.
         let count = blocks.passed_from(&self.last_blocks);
-        if count > 0 {
+        // when rendering is skipped only the beam position is tracked
+        if count > 0 && self.rendering {
             // fill pixels from prev to current
             let prev_block = self.last_blocks.lines * ATTR_COLS + self.last_blocks.columns;
             let curr_block = blocks.lines * ATTR_COLS + blocks.columns;
@@ -167,32 +186,95 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
                 let attr_row = block / (ATTR_COLS * 8);
                 let attr_col = block % ATTR_COLS;
                 let attr = self.banks[self.active_bank].attributes[attr_row * ATTR_COLS + attr_col];
-                for pixel in 0..8 {
-                    // from most significant bit
-                    let state = ((bitmap << pixel) & 0x80) != 0;
-                    self.back_buffer.set_color(
-                        (block % ATTR_COLS) * 8 + pixel,
-                        block / ATTR_COLS,
-                        attr.active_color(state, self.flash),
-                        attr.brightness,
-                    );
-                }
+                self.render_block(block, bitmap, attr);
             }
+        }
+        if count > 0 {
             // change last block to current
             self.last_blocks = blocks;
         }
     }
 
+    fn render_block(&mut self, block: usize, bitmap: u8, attr: ZXAttribute) {
+        let ink = attr.active_color(true, self.flash) as u32;
+        let paper = attr.active_color(false, self.flash) as u32;
+        let appearance = bitmap as u32 | ink << 8 | paper << 16 | (attr.brightness as u32) << 24;
+        if self.rendered_blocks[block] != appearance {
+            self.rendered_blocks[block] = appearance;
+            self.back_dirty
+                .mark_cell(block % ATTR_COLS, block / (ATTR_COLS * 8));
+        }
+        for pixel in 0..8 {
+            // from most significant bit
+            let state = ((bitmap << pixel) & 0x80) != 0;
+            self.back_buffer.set_color(
+                (block % ATTR_COLS) * 8 + pixel,
+                block / ATTR_COLS,
+                attr.active_color(state, self.flash),
+                attr.brightness,
+            );
+        }
+    }
+
+    /// Emulates ULA "snow" effect: when memory refresh happens during the ULA screen fetch
+    /// and refresh address is in contended memory, ULA fetches bitmap and attribute bytes
+    /// using `refresh_low` as the low byte of their addresses. Only the displayed block is
+    /// affected, screen memory is left intact.
+    /// `clocks` - frame clocks count at the refresh cycle
+    pub fn snow(&mut self, clocks: usize, refresh_low: u8) {
+        let specs = self.machine.specs();
+        if !self.rendering || clocks < specs.clocks_ula_read_origin {
+            return;
+        }
+        let clocks = clocks - specs.clocks_ula_read_origin;
+        let line = clocks / specs.clocks_line;
+        let line_clocks = clocks % specs.clocks_line;
+        if line >= CANVAS_HEIGHT || line_clocks >= ATTR_COLS * CLOCKS_PER_COL {
+            return;
+        }
+        // ULA fetches two bitmap/attribute pairs during the first half of each 8 clocks
+        // cycle, the second half is left for the CPU
+        let phase = line_clocks % (CLOCKS_PER_COL * 2);
+        if phase >= CLOCKS_PER_COL {
+            return;
+        }
+        let col = (line_clocks / (CLOCKS_PER_COL * 2)) * 2 + phase / 2;
+
+        let replace_low = |addr: u16| (addr & 0xFF00) | refresh_low as u16;
+        let bitmap_addr = replace_low(bitmap_line_addr(line) - 0x4000 + col as u16);
+        let attr_addr = replace_low(ATTR_BASE_REL + ((line / 8) * ATTR_COLS + col) as u16);
+        let bank = &self.banks[self.active_bank];
+        let bitmap =
+            bank.bitmap[bitmap_line_rel(bitmap_addr) * ATTR_COLS + bitmap_col_rel(bitmap_addr)];
+        let attr = bank.attributes[attr_row_rel(attr_addr) * ATTR_COLS + attr_col_rel(attr_addr)];
+        self.render_block(line * ATTR_COLS + col, bitmap, attr);
+    }
+
+    /// Enables or disables rendering of the next frames. When rendering is disabled, frame
+    /// buffer keeps the last rendered frame
+    pub fn set_rendering(&mut self, rendering: bool) {
+        self.rendering = rendering;
+    }
+
     /// starts new frame
     pub fn new_frame(&mut self) {
         // post finished bitmap to second buffer (all not-rendered part will be updated)
-        {
+        if self.rendering {
             let Self {
                 buffer,
                 back_buffer,
                 ..
             } = self;
             core::mem::swap(buffer, back_buffer);
+            self.dirty = core::mem::take(&mut self.back_dirty);
+            // Border which was changed during the previous frame is repainted with a
+            // single color on the next one
+            self.dirty
+                .set_border_dirty(self.border_changed || self.last_border_changed);
+            self.last_border_changed = self.border_changed;
+            self.border_changed = false;
+        } else {
+            self.dirty = DirtyMap::default();
         }
         self.last_blocks = BlocksCount::new(0, 0);
         if self.frame_counter % 16 == 0 {
@@ -224,7 +306,97 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
         }
     }
 
+    /// Notifies screen about the border color change, which is reported via the dirty map
+    pub fn border_changed(&mut self) {
+        self.border_changed = true;
+    }
+
+    /// Returns areas of the frame buffer which were changed since the previous frame
+    pub fn dirty_regions(&self) -> &DirtyMap {
+        &self.dirty
+    }
+
+    /// Returns current flash phase (true when ink and paper are swapped)
+    pub fn flash(&self) -> bool {
+        self.flash
+    }
+
     pub fn frame_buffer(&self) -> &FB {
         &self.buffer
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        utils::screen::bitmap_line_addr,
+        zx::video::{colors::ZXColor, testing::TestFrameBuffer},
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn attribute_change_mid_frame_is_rendered() {
+        let machine = ZXMachine::Sinclair48K;
+        let specs = machine.specs();
+        let mut screen = ZXScreen::<TestFrameBuffer>::new(machine, ());
+
+        // Solid ink in the first character cell
+        for line in 0..8 {
+            screen.update(bitmap_line_addr(line) - 0x4000, 0, 0xFF);
+        }
+        screen.update(ATTR_BASE_REL, 0, ZXColor::Blue as u8);
+
+        // Render first four lines of the cell, then change its ink color
+        screen.process_clocks(specs.clocks_ula_read_origin + 4 * specs.clocks_line - 1);
+        screen.update(ATTR_BASE_REL, 0, ZXColor::Red as u8);
+        screen.process_clocks(specs.clocks_frame);
+        screen.new_frame();
+
+        let frame = screen.frame_buffer();
+        for line in 0..4 {
+            assert_eq!(frame.color(0, line), ZXColor::Blue as u8);
+        }
+        for line in 4..8 {
+            assert_eq!(frame.color(0, line), ZXColor::Red as u8);
+        }
+    }
+
+    #[test]
+    fn only_changed_cell_is_reported_dirty() {
+        let machine = ZXMachine::Sinclair48K;
+        let specs = machine.specs();
+        let mut screen = ZXScreen::<TestFrameBuffer>::new(machine, ());
+        let render_frame = |screen: &mut ZXScreen<TestFrameBuffer>| {
+            screen.process_clocks(specs.clocks_frame);
+            screen.new_frame();
+            screen.dirty_regions().clone()
+        };
+
+        // Whole screen is reported on the first frame
+        assert_eq!(
+            render_frame(&mut screen).dirty_cells().count(),
+            ATTR_COLS * ATTR_ROWS
+        );
+        // Static frame
+        assert!(render_frame(&mut screen).is_clean());
+
+        // Change attribute of the single cell
+        let (col, row) = (5, 10);
+        screen.update(
+            ATTR_BASE_REL + (row * ATTR_COLS + col) as u16,
+            0,
+            ZXColor::Red as u8,
+        );
+        let dirty = render_frame(&mut screen);
+        assert_eq!(dirty.dirty_cells().collect::<Vec<_>>(), [(col, row)]);
+        assert!(!dirty.is_border_dirty());
+        assert!(render_frame(&mut screen).is_clean());
+
+        // Border is dirty on the frame with the change and on the following one
+        screen.border_changed();
+        assert!(render_frame(&mut screen).is_border_dirty());
+        assert!(render_frame(&mut screen).is_border_dirty());
+        assert!(render_frame(&mut screen).is_clean());
+    }
+}
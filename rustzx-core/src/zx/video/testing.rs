@@ -0,0 +1,39 @@
+//! Helpers for video devices unit tests
+use crate::{
+    host::{FrameBuffer, FrameBufferSource},
+    zx::video::colors::{ZXBrightness, ZXColor},
+};
+use alloc::{vec, vec::Vec};
+
+/// Frame buffer which stores raw color index for each pixel
+pub(crate) struct TestFrameBuffer {
+    width: usize,
+    pixels: Vec<u8>,
+}
+
+impl FrameBuffer for TestFrameBuffer {
+    type Context = ();
+
+    fn new(width: usize, height: usize, _: FrameBufferSource, _: Self::Context) -> Self {
+        Self {
+            width,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    fn set_color(&mut self, x: usize, y: usize, color: ZXColor, _: ZXBrightness) {
+        self.pixels[y * self.width + x] = color as u8;
+    }
+}
+
+impl TestFrameBuffer {
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Returns frame buffer width and height
+    #[cfg(feature = "precise-border")]
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.pixels.len() / self.width)
+    }
+}
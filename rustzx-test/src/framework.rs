@@ -2,25 +2,28 @@ use expect_test::Expect;
 use rustzx_core::{
     host::{
         BufferCursor, DebugInterface, FrameBuffer, FrameBufferSource, Host, HostContext,
-        IoExtender, RomFormat, RomSet, Snapshot, Tape,
+        IoExtender, RomFormat, RomSet, Screen, Snapshot, StubTapeListener, Tape, TraceEntry,
+        TraceSink,
     },
     poke,
     zx::{
-        keys::ZXKey,
-        machine::ZXMachine,
+        keys::{KeyboardIssue, ZXKey},
+        machine::{ZXMachine, ZXTimings},
         sound::ay::ZXAYMode,
-        video::colors::{ZXBrightness, ZXColor},
+        video::{
+            colors::{ZXBrightness, ZXColor},
+            BorderSize,
+        },
     },
-    EmulationMode, EmulationStopReason, Emulator, RustzxSettings,
+    EmulationMode, EmulationStopReason, Emulator, RustzxSettings, Z80Registers,
 };
 use rustzx_utils::{
-    io::{DynamicAsset, GzipAsset},
+    io::{self, DynamicAsset},
     palette::rgba::ORIGINAL as DEFAULT_PALETTE,
     stopwatch::InstantStopwatch,
 };
 use std::{
     collections::VecDeque,
-    io::Cursor,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -188,6 +191,18 @@ impl DebugInterface for TestDebugInterface {
     }
 }
 
+/// Trace sink which counts traced instructions
+#[derive(Default)]
+struct TraceCounter {
+    instructions: usize,
+}
+
+impl TraceSink for TraceCounter {
+    fn on_instruction(&mut self, _entry: &TraceEntry) {
+        self.instructions += 1;
+    }
+}
+
 struct TesterHost;
 
 impl Host for TesterHost {
@@ -197,6 +212,8 @@ impl Host for TesterHost {
     type FrameBuffer = FrameContent;
     type IoExtender = DebugPort;
     type TapeAsset = DynamicAsset;
+    type TapeListener = StubTapeListener;
+    type TraceSink = TraceCounter;
 }
 
 pub struct RustZXTester {
@@ -212,16 +229,28 @@ pub mod presets {
     pub fn settings_48k_nosound() -> RustzxSettings {
         RustzxSettings {
             machine: ZXMachine::Sinclair48K,
+            timings: ZXTimings::Early,
+            issue: KeyboardIssue::Issue3,
             emulation_mode: EmulationMode::FrameCount(1),
             tape_fastload_enabled: true,
+            tape_auto_control_enabled: false,
+            reset_rewinds_tape: true,
             kempston_enabled: false,
+            fuller_enabled: false,
             mouse_enabled: false,
+            ula_snow_enabled: false,
+            fast_boot: false,
+            border_size: BorderSize::Small,
             ay_mode: ZXAYMode::ABC,
             ay_enabled: false,
+            turbosound_enabled: false,
             beeper_enabled: false,
+            specdrum_port: None,
+            covox_port: None,
             sound_enabled: false,
             sound_volume: 100,
             sound_sample_rate: DEFAULT_SOUND_BITRATE,
+            sound_channels_enabled: false,
             load_default_rom: true,
             autoload_enabled: true,
         }
@@ -234,6 +263,13 @@ pub mod presets {
         }
     }
 
+    pub fn settings_pentagon_nosound() -> RustzxSettings {
+        RustzxSettings {
+            machine: ZXMachine::Pentagon128,
+            ..settings_48k_nosound()
+        }
+    }
+
     pub fn settings_48k() -> RustzxSettings {
         RustzxSettings {
             sound_enabled: true,
@@ -264,6 +300,24 @@ impl RustZXTester {
         }
     }
 
+    /// Creates tester for the machine model detected from the SNA snapshot and loads it, see
+    /// [Emulator::from_snapshot]
+    pub fn from_sna(test_name: &str, settings: RustzxSettings, name: impl AsRef<Path>) -> Self {
+        let content =
+            std::fs::read(Path::new("test_data").join(name)).expect("Failed to load asset");
+        let asset: DynamicAsset =
+            BufferCursor::new(io::unpack(content).expect("Failed to unpack asset")).into();
+        let emulator = Emulator::from_snapshot(settings, TesterContext, Snapshot::Sna(asset))
+            .expect("Failed to initialize emulator from test SNA");
+
+        Self {
+            emulator,
+            test_name: test_name.to_owned(),
+            sound_buffer: None,
+            sync_timeout: DEFAULT_SYNC_TIMEOUT,
+        }
+    }
+
     fn assets_folder(&self) -> PathBuf {
         Path::new("test_data").to_owned()
     }
@@ -274,19 +328,8 @@ impl RustZXTester {
 
     fn load_asset_data(&mut self, name: impl AsRef<Path>) -> Vec<u8> {
         let path = self.assets_folder().join(name);
-        let content = std::fs::read(&path).expect("Failed to load asset");
-
-        if path
-            .extension()
-            .map(|e| e.to_str().unwrap() == "gz")
-            .unwrap_or_default()
-        {
-            GzipAsset::new(Cursor::new(content))
-                .expect("Failed to decompress gz")
-                .into_vec()
-        } else {
-            content
-        }
+        let content = std::fs::read(path).expect("Failed to load asset");
+        io::unpack(content).expect("Failed to unpack asset")
     }
 
     fn load_asset(&mut self, name: impl AsRef<Path>) -> DynamicAsset {
@@ -295,11 +338,28 @@ impl RustZXTester {
 
     pub fn load_tap(&mut self, name: impl AsRef<Path>) {
         let asset = self.load_asset(name);
+        self.load_tap_asset(asset);
+    }
+
+    pub fn load_tap_asset(&mut self, asset: DynamicAsset) {
         self.emulator
             .load_tape(Tape::Tap(asset))
             .expect("Failed to load test TAP");
     }
 
+    pub fn load_pzx_asset(&mut self, asset: DynamicAsset) {
+        self.emulator
+            .load_tape(Tape::Pzx(asset))
+            .expect("Failed to load test PZX");
+    }
+
+    /// Swaps tape without touching the machine state, see [Emulator::insert_tape]
+    pub fn insert_tap_asset(&mut self, asset: DynamicAsset) {
+        self.emulator
+            .insert_tape(Tape::Tap(asset))
+            .expect("Failed to insert test TAP");
+    }
+
     pub fn load_sna(&mut self, name: impl AsRef<Path>) {
         let asset = self.load_asset(name);
         self.emulator
@@ -307,8 +367,21 @@ impl RustZXTester {
             .expect("Failed to load test SNA")
     }
 
+    pub fn load_scr(&mut self, name: impl AsRef<Path>) {
+        let asset = self.load_asset(name);
+        self.emulator
+            .load_screen(Screen::Scr(asset))
+            .expect("Failed to load test SCR")
+    }
+
     pub fn load_single_page_rom(&mut self, name: impl AsRef<Path>) {
         let rom_data = self.load_asset_data(name);
+        self.load_single_page_rom_data(rom_data);
+    }
+
+    /// Loads ROM from the raw data, which is padded to the 16K page size
+    pub fn load_single_page_rom_data(&mut self, mut rom_data: Vec<u8>) {
+        rom_data.resize(16 * 1024, 0);
         struct DiagRomSet {
             pages: VecDeque<Vec<u8>>,
         }
@@ -350,6 +423,19 @@ impl RustZXTester {
         }
     }
 
+    /// Starts counting of the executed instructions
+    pub fn start_instructions_trace(&mut self) {
+        self.emulator.set_trace_sink(TraceCounter::default());
+    }
+
+    /// Stops instructions tracing and returns count of the traced instructions
+    pub fn stop_instructions_trace(&mut self) -> usize {
+        self.emulator
+            .take_trace_sink()
+            .map(|counter| counter.instructions)
+            .unwrap_or(0)
+    }
+
     /// Sets the breakpoint and emulates until it is hit or the timeout is reached
     pub fn emulate_until_breakpoint(&mut self, breakpoint_addr: u16, timeout: Duration) {
         self.clear_breakpoints();
@@ -561,6 +647,21 @@ impl RustZXTester {
     pub fn peek(&mut self, addr: u16) -> u8 {
         self.emulator.peek(addr)
     }
+
+    /// Writes `program` to memory at `addr` and moves PC to its first instruction
+    pub fn load_program(&mut self, addr: u16, program: &[u8]) {
+        let pokes = program
+            .iter()
+            .enumerate()
+            .map(|(offset, byte)| (addr.wrapping_add(offset as u16), *byte))
+            .collect::<Vec<_>>();
+        self.emulator.apply_pokes(&pokes);
+        let registers = Z80Registers {
+            pc: addr,
+            ..self.emulator.registers()
+        };
+        self.emulator.set_registers(registers);
+    }
 }
 
 struct TestEnv;
@@ -0,0 +1,20 @@
+//! Builders of the in-memory tape images used by the tests
+use rustzx_core::host::BufferCursor;
+use rustzx_utils::io::DynamicAsset;
+
+/// Builds TAP image from the given blocks
+pub fn tap_image(blocks: &[&[u8]]) -> DynamicAsset {
+    let mut image = Vec::new();
+    for block in blocks {
+        image.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        image.extend_from_slice(block);
+    }
+    BufferCursor::new(image).into()
+}
+
+/// Appends PZX chunk with the given `tag` and `body` to the `image`
+pub fn push_pzx_chunk(image: &mut Vec<u8>, tag: &[u8; 4], body: &[u8]) {
+    image.extend_from_slice(tag);
+    image.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    image.extend_from_slice(body);
+}
@@ -0,0 +1,196 @@
+use rustzx_core::Z80Registers;
+use rustzx_test::framework::{presets, RustZXTester};
+
+#[test]
+fn registers_are_read_back() {
+    let mut tester = RustZXTester::new("registers_are_read_back", presets::settings_48k_nosound());
+    let registers = Z80Registers {
+        af: 0x1234,
+        bc: 0x2345,
+        de: 0x3456,
+        hl: 0x4567,
+        af_alt: 0x5678,
+        bc_alt: 0x6789,
+        de_alt: 0x789A,
+        hl_alt: 0x89AB,
+        ix: 0x9ABC,
+        iy: 0xABCD,
+        sp: 0xBCDE,
+        pc: 0xCDEF,
+        i: 0x3F,
+        r: 0x81,
+        im: 2,
+        iff1: true,
+        iff2: false,
+        halted: true,
+    };
+    tester.emulator().set_registers(registers);
+    assert_eq!(tester.emulator().registers(), registers);
+}
+
+#[test]
+fn run_until_halt_returns_exact_tstates() {
+    const PROGRAM_ADDR: u16 = 0x8000;
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,       // DI
+        0x06, 0x0A, // LD B, 10
+        0x10, 0xFE, // loop: DJNZ loop
+        0x76,       // HALT
+    ];
+    // DI + LD B,n + 9 taken DJNZ + final DJNZ + HALT
+    const PROGRAM_TSTATES: u64 = 4 + 7 + 9 * 13 + 8 + 4;
+
+    let program_tester = || {
+        let mut tester = RustZXTester::new("run_until_halt", presets::settings_48k_nosound());
+        tester.load_program(PROGRAM_ADDR, PROGRAM);
+        tester
+    };
+
+    // HALT itself should fit into the budget
+    assert!(program_tester()
+        .emulator()
+        .run_until_halt(PROGRAM_TSTATES - 1)
+        .is_err());
+
+    let mut tester = program_tester();
+    let tstates = tester.emulator().run_until_halt(PROGRAM_TSTATES).unwrap();
+    assert_eq!(tstates, PROGRAM_TSTATES);
+    // Already halted CPU is not run further
+    assert_eq!(
+        tester.emulator().run_until_halt(PROGRAM_TSTATES).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn trace_sink_receives_each_instruction() {
+    const PROGRAM_ADDR: u16 = 0x8000;
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,             // DI
+        0x21, 0x00, 0x90, // LD HL, 0x9000
+        0x06, 0x04,       // LD B, 4
+        0x70,             // loop: LD (HL), B
+        0x23,             // INC HL
+        0x10, 0xFC,       // DJNZ loop
+        0x76,             // HALT
+    ];
+    // DI + LD HL,nn + LD B,n + 4 loop iterations of 3 instructions + HALT
+    const PROGRAM_INSTRUCTIONS: usize = 3 + 4 * 3 + 1;
+
+    let mut tester = RustZXTester::new("trace_sink", presets::settings_48k_nosound());
+    tester.load_program(PROGRAM_ADDR, PROGRAM);
+
+    tester.start_instructions_trace();
+    tester.emulator().run_until_halt(1000).unwrap();
+    assert_eq!(tester.stop_instructions_trace(), PROGRAM_INSTRUCTIONS);
+}
+
+#[test]
+fn forced_interrupt_mode_controls_interrupt_acceptance() {
+    const PROGRAM_ADDR: u16 = 0x8000;
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0x76, // HALT
+        0xF3, // DI
+        0x76, // HALT
+    ];
+
+    let mut tester = RustZXTester::new("force_interrupt_mode", presets::settings_48k_nosound());
+    tester.load_program(PROGRAM_ADDR, PROGRAM);
+
+    // Interrupts are disabled, first HALT is never left
+    tester.emulator().force_interrupt_mode(1, false, true);
+    let registers = tester.emulator().registers();
+    assert_eq!(
+        (registers.im, registers.iff1, registers.iff2),
+        (1, false, true)
+    );
+    tester.emulate_frame();
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().registers().pc, PROGRAM_ADDR);
+
+    // Interrupt is taken and ROM handler returns to the second HALT
+    tester.emulator().force_interrupt_mode(1, true, true);
+    tester.emulate_frame();
+    tester.emulate_frame();
+    let registers = tester.emulator().registers();
+    assert!(registers.halted);
+    assert_eq!(registers.pc, PROGRAM_ADDR + 2);
+}
+
+#[test]
+fn io_breakpoint_stops_frame_at_port_access() {
+    const PROGRAM_ADDR: u16 = 0x8000;
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,       // DI
+        0x3E, 0x7F, // LD A, 0x7F
+        0x00,       // loop: NOP
+        0xDB, 0xFE, // IN A, (0xFE)
+        0x18, 0xFB, // JR loop
+    ];
+
+    let mut tester = RustZXTester::new("io_breakpoint", presets::settings_48k_nosound());
+    tester.load_program(PROGRAM_ADDR, PROGRAM);
+
+    // ULA port is polled with A = 0x7F in the high byte
+    tester.emulator().set_io_breakpoint(0x00FF, 0x00FE);
+    for _ in 0..2 {
+        let result = tester.emulator().emulate_frame().unwrap();
+        assert_eq!(result.io_breakpoint_pc, Some(PROGRAM_ADDR + 4));
+        assert_eq!(tester.emulator().registers().pc, PROGRAM_ADDR + 6);
+        assert_eq!(tester.emulator().frame_number(), 0);
+    }
+
+    // Other ports do not match
+    tester.emulator().set_io_breakpoint(0xFFFF, 0x00FE);
+    let result = tester.emulator().emulate_frame().unwrap();
+    assert_eq!(result.io_breakpoint_pc, None);
+    assert_eq!(tester.emulator().frame_number(), 1);
+
+    tester.emulator().clear_io_breakpoint();
+    let result = tester.emulator().emulate_frame().unwrap();
+    assert_eq!(result.io_breakpoint_pc, None);
+}
+
+#[test]
+fn io_breakpoint_reports_interrupt_handler_address() {
+    const PROGRAM_ADDR: u16 = 0x8000;
+    const VECTOR_TABLE_ADDR: u16 = 0x9000;
+    const HANDLER_ADDR: u16 = 0x9292;
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,       // DI
+        0x3E, 0x90, // LD A, 0x90
+        0xED, 0x47, // LD I, A
+        0xED, 0x5E, // IM 2
+        0xFB,       // EI
+        0x18, 0xFE, // loop: JR loop
+    ];
+    #[rustfmt::skip]
+    const HANDLER: &[u8] = &[
+        0xDB, 0xFE, // IN A, (0xFE)
+        0xFB,       // EI
+        0xC9,       // RET
+    ];
+
+    let mut tester = RustZXTester::new("io_breakpoint_int", presets::settings_48k_nosound());
+    // Vector is read from any byte of the table, as data bus value is not defined
+    let vector_table = vec![(HANDLER_ADDR >> 8) as u8; 257];
+    tester.load_program(VECTOR_TABLE_ADDR, &vector_table);
+    tester.load_program(HANDLER_ADDR, HANDLER);
+    tester.load_program(PROGRAM_ADDR, PROGRAM);
+
+    // Port is accessed by the first instruction of the handler, in the same CPU step which
+    // accepts the interrupt
+    tester.emulator().set_io_breakpoint(0x00FF, 0x00FE);
+    // Interrupt of the current frame has already passed
+    let result = tester.emulator().emulate_frame().unwrap();
+    assert_eq!(result.io_breakpoint_pc, None);
+    let result = tester.emulator().emulate_frame().unwrap();
+    assert!(result.interrupt_taken);
+    assert_eq!(result.io_breakpoint_pc, Some(HANDLER_ADDR));
+    assert_eq!(tester.emulator().registers().pc, HANDLER_ADDR + 2);
+}
@@ -95,7 +95,7 @@ fn diag_rom_ula_128k() {
     t.emulate_for(Duration::from_secs(3));
     t.expect_screen(
         "result",
-        expect![[r#"I2YQuImYmwfzNB9Y48M4ce2JVwDsQcEO2jamkLoBYxs="#]],
+        expect![[r#"qYsN78imAMbkXusiHcNjc2NNB0ek6Bqa1cDlfvfUlbY="#]],
     );
 }
 
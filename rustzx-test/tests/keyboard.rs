@@ -1,9 +1,11 @@
 use expect_test::expect;
 use rustzx_core::{
+    error::{Error, TypingError},
     zx::keys::{CompoundKey, ZXKey},
     IterableEnum,
 };
 use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
 
 #[test]
 fn standard_keys() {
@@ -115,3 +117,109 @@ fn compound_keys() {
         expect![[r#"v01HM6RHAtHfvFEnvCXae4dl1FrHEISrnDgljzvMcoE="#]],
     );
 }
+
+/// Pastes BASIC `program` to the 48K machine after its startup and returns contents of the
+/// program area after the program has been entered
+fn paste_basic_48k_program(name: &str, program: &str) -> Vec<u8> {
+    const SYSVAR_VARS: u16 = 0x5C4B;
+    const SYSVAR_PROG: u16 = 0x5C53;
+
+    let mut t = RustZXTester::new(name, presets::settings_48k_nosound());
+    t.emulate_for(Duration::from_secs(2));
+
+    t.emulator().paste_basic(program).unwrap();
+    while !t.emulator().is_typing_finished() {
+        t.emulate_frame();
+    }
+    t.emulate_for(Duration::from_millis(100));
+
+    let peek_word =
+        |t: &mut RustZXTester, addr: u16| u16::from_le_bytes([t.peek(addr), t.peek(addr + 1)]);
+    let prog = peek_word(&mut t, SYSVAR_PROG);
+    let vars = peek_word(&mut t, SYSVAR_VARS);
+    (prog..vars).map(|addr| t.peek(addr)).collect()
+}
+
+#[test]
+fn paste_basic_48k() {
+    let program = paste_basic_48k_program(
+        "paste_basic_48k",
+        "10 PRINT \"HI\"\n20 IF INKEY$ <> \"\" THEN GO TO 10\n",
+    );
+
+    #[rustfmt::skip]
+    let expected = [
+        // 10 PRINT "HI"
+        0x00, 0x0A, 0x06, 0x00,
+        0xF5, b'"', b'H', b'I', b'"', 0x0D,
+        // 20 IF INKEY$<>"" THEN GO TO 10
+        0x00, 0x14, 0x10, 0x00,
+        0xFA, 0xA6, 0xC9, b'"', b'"', 0xCB, 0xEC, b'1', b'0',
+        0x0E, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x0D,
+    ];
+    assert_eq!(program, expected);
+}
+
+#[test]
+fn paste_basic_48k_extended_symbol_keywords() {
+    let program = paste_basic_48k_program(
+        "paste_basic_48k_extended_symbol_keywords",
+        "10 PRINT ASN 1+ACS 1+ATN 1\n20 VERIFY \"\"\n30 MERGE \"\"\n",
+    );
+
+    #[rustfmt::skip]
+    let expected = [
+        // 10 PRINT ASN 1+ACS 1+ATN 1
+        0x00, 0x0A, 0x1C, 0x00,
+        0xF5,
+        0xB5, b'1', 0x0E, 0x00, 0x00, 0x01, 0x00, 0x00, b'+',
+        0xB6, b'1', 0x0E, 0x00, 0x00, 0x01, 0x00, 0x00, b'+',
+        0xB7, b'1', 0x0E, 0x00, 0x00, 0x01, 0x00, 0x00, 0x0D,
+        // 20 VERIFY ""
+        0x00, 0x14, 0x04, 0x00,
+        0xD6, b'"', b'"', 0x0D,
+        // 30 MERGE ""
+        0x00, 0x1E, 0x04, 0x00,
+        0xD5, b'"', b'"', 0x0D,
+    ];
+    assert_eq!(program, expected);
+}
+
+#[test]
+fn paste_basic_48k_keeps_comments_and_strings() {
+    let program = paste_basic_48k_program(
+        "paste_basic_48k_keeps_comments_and_strings",
+        "10 REM GO TO: PRINT::X\n20 PRINT \"AT NOT\"\n",
+    );
+
+    #[rustfmt::skip]
+    let expected = [
+        // 10 REM GO TO: PRINT::X
+        0x00, 0x0A, 0x11, 0x00,
+        0xEA, b'G', b'O', b' ', b'T', b'O', b':', b' ', b'P', b'R', b'I', b'N', b'T',
+        b':', b':', b'X', 0x0D,
+        // 20 PRINT "AT NOT"
+        0x00, 0x14, 0x0A, 0x00,
+        0xF5, b'"', b'A', b'T', b' ', b'N', b'O', b'T', b'"', 0x0D,
+    ];
+    assert_eq!(program, expected);
+}
+
+#[test]
+fn paste_basic_rejects_untypeable_characters() {
+    for settings in [
+        presets::settings_48k_nosound(),
+        presets::settings_128k_nosound(),
+    ] {
+        let mut t = RustZXTester::new("paste_basic_rejects_untypeable_characters", settings);
+        let error = t
+            .emulator()
+            .paste_basic("10 PRINT 1\n20 PRINT \"{\"\n")
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Typing(TypingError::UnsupportedCharacter { line: 2, ch: '{' })
+        ));
+        assert!(t.emulator().is_typing_finished());
+    }
+}
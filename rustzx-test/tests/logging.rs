@@ -0,0 +1,60 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use rustzx_core::host::BufferCursor;
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    images::push_pzx_chunk,
+};
+use std::sync::Mutex;
+
+/// Logger which keeps all records for the later inspection
+struct CapturingLogger {
+    records: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.records
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+#[test]
+fn unsupported_pzx_block_is_logged_as_warning() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+
+    let mut image = Vec::new();
+    push_pzx_chunk(&mut image, b"PZXT", &[1, 0]);
+    push_pzx_chunk(&mut image, b"BRWS", b"start\0");
+    push_pzx_chunk(&mut image, b"XTRA", &[0x00, 0x01]);
+    push_pzx_chunk(&mut image, b"PULS", &[0x10, 0x00]);
+
+    let mut tester = RustZXTester::new(
+        "unsupported_pzx_block_is_logged_as_warning",
+        presets::settings_48k_nosound(),
+    );
+    tester.load_pzx_asset(BufferCursor::new(image).into());
+
+    let records = LOGGER.records.lock().unwrap();
+    let warnings = records
+        .iter()
+        .filter(|(level, message)| *level == Level::Warn && message.contains("PZX"))
+        .map(|(_, message)| message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        ["Skipping unsupported PZX block \"XTRA\" at offset 24"]
+    );
+}
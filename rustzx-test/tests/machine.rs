@@ -0,0 +1,107 @@
+use expect_test::expect;
+use rustzx_core::{
+    zx::{machine::ZXMachine, video::colors::ZXColor},
+    RustzxSettings,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+#[test]
+fn pentagon_boots_128k_rom() {
+    let mut tester = RustZXTester::new(
+        "pentagon_boots_128k_rom",
+        presets::settings_pentagon_nosound(),
+    );
+    tester.emulate_for(Duration::from_secs(2));
+    tester.expect_screen(
+        "menu",
+        expect![[r#"YCEB/zJug2IJl+31Hh4RMDOmPVpn6MmpeoRHyeh27H0="#]],
+    );
+}
+
+/// Returns count of frames emulated until the copyright message is printed on the bottom
+/// line of the screen and the screen contents a second later
+fn boot_until_copyright(settings: RustzxSettings) -> (usize, Vec<u8>) {
+    const BOTTOM_LINE_ADDR: u16 = 0x50E0;
+    // `©` character of the ROM font
+    const COPYRIGHT_GLYPH: [u8; 8] = [0x3C, 0x42, 0x99, 0xA1, 0xA1, 0x99, 0x42, 0x3C];
+    const MAX_FRAMES: usize = 500;
+
+    let mut tester = RustZXTester::new("fast_boot", settings);
+    let frames = (1..=MAX_FRAMES)
+        .find(|_| {
+            tester.emulate_frame();
+            (0..32).any(|col| {
+                (0..8).all(|line| {
+                    let addr = BOTTOM_LINE_ADDR + line * 0x100 + col;
+                    tester.peek(addr) == COPYRIGHT_GLYPH[line as usize]
+                })
+            })
+        })
+        .expect("Copyright message was not printed");
+    tester.emulate_for(Duration::from_secs(1));
+    (frames, tester.emulator().screenshot_scr().to_vec())
+}
+
+#[test]
+fn fast_boot_skips_ram_test() {
+    for preset in [
+        presets::settings_48k_nosound,
+        presets::settings_128k_nosound,
+    ] {
+        let (normal_frames, normal_screen) = boot_until_copyright(preset());
+        let (fast_frames, fast_screen) = boot_until_copyright(RustzxSettings {
+            fast_boot: true,
+            ..preset()
+        });
+        assert!(fast_frames <= 5, "fast boot took {} frames", fast_frames);
+        assert!(
+            normal_frames >= 10 * fast_frames,
+            "normal boot took {} frames",
+            normal_frames
+        );
+        // Machine reaches the same state
+        assert_eq!(fast_screen, normal_screen);
+    }
+}
+
+#[test]
+fn from_snapshot_detects_machine_model() {
+    for (settings, sna, machine) in [
+        (
+            presets::settings_128k_nosound(),
+            "sound.48k.sna.gz",
+            ZXMachine::Sinclair48K,
+        ),
+        (
+            presets::settings_48k_nosound(),
+            "sound.128k.sna.gz",
+            ZXMachine::Sinclair128K,
+        ),
+        (
+            presets::settings_pentagon_nosound(),
+            "sound.128k.sna.gz",
+            ZXMachine::Pentagon128,
+        ),
+    ] {
+        let mut tester = RustZXTester::from_sna("from_snapshot", settings, sna);
+        assert_eq!(tester.emulator().machine(), machine);
+    }
+}
+
+#[test]
+fn border_is_reset_and_restored_from_snapshot() {
+    let mut tester = RustZXTester::new("border_reset", presets::settings_128k_nosound());
+    // ROM initialization sets white border
+    tester.emulate_for(Duration::from_secs(2));
+    assert_eq!(tester.emulator().border_color() as u8, ZXColor::White as u8);
+
+    tester.emulator().reset().unwrap();
+    assert_eq!(tester.emulator().border_color() as u8, ZXColor::Black as u8);
+
+    tester.load_sna("sound.128k.sna.gz");
+    assert_eq!(tester.emulator().border_color() as u8, ZXColor::White as u8);
+    // Snapshot border overrides the leftover one
+    tester.load_sna("diag_rom_v56_started.128k.sna.gz");
+    assert_eq!(tester.emulator().border_color() as u8, ZXColor::Black as u8);
+}
@@ -0,0 +1,60 @@
+use rustzx_core::{host::BufferCursor, poke::PokeSet};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+// Bank 5 is always mapped to 0x4000 on 128K, therefore poke to it via the paged window
+// should be visible in the screen memory
+const POK_128K: &[u8] = b"NBanked\n\
+    M 5 49152 170 0\n\
+    Z 8 24576  85 0\n\
+    Y\n";
+
+fn load_pok(data: &[u8]) -> PokeSet {
+    PokeSet::load_pok(BufferCursor::new(data)).expect("Failed to parse POK file")
+}
+
+#[test]
+fn apply_pokes() {
+    let mut t = RustZXTester::new("apply_pokes", presets::settings_48k_nosound());
+    t.emulate_for(Duration::from_secs(2));
+    t.emulator().apply_poke(0x8000, 0x12);
+    t.emulator().apply_pokes(&[(0x8001, 0x34), (0x8002, 0x56)]);
+    assert_eq!(t.peek(0x8000), 0x12);
+    assert_eq!(t.peek(0x8001), 0x34);
+    assert_eq!(t.peek(0x8002), 0x56);
+}
+
+#[test]
+fn pok_poke_lands_in_bank_128k() {
+    let mut t = RustZXTester::new(
+        "pok_poke_lands_in_bank_128k",
+        presets::settings_128k_nosound(),
+    );
+    t.emulate_for(Duration::from_secs(2));
+    let set = load_pok(POK_128K);
+    let trainer = &set.trainers()[0];
+
+    let paged_before = t.peek(0xC000);
+    t.emulator().execute_poke(trainer.actions(0));
+    assert_eq!(t.peek(0x4000), 170);
+    assert_eq!(t.peek(0x6000), 85);
+    // Currently paged bank should be left untouched
+    assert_eq!(t.peek(0xC000), paged_before);
+
+    t.emulator().execute_poke(trainer.revert_actions());
+    assert_eq!(t.peek(0x4000), 0);
+    assert_eq!(t.peek(0x6000), 0);
+}
+
+#[test]
+fn pok_bank_is_ignored_on_48k() {
+    let mut t = RustZXTester::new(
+        "pok_bank_is_ignored_on_48k",
+        presets::settings_48k_nosound(),
+    );
+    t.emulate_for(Duration::from_secs(2));
+    let set = load_pok(POK_128K);
+    t.emulator().execute_poke(set.trainers()[0].actions(0));
+    assert_eq!(t.peek(0xC000), 170);
+    assert_eq!(t.peek(0x6000), 85);
+}
@@ -0,0 +1,135 @@
+use expect_test::expect;
+use rustzx_core::{
+    host::{BufferCursor, Screen},
+    poke::{Poke, PokeAction},
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::palette::rgba::ORIGINAL as PALETTE;
+
+const BITMAP_SIZE: usize = 6144;
+const SCR_SIZE: usize = 6912;
+// Bright, red paper, blue ink
+const TEST_ATTRIBUTE: u8 = 0x51;
+const TEST_BITMAP: u8 = 0xAA;
+
+struct FillScreen(Vec<PokeAction>);
+
+impl FillScreen {
+    fn new() -> Self {
+        let actions = (0..SCR_SIZE)
+            .map(|offset| {
+                let value = if offset < BITMAP_SIZE {
+                    TEST_BITMAP
+                } else {
+                    TEST_ATTRIBUTE
+                };
+                PokeAction::mem(0x4000 + offset as u16, value)
+            })
+            .collect();
+        Self(actions)
+    }
+}
+
+impl Poke for FillScreen {
+    fn actions(&self) -> &[PokeAction] {
+        &self.0
+    }
+}
+
+fn rgba_pixel(buffer: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+    let offset = ((y * width + x) * 4) as usize;
+    buffer[offset..offset + 4].try_into().unwrap()
+}
+
+#[test]
+fn screenshot_scr() {
+    let mut tester = RustZXTester::new("screenshot_scr", presets::settings_48k_nosound());
+    tester.emulator().execute_poke(FillScreen::new());
+
+    let scr = tester.emulator().screenshot_scr();
+    assert!(scr[..BITMAP_SIZE].iter().all(|b| *b == TEST_BITMAP));
+    assert!(scr[BITMAP_SIZE..].iter().all(|b| *b == TEST_ATTRIBUTE));
+}
+
+#[test]
+fn screenshot_rgba() {
+    let mut tester = RustZXTester::new("screenshot_rgba", presets::settings_48k_nosound());
+    tester.emulator().execute_poke(FillScreen::new());
+
+    let (width, height, buffer) = tester.emulator().screenshot_rgba(&PALETTE, false);
+    assert_eq!((width, height), (256, 192));
+    assert_eq!(buffer.len(), 256 * 192 * 4);
+    // Bright blue ink for set bits, bright red paper for reset bits
+    assert_eq!(rgba_pixel(&buffer, width, 0, 0), PALETTE[9]);
+    assert_eq!(rgba_pixel(&buffer, width, 1, 0), PALETTE[10]);
+    assert_eq!(rgba_pixel(&buffer, width, 254, 191), PALETTE[9]);
+
+    let (width, height, buffer) = tester.emulator().screenshot_rgba(&PALETTE, true);
+    assert_eq!((width, height), (320, 240));
+    // Black border
+    assert_eq!(rgba_pixel(&buffer, width, 0, 0), PALETTE[0]);
+    assert_eq!(rgba_pixel(&buffer, width, 32, 24), PALETTE[9]);
+    assert_eq!(rgba_pixel(&buffer, width, 33, 24), PALETTE[10]);
+}
+
+#[test]
+fn shadow_screen_is_captured_from_bank_7() {
+    const PROGRAM_ADDR: u16 = 0x8000;
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,             // DI
+        0x01, 0xFD, 0x7F, // LD BC, 0x7FFD
+        0x3E, 0x08,       // LD A, 0x08 ; Shadow screen, bank 0 paged
+        0xED, 0x79,       // OUT (C), A
+        0x18, 0xFE,       // loop: JR loop
+    ];
+
+    let mut tester = RustZXTester::new("shadow_screen", presets::settings_128k_nosound());
+    for offset in 0..SCR_SIZE {
+        let value = if offset < BITMAP_SIZE {
+            TEST_BITMAP
+        } else {
+            TEST_ATTRIBUTE
+        };
+        tester.emulator().write_bank(7, offset as u16, value);
+    }
+    tester.load_program(PROGRAM_ADDR, PROGRAM);
+    tester.emulate_frame();
+    tester.emulate_frame();
+
+    let scr = tester.emulator().screenshot_scr();
+    assert!(scr[..BITMAP_SIZE].iter().all(|b| *b == TEST_BITMAP));
+    assert!(scr[BITMAP_SIZE..].iter().all(|b| *b == TEST_ATTRIBUTE));
+    let (width, _, buffer) = tester.emulator().screenshot_rgba(&PALETTE, false);
+    assert_eq!(rgba_pixel(&buffer, width, 0, 0), PALETTE[9]);
+    assert_eq!(rgba_pixel(&buffer, width, 1, 0), PALETTE[10]);
+    tester.expect_screen(
+        "result",
+        expect![[r#"kbUoB8fkGmxcOemKFR9yqIVKWZEQqzpBKv8uL3UBzhk="#]],
+    );
+}
+
+#[test]
+fn load_scr() {
+    let mut tester = RustZXTester::new("load_scr", presets::settings_48k_nosound());
+    tester.load_scr("src/rustzx.scr");
+
+    let expected = std::fs::read("test_data/src/rustzx.scr").unwrap();
+    let actual = (0..SCR_SIZE)
+        .map(|offset| tester.peek(0x4000 + offset as u16))
+        .collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+    assert_eq!(tester.emulator().screenshot_scr().to_vec(), expected);
+}
+
+#[test]
+fn load_scr_invalid_size() {
+    let mut tester = RustZXTester::new("load_scr_invalid_size", presets::settings_48k_nosound());
+    let before = tester.emulator().screenshot_scr();
+    let result = tester
+        .emulator()
+        .load_screen(Screen::Scr(BufferCursor::new(vec![0u8; SCR_SIZE - 1])));
+    assert!(result.is_err());
+    // Screen memory should be left untouched
+    assert_eq!(tester.emulator().screenshot_scr(), before);
+}
@@ -0,0 +1,105 @@
+use rustzx_core::{zx::machine::ZXMachine, EmulationMode};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+/// Longest Z80 instruction duration (e.g. `RES b, (IX+d), r`)
+const MAX_INSTRUCTION_TSTATES: usize = 23;
+/// ROM `FRAMES` system variable, incremented on each frame interrupt
+const FRAMES_ADDR: u16 = 0x5C78;
+
+fn read_frames(t: &mut RustZXTester) -> u32 {
+    u32::from_le_bytes([
+        t.peek(FRAMES_ADDR),
+        t.peek(FRAMES_ADDR + 1),
+        t.peek(FRAMES_ADDR + 2),
+        0,
+    ])
+}
+
+fn frames_emulated_for(t: &mut RustZXTester, mode: EmulationMode, duration: Duration) -> u32 {
+    t.emulator().set_speed(mode);
+    let before = read_frames(t);
+    t.emulate_for(duration);
+    read_frames(t) - before
+}
+
+#[test]
+fn speed_multiplier_scales_emulated_frames() {
+    let mut t = RustZXTester::new(
+        "speed_multiplier_scales_emulated_frames",
+        presets::settings_48k_nosound(),
+    );
+    // Wait for the ROM to finish its initialization and enable interrupts
+    t.emulate_for(Duration::from_secs(2));
+
+    let budget = Duration::from_secs(1);
+    let normal = frames_emulated_for(&mut t, EmulationMode::Multiplier(1.0), budget);
+    assert_eq!(normal, 50);
+    let double = frames_emulated_for(&mut t, EmulationMode::Multiplier(2.0), budget);
+    assert_eq!(double, normal * 2);
+    let half = frames_emulated_for(&mut t, EmulationMode::Multiplier(0.5), budget);
+    assert_eq!(half, normal / 2);
+    let frame_count = frames_emulated_for(&mut t, EmulationMode::FrameCount(2), budget);
+    assert_eq!(frame_count, double);
+}
+
+/// Returns T-states emulated since the emulator creation
+fn elapsed_tstates(t: &mut RustZXTester) -> u64 {
+    let clocks_frame = t.emulator().frame_tstates() as u64;
+    let frame_clocks = clocks_frame - t.emulator().tstates_to_interrupt() as u64;
+    t.emulator().frame_number() * clocks_frame + frame_clocks
+}
+
+#[test]
+fn emulate_for_runs_tstates_of_elapsed_time() {
+    let mut t = RustZXTester::new(
+        "emulate_for_runs_tstates_of_elapsed_time",
+        presets::settings_48k_nosound(),
+    );
+    let cpu_hz = ZXMachine::Sinclair48K.specs().freq_cpu as u64;
+
+    // 20ms is slightly longer than a 50Hz frame of 48K machine
+    t.emulator().emulate_for(20.0).unwrap();
+    assert_eq!(t.emulator().frame_number(), 1);
+    let tstates = elapsed_tstates(&mut t);
+    let ideal = cpu_hz / 50;
+    assert!(tstates >= ideal);
+    assert!(tstates - ideal < MAX_INSTRUCTION_TSTATES as u64);
+
+    // Overshoot is carried over between calls
+    t.emulator().set_speed(EmulationMode::Multiplier(2.0));
+    for _ in 0..49 {
+        t.emulator().emulate_for(20.0).unwrap();
+    }
+    let tstates = elapsed_tstates(&mut t);
+    let ideal = ideal + cpu_hz * 49 * 2 / 50;
+    assert!(tstates >= ideal);
+    assert!(tstates - ideal < MAX_INSTRUCTION_TSTATES as u64);
+}
+
+#[test]
+fn emulate_frame_tstates_do_not_drift() {
+    let mut t = RustZXTester::new(
+        "emulate_frame_tstates_do_not_drift",
+        presets::settings_48k_nosound(),
+    );
+    let clocks_frame = ZXMachine::Sinclair48K.specs().clocks_frame;
+
+    let frames = 1000;
+    let mut total = 0;
+    for frame in 0..frames {
+        let result = t.emulator().emulate_frame().unwrap();
+        assert!(result.overshoot < MAX_INSTRUCTION_TSTATES);
+        assert!(result.tstates_run + MAX_INSTRUCTION_TSTATES > clocks_frame);
+        assert!(result.tstates_run < clocks_frame + MAX_INSTRUCTION_TSTATES);
+        // ROM enables interrupts after ~1.5 seconds of initialization
+        if frame > 100 {
+            assert!(result.interrupt_taken);
+        }
+        total += result.tstates_run;
+    }
+
+    let ideal = clocks_frame * frames;
+    assert!(total >= ideal);
+    assert!(total - ideal < MAX_INSTRUCTION_TSTATES);
+}
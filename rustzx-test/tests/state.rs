@@ -0,0 +1,68 @@
+use rustzx_core::{zx::keys::ZXKey, MachineState};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+fn tape_loading_tester(name: &str) -> RustZXTester {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new(name, settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester
+}
+
+fn ram(tester: &mut RustZXTester) -> Vec<u8> {
+    (0x4000..=0xFFFF).map(|addr| tester.peek(addr)).collect()
+}
+
+#[test]
+fn machine_state_json_round_trip() {
+    let mut source = tape_loading_tester("machine_state_json_round_trip_source");
+    source.emulate_for(Duration::from_millis(2000));
+    source.send_keystrokes(
+        &[
+            &[ZXKey::J],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+    source.emulator().play_tape();
+    // Stop in the middle of the data block loading
+    source.emulate_for(Duration::from_millis(5000));
+
+    let json = serde_json::to_string(&source.emulator().machine_state()).unwrap();
+    let state: MachineState = serde_json::from_str(&json).unwrap();
+
+    let mut restored = tape_loading_tester("machine_state_json_round_trip_restored");
+    restored.emulator().load_machine_state(state).unwrap();
+
+    let restored_json = serde_json::to_string(&restored.emulator().machine_state()).unwrap();
+    assert_eq!(restored_json, json);
+    assert_eq!(ram(&mut restored), ram(&mut source));
+
+    // Tape loading should continue from the same position
+    source.emulate_for(Duration::from_millis(1000));
+    restored.emulate_for(Duration::from_millis(1000));
+    assert_eq!(ram(&mut restored), ram(&mut source));
+    assert_eq!(
+        restored.emulator().screenshot_scr(),
+        source.emulator().screenshot_scr()
+    );
+}
+
+#[test]
+fn machine_state_for_other_machine_is_rejected() {
+    let mut source = RustZXTester::new(
+        "machine_state_for_other_machine_is_rejected_48k",
+        presets::settings_48k_nosound(),
+    );
+    let state = source.emulator().machine_state();
+    let mut target = RustZXTester::new(
+        "machine_state_for_other_machine_is_rejected_128k",
+        presets::settings_128k_nosound(),
+    );
+    assert!(target.emulator().load_machine_state(state).is_err());
+}
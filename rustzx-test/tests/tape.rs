@@ -1,7 +1,19 @@
 use expect_test::expect;
-use rustzx_core::zx::keys::ZXKey;
-use rustzx_test::framework::{presets, RustZXTester};
-use std::time::Duration;
+use flate2::{write::DeflateEncoder, Compression};
+use rustzx_core::{
+    error::{EmulationError, Error},
+    zx::keys::ZXKey,
+    TapeTimings, TapeTimingsBuilder, Z80Registers,
+};
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    images::tap_image,
+};
+use rustzx_utils::io;
+use std::{
+    io::{Cursor, Write},
+    time::Duration,
+};
 
 #[test]
 fn no_fastload() {
@@ -41,14 +53,14 @@ fn no_fastload() {
     // Check tack tape is started loading
     tester.expect_border(
         "sync_pulses",
-        expect![[r#"Oc++rVrRSea7L5+dCz066kS/mPzhKZ8MhhvVo+8r5iY="#]],
+        expect![[r#"VWJOTidV0XMjDRVMMrThPgw7KLJT+e3LCH1VO6D79T8="#]],
     );
 
     // Check that data block started loading
     tester.emulate_for(Duration::from_millis(3100));
     tester.expect_border(
         "data_pulses",
-        expect![[r#"pf8oQYn3t7yAJuLr0MjOpROdoDJ1wK1CsqQvsasuDew="#]],
+        expect![[r#"ZChqev1X+PfwcEEvYrb8QhpM2DMb9OR+ZGWfuNapzjg="#]],
     );
 
     // Check that Loader has been loaded
@@ -101,14 +113,14 @@ fn no_fastload_128k() {
     // Check tack tape is started loading
     tester.expect_border(
         "sync_pulses",
-        expect![[r#"lbeRI1CgCfyRmOpuVsyzKEzvnnJe/3CZJUj+BEaypwE="#]],
+        expect![[r#"4oyFa+FDPVMEEP4VhWKIJEh+z3ACjS0jZJCCJ7AGRa8="#]],
     );
 
     // Check that data block started loading
     tester.emulate_for(Duration::from_millis(3000));
     tester.expect_border(
         "data_pulses",
-        expect![[r#"TCEfW/Ng3an2OgjmHdfYMvSdUVUt3RuEw+kYGgoEIh8="#]],
+        expect![[r#"u7LhRzaM6KIaGuBZ2K/3p6bZTdOSkPZV3/5YDSYVNio="#]],
     );
 
     // Check that Loader has been loaded
@@ -149,7 +161,7 @@ fn tape_stop() {
     // Check tack tape is started loading
     tester.expect_border(
         "sync_pulses",
-        expect![[r#"Oc++rVrRSea7L5+dCz066kS/mPzhKZ8MhhvVo+8r5iY="#]],
+        expect![[r#"VWJOTidV0XMjDRVMMrThPgw7KLJT+e3LCH1VO6D79T8="#]],
     );
 
     // Check that stop actually stopped tape loading
@@ -157,7 +169,7 @@ fn tape_stop() {
     tester.emulate_for(Duration::from_millis(100));
     tester.expect_border(
         "stopped",
-        expect![[r#"rIAW+jIqzRy5w0Xd+cqKAa6JVIgUhU5eTCvJ/kTmLuw="#]],
+        expect![[r#"CkU7FUXUKUZneunabAn/h+88EDIxzvO1aqCl5LadYEs="#]],
     );
 }
 
@@ -227,3 +239,305 @@ fn fastload_128k() {
         expect![[r#"tmGY7e4h+XA3px6BcqnCXF83NEdBqVw8PW9sQtpMAvM="#]],
     );
 }
+
+#[test]
+fn tape_auto_control() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.tape_auto_control_enabled = true;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("tape_auto_control", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(2000));
+    assert!(!tester.emulator().is_tape_playing());
+
+    // Emulate LOAD ""
+    tester.send_keystrokes(
+        &[
+            &[ZXKey::J],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+    // ROM has entered LD-BYTES, tape should be started without manual `play_tape`
+    tester.emulate_for(Duration::from_millis(100));
+    assert!(tester.emulator().is_tape_playing());
+
+    // Both blocks have been loaded, tape should be stopped after the last one
+    tester.emulate_for(Duration::from_millis(60000));
+    assert!(!tester.emulator().is_tape_playing());
+    tester.expect_screen(
+        "end",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+/// Wraps `data` to the minimal single-file zip archive with deflate compression
+fn zip_archive(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+    encoder.write_all(data).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut archive = vec![];
+    archive.extend_from_slice(b"PK\x03\x04");
+    archive.extend_from_slice(&20u16.to_le_bytes()); // version
+    archive.extend_from_slice(&0u16.to_le_bytes()); // flags
+    archive.extend_from_slice(&8u16.to_le_bytes()); // deflate
+    archive.extend_from_slice(&[0; 8]); // time, date, crc
+    archive.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+    archive.extend_from_slice(name.as_bytes());
+    archive.extend_from_slice(&compressed);
+    archive
+}
+
+#[test]
+fn compressed_tape_containers() {
+    let gzip = std::fs::read("test_data/simple_tape.tap.gz").unwrap();
+    let raw = io::unpack(gzip.clone()).unwrap();
+    assert_ne!(raw, gzip);
+    let zip = zip_archive("simple_tape.tap", &raw);
+
+    for (name, data) in [("raw", raw.clone()), ("gzip", gzip), ("zip", zip)] {
+        let asset = io::open_asset(Cursor::new(data)).unwrap();
+        let mut tester = RustZXTester::new(
+            &format!("compressed_tape_containers_{}", name),
+            presets::settings_48k_nosound(),
+        );
+        tester.load_tap_asset(asset);
+        tester.emulate_for(Duration::from_millis(100));
+        tester.expect_screen(
+            "finished",
+            expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+        );
+    }
+}
+
+#[test]
+fn loopback_tape_save_then_load() {
+    const SAVE_ADDR: u16 = 0x8000;
+    const LOAD_ADDR: u16 = 0x8010;
+    const SOURCE_ADDR: u16 = 0x9000;
+    const TARGET_ADDR: u16 = 0xA000;
+    const DATA_LEN: u16 = 16;
+    const HALT_BUDGET: u64 = 20_000_000;
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        // SAVE_ADDR
+        0xDD, 0x21, 0x00, 0x90, // LD IX, SOURCE_ADDR
+        0x11, 0x10, 0x00,       // LD DE, DATA_LEN
+        0x3E, 0xFF,             // LD A, 0xFF
+        0xCD, 0xC2, 0x04,       // CALL SA-BYTES
+        0x76,                   // HALT
+        0x00, 0x00, 0x00,
+        // LOAD_ADDR
+        0xDD, 0x21, 0x00, 0xA0, // LD IX, TARGET_ADDR
+        0x11, 0x10, 0x00,       // LD DE, DATA_LEN
+        0x3E, 0xFF,             // LD A, 0xFF
+        0x37,                   // SCF
+        0xCD, 0x56, 0x05,       // CALL LD-BYTES
+        0x76,                   // HALT
+    ];
+
+    let mut tester = RustZXTester::new("loopback_tape", presets::settings_48k_nosound());
+    // Wait for ROM to initialize system variables
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.emulator().insert_loopback_tape();
+
+    let data = (0..DATA_LEN).map(|i| (i * 17) as u8).collect::<Vec<_>>();
+    let mut pokes = PROGRAM
+        .iter()
+        .enumerate()
+        .map(|(offset, byte)| (SAVE_ADDR + offset as u16, *byte))
+        .collect::<Vec<_>>();
+    pokes.extend(
+        data.iter()
+            .enumerate()
+            .map(|(offset, byte)| (SOURCE_ADDR + offset as u16, *byte)),
+    );
+    tester.emulator().apply_pokes(&pokes);
+
+    let run_from = |tester: &mut RustZXTester, pc: u16| {
+        let registers = Z80Registers {
+            pc,
+            iff1: false,
+            iff2: false,
+            halted: false,
+            ..tester.emulator().registers()
+        };
+        tester.emulator().set_registers(registers);
+        tester.emulator().run_until_halt(HALT_BUDGET).unwrap();
+    };
+
+    run_from(&mut tester, SAVE_ADDR);
+    tester.emulator().play_tape();
+    run_from(&mut tester, LOAD_ADDR);
+
+    // Carry is set on successful load
+    assert_eq!(tester.emulator().registers().af & 0x01, 0x01);
+    let loaded = (0..DATA_LEN)
+        .map(|offset| tester.peek(TARGET_ADDR + offset))
+        .collect::<Vec<_>>();
+    assert_eq!(loaded, data);
+}
+
+#[test]
+fn reset_keeps_tape_position_if_configured() {
+    let tape_after_reset = |reset_rewinds_tape| {
+        let mut settings = presets::settings_48k_nosound();
+        settings.tape_fastload_enabled = false;
+        settings.reset_rewinds_tape = reset_rewinds_tape;
+        let mut tester = RustZXTester::new("reset_keeps_tape_position", settings);
+        tester.load_tap("simple_tape.tap.gz");
+        tester.emulator().play_tape();
+        tester.emulate_for(Duration::from_millis(3000));
+
+        let before = tester.emulator().transport_status();
+        assert!(before.playing);
+        assert!(before.block_progress > 0.0);
+        tester.emulator().reset().unwrap();
+        assert_eq!(tester.emulator().registers().pc, 0);
+        (before, tester.emulator().transport_status())
+    };
+
+    let (before, after) = tape_after_reset(false);
+    assert_eq!(after, before);
+
+    let (_, after) = tape_after_reset(true);
+    assert!(!after.playing);
+    assert_eq!(after.block, 0);
+    assert_eq!(after.block_progress, 0.0);
+}
+
+#[test]
+fn tape_is_swapped_without_touching_machine_state() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    let mut tester = RustZXTester::new("insert_tape", settings);
+    tester.load_tap_asset(tap_image(&[&[0xFF, 0x01, 0x01]]));
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(5000));
+    assert!(!tester.emulator().is_tape_playing());
+
+    let registers = tester.emulator().registers();
+    let ram_checksum = tester.emulator().ram_checksum();
+    tester.insert_tap_asset(tap_image(&[&[0x00, 0x02, 0x02], &[0xFF, 0x03, 0x03]]));
+    assert_eq!(tester.emulator().registers(), registers);
+    assert_eq!(tester.emulator().ram_checksum(), ram_checksum);
+    let status = tester.emulator().transport_status();
+    assert!(!status.playing);
+    assert_eq!((status.block, status.total_blocks), (0, 2));
+
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(500));
+    let status = tester.emulator().transport_status();
+    assert!(status.playing);
+    assert_eq!(status.block, 0);
+    assert!(tester.emulator().tape_edge_count() > 0);
+}
+
+#[test]
+fn tape_timings_override_applies_to_current_tape() {
+    // Edge count after playing the header pilot for a while
+    let pilot_edges = |instant: bool, timings: Option<TapeTimings>| {
+        let mut settings = presets::settings_48k_nosound();
+        settings.tape_fastload_enabled = false;
+        let mut tester = RustZXTester::new("tape_timings_override", settings);
+        tester.emulator().set_tape_instant_mode(instant);
+        tester.insert_tap_asset(tap_image(&[&[0x00, 0x01, 0x01]]));
+        if let Some(timings) = timings {
+            tester.emulator().override_tape_timings(timings);
+        }
+        tester.emulator().play_tape();
+        tester.emulate_for(Duration::from_millis(500));
+        tester.emulator().tape_edge_count()
+    };
+    let slow_pilot = TapeTimingsBuilder::new()
+        .pilot(2168 * 2, 8063, 3223)
+        .build();
+
+    let standard = pilot_edges(false, None);
+    let overridden = pilot_edges(false, Some(slow_pilot));
+    assert!(overridden.abs_diff(standard / 2) <= 1);
+    // Instant tape was already decoded with the standard timings
+    assert_eq!(pilot_edges(true, Some(slow_pilot)), pilot_edges(true, None));
+}
+
+#[test]
+fn tape_blocks_are_skipped() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    let mut tester = RustZXTester::new("tape_blocks_are_skipped", settings);
+    tester.load_tap_asset(tap_image(&[
+        &[0x00, 0x01, 0x01],
+        &[0xFF, 0x02, 0x02],
+        &[0xFF, 0x03, 0x03],
+    ]));
+    let current_block = |tester: &mut RustZXTester| {
+        let status = tester.emulator().transport_status();
+        assert!(!status.playing);
+        status.block
+    };
+
+    tester.emulator().skip_tape_forward().unwrap();
+    assert_eq!(current_block(&mut tester), 1);
+    // Last block is not skipped
+    tester.emulator().skip_tape_forward().unwrap();
+    tester.emulator().skip_tape_forward().unwrap();
+    assert_eq!(current_block(&mut tester), 2);
+    tester.emulator().skip_tape_back().unwrap();
+    assert_eq!(current_block(&mut tester), 1);
+    // First block is not skipped
+    tester.emulator().skip_tape_back().unwrap();
+    tester.emulator().skip_tape_back().unwrap();
+    assert_eq!(current_block(&mut tester), 0);
+}
+
+#[test]
+fn loopback_tape_block_skip_is_rejected() {
+    const PROGRAM_ADDR: u16 = 0x8000;
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,       // DI
+        0x3E, 0x08, // LD A, 0x08
+        0xD3, 0xFE, // OUT (0xFE), A
+        0x16, 0x40, // LD D, 0x40
+        0x06, 0x00, // outer: LD B, 0
+        0x10, 0xFE, // inner: DJNZ inner
+        0x15,       // DEC D
+        0x20, 0xF9, // JR NZ, outer
+        0xAF,       // XOR A
+        0xD3, 0xFE, // OUT (0xFE), A
+        0x76,       // HALT
+    ];
+
+    let mut tester = RustZXTester::new("loopback_block_skip", presets::settings_48k_nosound());
+    tester.emulator().insert_loopback_tape();
+    // Record MIC pulse which is a few frames long
+    tester.load_program(PROGRAM_ADDR, PROGRAM);
+    tester.emulator().run_until_halt(1_000_000).unwrap();
+
+    tester.emulator().play_tape();
+    tester.emulate_frame();
+    let status = tester.emulator().transport_status();
+    assert!(status.playing);
+    assert!(status.block_progress > 0.0);
+    assert_eq!(tester.emulator().tape_edge_count(), 1);
+
+    assert!(matches!(
+        tester.emulator().skip_tape_forward(),
+        Err(Error::Emulation(EmulationError::TapeNotSeekable))
+    ));
+    assert_eq!(tester.emulator().transport_status(), status);
+    assert!(matches!(
+        tester.emulator().skip_tape_back(),
+        Err(Error::Emulation(EmulationError::TapeNotSeekable))
+    ));
+    assert_eq!(tester.emulator().transport_status(), status);
+    assert_eq!(tester.emulator().tape_edge_count(), 1);
+}
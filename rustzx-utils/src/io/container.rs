@@ -0,0 +1,51 @@
+use super::{DynamicAsset, GzipAsset, ZipAsset};
+
+use rustzx_core::host::BufferCursor;
+
+use std::{
+    io::{self, Cursor, Read},
+    vec,
+    vec::Vec,
+};
+
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Compressed container kind of the asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    None,
+    Gzip,
+    Zip,
+}
+
+impl ContainerKind {
+    /// Detects container kind by the magic bytes at the start of the asset data
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(GZIP_MAGIC) {
+            Self::Gzip
+        } else if data.starts_with(ZIP_MAGIC) {
+            Self::Zip
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// Returns unpacked asset data if `data` is a gzip or single-file zip container, otherwise
+/// returns `data` as is
+pub fn unpack(data: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+    match ContainerKind::detect(&data) {
+        ContainerKind::None => Ok(data),
+        ContainerKind::Gzip => Ok(GzipAsset::new(Cursor::new(data))?.into_vec()),
+        ContainerKind::Zip => Ok(ZipAsset::new(Cursor::new(data))?.into_vec()),
+    }
+}
+
+/// Reads asset to memory, transparently unpacking it if it is wrapped in gzip or single-file
+/// zip container
+pub fn open_asset(mut file: impl Read) -> Result<DynamicAsset, io::Error> {
+    let mut data = vec![];
+    file.read_to_end(&mut data)?;
+    Ok(BufferCursor::new(unpack(data)?).into())
+}
@@ -1,5 +1,7 @@
+mod container;
 mod file;
 mod gzip;
+mod zip;
 
 use rustzx_core::{
     error::IoError,
@@ -8,8 +10,10 @@ use rustzx_core::{
 
 use std::boxed::Box;
 
+pub use container::{open_asset, unpack, ContainerKind};
 pub use file::FileAsset;
 pub use gzip::GzipAsset;
+pub use zip::ZipAsset;
 
 pub trait DynamicAssetImpl: LoadableAsset + SeekableAsset {}
 
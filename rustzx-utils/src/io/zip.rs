@@ -0,0 +1,98 @@
+use super::DynamicAssetImpl;
+
+use rustzx_core::{
+    error::IoError,
+    host::{BufferCursor, LoadableAsset, SeekFrom, SeekableAsset},
+};
+
+use std::{
+    io::{self, Read},
+    vec,
+    vec::Vec,
+};
+
+use flate2::read::DeflateDecoder;
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034B50;
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+fn invalid_archive(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Asset which contains the first file of the zip archive. Intended for single-file archives,
+/// which are common for ZX Spectrum software distribution
+pub struct ZipAsset {
+    buffer: BufferCursor<Vec<u8>>,
+}
+
+impl ZipAsset {
+    pub fn new(mut file: impl Read) -> Result<Self, io::Error> {
+        // ZX Spectrum assets are small enough to use RAM for unpacked data
+        let mut archive = vec![];
+        file.read_to_end(&mut archive)?;
+
+        let header = archive
+            .get(..LOCAL_FILE_HEADER_SIZE)
+            .ok_or_else(|| invalid_archive("Zip archive is too short"))?;
+        let u16_at = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+
+        if u32_at(0) != LOCAL_FILE_HEADER_SIGNATURE {
+            return Err(invalid_archive("Invalid zip local file header"));
+        }
+        let flags = u16_at(6);
+        let method = u16_at(8);
+        let compressed_size = u32_at(18) as usize;
+        let data_offset = LOCAL_FILE_HEADER_SIZE + u16_at(26) as usize + u16_at(28) as usize;
+        let data = archive
+            .get(data_offset..)
+            .ok_or_else(|| invalid_archive("Zip archive is too short"))?;
+
+        let buffer = match method {
+            // Size of the stored file is unknown until the data descriptor is found
+            METHOD_STORED if flags & FLAG_DATA_DESCRIPTOR == 0 => data
+                .get(..compressed_size)
+                .ok_or_else(|| invalid_archive("Zip archive is too short"))?
+                .to_vec(),
+            METHOD_DEFLATE => {
+                let mut buffer = vec![];
+                DeflateDecoder::new(data).read_to_end(&mut buffer)?;
+                buffer
+            }
+            _ => return Err(invalid_archive("Unsupported zip compression method")),
+        };
+
+        Ok(Self {
+            buffer: BufferCursor::new(buffer),
+        })
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buffer.into_inner()
+    }
+}
+
+impl SeekableAsset for ZipAsset {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, IoError> {
+        self.buffer.seek(pos)
+    }
+}
+
+impl LoadableAsset for ZipAsset {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        self.buffer.read(buf)
+    }
+}
+
+impl DynamicAssetImpl for ZipAsset {}
@@ -57,6 +57,15 @@ pub trait Z80Bus {
     fn read_interrupt(&mut self) -> u8;
     /// Method, invoked by Z80 in case of RETI instruction. Default implementation is empty
     fn reti(&mut self);
+    /// Method, invoked by Z80 when maskable interrupt is accepted. Default implementation is
+    /// empty
+    fn int_accepted(&mut self) {}
+    /// Method, invoked by Z80 when non-maskable interrupt is accepted. Default implementation
+    /// is empty
+    fn nmi_accepted(&mut self) {}
+    /// Method, invoked by Z80 after opcode fetch, when `ir` refresh address is placed on the
+    /// address bus. Default implementation is empty
+    fn refresh(&mut self, _ir: u16) {}
     /// Method, invoked by Z80 in case of HALT line change
     fn halt(&mut self, halted: bool);
     /// Checks int signal
@@ -65,6 +74,11 @@ pub trait Z80Bus {
     fn nmi_active(&self) -> bool;
     /// invokes breakpoints check on bus device
     fn pc_callback(&mut self, addr: u16);
+    /// Method, invoked by Z80 before the first opcode fetch of the instruction at `addr`,
+    /// including the first instruction of the interrupt handler. Instruction which continues
+    /// the prefix chain from the previous step is not reported again. Default implementation
+    /// is empty
+    fn instruction_start(&mut self, _addr: u16) {}
     fn process_unknown_opcode(&mut self, _prefix: Prefix, _opcode: Opcode) {}
 }
 
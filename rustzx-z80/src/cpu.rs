@@ -10,6 +10,7 @@ use crate::{
 
 /// Interrupt mode enum
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IntMode {
     Im0,
     Im1,
@@ -27,6 +28,8 @@ impl From<IntMode> for u8 {
 }
 
 /// Z80 Processor struct
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Z80 {
     /// Contains Z80 registers data
     pub regs: Regs,
@@ -60,6 +63,15 @@ impl Z80 {
         bus.read(addr, clk)
     }
 
+    /// Reads opcode byte (M1 cycle) and performs memory refresh
+    #[inline]
+    pub(crate) fn fetch_opcode(&mut self, bus: &mut impl Z80Bus) -> u8 {
+        let byte = self.fetch_byte(bus, 4);
+        bus.refresh(self.regs.get_ir());
+        self.regs.inc_r();
+        byte
+    }
+
     /// Reads word from memory and increments PC twice
     #[inline]
     pub(crate) fn fetch_word(&mut self, bus: &mut impl Z80Bus, clk: usize) -> u16 {
@@ -77,6 +89,11 @@ impl Z80 {
         self.halted
     }
 
+    /// Changes halt state. While halted, PC points to the HALT instruction
+    pub fn set_halted(&mut self, value: bool) {
+        self.halted = value;
+    }
+
     /// Returns current interrupt mode
     pub fn get_im(&self) -> IntMode {
         self.int_mode
@@ -118,6 +135,7 @@ impl Z80 {
             // push pc and set pc to 0x0066
             bus.wait_loop(self.regs.get_pc(), 5);
             self.regs.set_iff1(false);
+            bus.nmi_accepted();
             // 3 x 2 clocks consumed
             execute_push_16(self, bus, RegName16::PC, 3);
             self.regs.set_pc(0x0066);
@@ -139,6 +157,7 @@ impl Z80 {
             self.regs.inc_r();
             self.regs.set_iff1(false);
             self.regs.set_iff2(false);
+            bus.int_accepted();
             match self.int_mode {
                 // For zx spectrum both Im0 and Im1 are same
                 IntMode::Im0 | IntMode::Im1 => {
@@ -187,15 +206,14 @@ impl Z80 {
             self.active_prefix = Prefix::None;
             tmp
         } else {
-            self.regs.inc_r();
-            self.fetch_byte(bus, 4)
+            bus.instruction_start(self.regs.get_pc());
+            self.fetch_opcode(bus)
         };
         let prefix_hi = Prefix::from_byte(byte1);
         if prefix_hi != Prefix::None {
             match prefix_hi {
                 prefix_single @ Prefix::DD | prefix_single @ Prefix::FD => {
-                    let byte2 = self.fetch_byte(bus, 4);
-                    self.regs.inc_r();
+                    let byte2 = self.fetch_opcode(bus);
                     let prefix_lo = Prefix::from_byte(byte2);
                     match prefix_lo {
                         Prefix::DD | Prefix::ED | Prefix::FD => {
@@ -219,8 +237,7 @@ impl Z80 {
                     execute_bits(self, bus, Prefix::None);
                 }
                 Prefix::ED => {
-                    let byte2 = self.fetch_byte(bus, 4);
-                    self.regs.inc_r();
+                    let byte2 = self.fetch_opcode(bus);
                     let opcode = Opcode::from_byte(byte2);
                     before_execute_opcode(self);
                     execute_extended(self, bus, opcode);
@@ -117,7 +117,8 @@ impl RegName16 {
 
 /// Z80 registers
 #[rustfmt::skip]
-#[derive(Default)]
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Regs {
     pc: u16,
     sp: u16,
@@ -0,0 +1,76 @@
+//! Undocumented DDCB/FDCB forms, which copy the result of the memory operation to the
+//! register encoded in the lowest bits of the opcode
+use crate::TestingBus;
+use rustzx_z80::{
+    RegName16, RegName8, FLAG_CARRY, FLAG_F3, FLAG_F5, FLAG_HALF_CARRY, FLAG_PV, FLAG_ZERO, Z80,
+};
+
+const MEMORY_SIZE: usize = 64 * 1024;
+const PROGRAM_ADDR: u16 = 0x8000;
+const INDEX_BASE: u16 = 0xA800;
+
+/// Executes single instruction with IX and IY pointing to `INDEX_BASE`, initial `flags` and
+/// `value` at `value_addr`
+fn execute(instruction: &[u8], value_addr: u16, value: u8, flags: u8) -> (Z80, TestingBus) {
+    let mut bus = TestingBus::new(MEMORY_SIZE);
+    bus.load_to_memory(instruction, PROGRAM_ADDR);
+    bus.patch_memory(value_addr, value);
+
+    let mut cpu = Z80::default();
+    cpu.regs.set_pc(PROGRAM_ADDR);
+    cpu.regs.set_reg_16(RegName16::IX, INDEX_BASE);
+    cpu.regs.set_reg_16(RegName16::IY, INDEX_BASE);
+    cpu.regs.set_reg_16(RegName16::BC, 0x5555);
+    cpu.regs.set_reg_16(RegName16::HL, 0x5555);
+    cpu.regs.set_flags(flags);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), PROGRAM_ADDR + instruction.len() as u16);
+    (cpu, bus)
+}
+
+#[test]
+fn rotation_result_is_copied_to_register() {
+    // RLC (IX+5), B
+    let addr = INDEX_BASE + 5;
+    let (cpu, mut bus) = execute(&[0xDD, 0xCB, 0x05, 0x00], addr, 0x81, 0);
+    assert_eq!(bus.read_memory(addr), 0x03);
+    assert_eq!(cpu.regs.get_reg_8(RegName8::B), 0x03);
+    assert_eq!(cpu.regs.get_flags(), FLAG_CARRY | FLAG_PV);
+
+    // SRL (IY+2), C
+    let addr = INDEX_BASE + 2;
+    let (cpu, mut bus) = execute(&[0xFD, 0xCB, 0x02, 0x39], addr, 0x81, 0);
+    assert_eq!(bus.read_memory(addr), 0x40);
+    assert_eq!(cpu.regs.get_reg_8(RegName8::C), 0x40);
+    assert_eq!(cpu.regs.get_flags(), FLAG_CARRY);
+}
+
+#[test]
+fn set_and_reset_results_are_copied_to_register() {
+    // SET 7, (IY-2), A
+    let addr = INDEX_BASE - 2;
+    let (cpu, mut bus) = execute(&[0xFD, 0xCB, 0xFE, 0xFF], addr, 0x01, FLAG_ZERO);
+    assert_eq!(bus.read_memory(addr), 0x81);
+    assert_eq!(cpu.regs.get_acc(), 0x81);
+    assert_eq!(cpu.regs.get_flags(), FLAG_ZERO);
+
+    // RES 0, (IX+0), L
+    let (cpu, mut bus) = execute(&[0xDD, 0xCB, 0x00, 0x85], INDEX_BASE, 0xFF, 0);
+    assert_eq!(bus.read_memory(INDEX_BASE), 0xFE);
+    assert_eq!(cpu.regs.get_reg_8(RegName8::L), 0xFE);
+    assert_eq!(cpu.regs.get_flags(), 0);
+}
+
+#[test]
+fn bit_test_does_not_change_register() {
+    // BIT 3, (IX+1) with register B encoded
+    let addr = INDEX_BASE + 1;
+    let (cpu, mut bus) = execute(&[0xDD, 0xCB, 0x01, 0x58], addr, 0x00, FLAG_CARRY);
+    assert_eq!(bus.read_memory(addr), 0x00);
+    assert_eq!(cpu.regs.get_reg_8(RegName8::B), 0x55);
+    // F3 and F5 are taken from the high byte of the computed address
+    assert_eq!(
+        cpu.regs.get_flags(),
+        FLAG_CARRY | FLAG_HALF_CARRY | FLAG_ZERO | FLAG_PV | FLAG_F3 | FLAG_F5
+    );
+}
@@ -4,6 +4,7 @@ use crate::{app::settings::Settings, backends::SDL_CONTEXT};
 use rustzx_core::{
     zx::{
         joy::{
+            fuller::FullerKey,
             kempston::KempstonKey,
             sinclair::{SinclairJoyNum, SinclairKey},
         },
@@ -24,6 +25,7 @@ pub struct EventsSdl {
     event_pump: EventPump,
     mouse: MouseUtil,
     kempston_enabled: bool,
+    fuller_enabled: bool,
     mouse_enabled: bool,
     mouse_locked: bool,
     mouse_sensitivity: usize,
@@ -54,6 +56,7 @@ impl EventsSdl {
             mouse_enabled: settings.enable_mouse,
             mouse_locked: false,
             kempston_enabled: !settings.disable_kempston,
+            fuller_enabled: settings.enable_fuller,
             enable_joy_keyaboard_layer: false,
             mouse_sensitivity: settings.mouse_sensitivity,
             mouse_x_counter: 0,
@@ -159,7 +162,7 @@ impl EventsSdl {
         scancode: Option<Scancode>,
         pressed: bool,
     ) -> Option<Event> {
-        if !(self.kempston_enabled && self.enable_joy_keyaboard_layer) {
+        if !(self.kempston_enabled && !self.fuller_enabled && self.enable_joy_keyaboard_layer) {
             return None;
         }
 
@@ -175,6 +178,24 @@ impl EventsSdl {
         kempston_event.map(|k| Event::Kempston(k, pressed))
     }
 
+    /// returns fuller key form scancode of None if not found
+    fn scancode_to_fuller_event(&self, scancode: Option<Scancode>, pressed: bool) -> Option<Event> {
+        if !(self.fuller_enabled && self.enable_joy_keyaboard_layer) {
+            return None;
+        }
+
+        let fuller_event = match scancode? {
+            Scancode::LAlt | Scancode::RAlt => Some(FullerKey::Fire),
+            Scancode::Up => Some(FullerKey::Up),
+            Scancode::Down => Some(FullerKey::Down),
+            Scancode::Left => Some(FullerKey::Left),
+            Scancode::Right => Some(FullerKey::Right),
+            _ => None,
+        };
+
+        fuller_event.map(|k| Event::Fuller(k, pressed))
+    }
+
     fn scancode_to_sinclair_event(
         &self,
         scancode: Option<Scancode>,
@@ -216,6 +237,7 @@ impl EventsSdl {
                 Scancode::F4 => Some(Event::ChangeSpeed(EmulationMode::FrameCount(2))),
                 Scancode::F5 => Some(Event::ChangeSpeed(EmulationMode::Max)),
                 Scancode::F6 => Some(Event::SwitchFrameTrace),
+                Scancode::F7 => Some(Event::Nmi),
                 Scancode::F9 => {
                     self.enable_joy_keyaboard_layer = !self.enable_joy_keyaboard_layer;
                     Some(Event::ChangeJoyKeyboardLayer(
@@ -256,6 +278,7 @@ impl EventDevice for EventsSdl {
                     // Form highest priority event to lowest
                     self.scancode_to_emulator_event(scancode, pressed)
                         .or_else(|| self.scancode_to_kempston_event(scancode, pressed))
+                        .or_else(|| self.scancode_to_fuller_event(scancode, pressed))
                         .or_else(|| self.scancode_to_sinclair_event(scancode, pressed))
                         .or_else(|| self.scancode_to_zxkey_event(scancode, pressed))
                         .or_else(|| self.scancode_to_compound_key_event(scancode, pressed))
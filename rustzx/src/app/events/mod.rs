@@ -5,6 +5,7 @@ mod events_sdl;
 use rustzx_core::{
     zx::{
         joy::{
+            fuller::FullerKey,
             kempston::KempstonKey,
             sinclair::{SinclairJoyNum, SinclairKey},
         },
@@ -22,6 +23,7 @@ pub enum Event {
     ZXKey(ZXKey, bool),
     CompoundKey(CompoundKey, bool),
     Kempston(KempstonKey, bool),
+    Fuller(FullerKey, bool),
     Sinclair(SinclairJoyNum, SinclairKey, bool),
     MouseMove { x: i8, y: i8 },
     MouseButton(KempstonMouseButton, bool),
@@ -33,6 +35,7 @@ pub enum Event {
     StopTape,
     QuickSave,
     QuickLoad,
+    Nmi,
     OpenFile(PathBuf),
     Exit,
 }
@@ -14,9 +14,7 @@ use crate::{
 use anyhow::{anyhow, Context};
 use rustzx_core::{
     host::SnapshotRecorder,
-    zx::constants::{
-        CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y, FPS, SCREEN_HEIGHT, SCREEN_WIDTH,
-    },
+    zx::constants::{CANVAS_HEIGHT, CANVAS_WIDTH, FPS},
     Emulator,
 };
 use rustzx_utils::io::FileAsset;
@@ -65,7 +63,10 @@ impl RustzxApp {
             None
         };
         let mut video = Box::new(VideoSdl::new(&settings));
-        let tex_border = video.gen_texture(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+        let tex_border = video.gen_texture(
+            settings.border_size.frame_width() as u32,
+            settings.border_size.frame_height() as u32,
+        );
         let tex_canvas = video.gen_texture(CANVAS_WIDTH as u32, CANVAS_HEIGHT as u32);
         let scale = settings.scale as u32;
         let events = Box::new(EventsSdl::new(&settings));
@@ -163,21 +164,22 @@ impl RustzxApp {
             self.video
                 .update_texture(self.tex_canvas, self.emulator.screen_buffer().rgba_data());
 
+            let border_size = self.emulator.border_size();
             self.video.begin();
             self.video.draw_texture_2d(
                 self.tex_border,
                 Some(Rect::new(
                     0,
                     0,
-                    SCREEN_WIDTH as u32 * scale,
-                    SCREEN_HEIGHT as u32 * scale,
+                    border_size.frame_width() as u32 * scale,
+                    border_size.frame_height() as u32 * scale,
                 )),
             );
             self.video.draw_texture_2d(
                 self.tex_canvas,
                 Some(Rect::new(
-                    CANVAS_X as i32 * scale as i32,
-                    CANVAS_Y as i32 * scale as i32,
+                    border_size.canvas_x() as i32 * scale as i32,
+                    border_size.canvas_y() as i32 * scale as i32,
                     CANVAS_WIDTH as u32 * scale,
                     CANVAS_HEIGHT as u32 * scale,
                 )),
@@ -206,6 +208,9 @@ impl RustzxApp {
                     Event::Kempston(key, state) => {
                         self.emulator.send_kempston_key(key, state);
                     }
+                    Event::Fuller(key, state) => {
+                        self.emulator.send_fuller_key(key, state);
+                    }
                     Event::Sinclair(num, key, state) => {
                         self.emulator.send_sinclair_key(num, key, state);
                     }
@@ -226,6 +231,7 @@ impl RustzxApp {
                     Event::OpenFile(path) => self.load_file_autodetect(&path)?,
                     Event::QuickSave => self.quick_save()?,
                     Event::QuickLoad => self.quick_load()?,
+                    Event::Nmi => self.emulator.trigger_nmi(),
                 }
             }
             // how long emulation iteration was
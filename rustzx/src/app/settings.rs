@@ -1,5 +1,13 @@
 use rustzx_core::{
-    zx::{machine::ZXMachine, sound::ay::ZXAYMode},
+    zx::{
+        keys::KeyboardIssue,
+        machine::{ZXMachine, ZXTimings},
+        sound::{
+            ay::ZXAYMode,
+            dac::{COVOX_DEFAULT_PORT, SPECDRUM_DEFAULT_PORT},
+        },
+        video::BorderSize,
+    },
     EmulationMode, RustzxSettings,
 };
 use std::path::PathBuf;
@@ -28,15 +36,27 @@ pub struct Settings {
     /// Specify machine type for launch. Possible values:
     ///   [`48k`, `48`] - Sinclair ZX Spectrum 48K
     ///   [`128k`, `128`] - Sinclair ZX Spectrum 128K
+    ///   [`pentagon`] - Pentagon 128
     #[structopt(verbatim_doc_comment, short, long, default_value = "48k", parse(try_from_str = machine_from_str))]
     pub machine: ZXMachine,
+    /// Emulate late ULA timings, on which contention, floating bus and screen rendering start
+    /// one clock later relative to the interrupt
+    #[structopt(long = "late-timings")]
+    pub late_timings: bool,
+    /// Emulate issue 2 keyboard EAR bit behavior instead of issue 3
+    #[structopt(long = "issue2")]
+    pub issue2: bool,
     /// Set emulation speed at emualtor start-up. Can be specified as deciamal non-zero
-    /// value or as a special value `MAX` to run emulator as fast as possible
+    /// value, as a fractional speed multiplier (e.g. `0.5`) or as a special value `MAX` to run
+    /// emulator as fast as possible
     #[structopt(long, default_value = "1", parse(try_from_str = emulation_speed_from_str))]
     pub speed: EmulationMode,
     /// Disable fast tape loading
     #[structopt(long = "nofastload")]
     pub disable_fastload: bool,
+    /// Start and stop tape automatically when ROM enters and leaves tape loading routine
+    #[structopt(long = "tape-auto")]
+    pub tape_auto_control: bool,
     /// Disable automatic tape loading via placing emulator to tape load state immediately
     /// after launch
     #[structopt(long = "noautoload")]
@@ -44,13 +64,27 @@ pub struct Settings {
     /// Set windows scale for emulator. Can be set as decimal non-zero value. Defaults to 2
     #[structopt(short, long, default_value = "2", parse(try_from_str = scale_from_str))]
     pub scale: usize,
+    /// Set size of the rendered border. Can be set to `none`, `small` (320x240 frame) or
+    /// `full` (352x288 frame). Defaults to `small`
+    #[structopt(long = "border", default_value = "small", parse(try_from_str = border_size_from_str))]
+    pub border_size: BorderSize,
     /// Disable kempston joy support. If enabled, arrow and `Alt` keys are bound by default
     /// to the kempston joy
     #[structopt(long = "nokempston")]
     pub disable_kempston: bool,
+    /// Enables Fuller Box support: AY chip on Fuller ports and Fuller joystick. If enabled,
+    /// arrow and `Alt` keys are bound to the Fuller joystick instead of kempston joy
+    #[structopt(long = "fuller")]
+    pub enable_fuller: bool,
     /// Enables kempston mouse support. If enabled, locks mouse in application
     #[structopt(long = "mouse")]
     pub enable_mouse: bool,
+    /// Emulates ULA "snow" effect when the I register points to the contended memory
+    #[structopt(long = "snow")]
+    pub enable_snow: bool,
+    /// Skip startup RAM test of the original ROMs
+    #[structopt(long = "fast-boot")]
+    pub fast_boot: bool,
     /// Sets mouse sensitivity [1..=100]. Defaults to 20
     #[structopt(long = "mouse-sensitivity", default_value = "20")]
     pub mouse_sensitivity: usize,
@@ -65,9 +99,18 @@ pub struct Settings {
     /// Force disable AY-3-8910 chip on supported systems
     #[structopt(long = "noay", conflicts_with = "force-enable-ay")]
     pub force_disable_ay: bool,
+    /// Enable Turbosound (second AY-3-8910 chip)
+    #[structopt(long = "turbosound")]
+    pub enable_turbosound: bool,
     /// Disable beeper
     #[structopt(long = "nobeeper")]
     pub disable_beeper: bool,
+    /// Enable SpecDrum DAC on port `0xDF`
+    #[structopt(long = "specdrum")]
+    pub enable_specdrum: bool,
+    /// Enable Covox DAC on port `0xFB`
+    #[structopt(long = "covox")]
+    pub enable_covox: bool,
     /// Disable sound
     #[structopt(long = "nosound")]
     pub disable_sound: bool,
@@ -106,6 +149,7 @@ fn machine_from_str(s: &str) -> Result<ZXMachine, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "48k" | "48" => Ok(ZXMachine::Sinclair48K),
         "128k" | "128" => Ok(ZXMachine::Sinclair128K),
+        "pentagon" => Ok(ZXMachine::Pentagon128),
         s => Err(anyhow::anyhow!("Invalid machine type `{}`", s)),
     }
 }
@@ -114,11 +158,15 @@ fn emulation_speed_from_str(s: &str) -> Result<EmulationMode, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "max" => Ok(EmulationMode::Max),
         s => {
-            let speed: std::num::NonZeroUsize = s
-                .parse()
-                .map_err(|_| anyhow::anyhow!("Invalid emulation speed `{}`", s))?;
-
-            Ok(EmulationMode::FrameCount(speed.into()))
+            if let Ok(speed) = s.parse::<std::num::NonZeroUsize>() {
+                return Ok(EmulationMode::FrameCount(speed.into()));
+            }
+            match s.parse::<f32>() {
+                Ok(multiplier) if multiplier.is_finite() && multiplier > 0.0 => {
+                    Ok(EmulationMode::Multiplier(multiplier))
+                }
+                _ => Err(anyhow::anyhow!("Invalid emulation speed `{}`", s)),
+            }
         }
     }
 }
@@ -131,6 +179,15 @@ fn scale_from_str(s: &str) -> Result<usize, anyhow::Error> {
     Ok(scale.into())
 }
 
+fn border_size_from_str(s: &str) -> Result<BorderSize, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "none" => Ok(BorderSize::None),
+        "small" => Ok(BorderSize::Small),
+        "full" => Ok(BorderSize::Full),
+        s => Err(anyhow::anyhow!("Invalid border size `{}`", s)),
+    }
+}
+
 fn ay_mode_from_str(s: &str) -> Result<ZXAYMode, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "mono" => Ok(ZXAYMode::Mono),
@@ -173,22 +230,45 @@ fn sound_sample_rate_from_str(s: &str) -> Result<usize, anyhow::Error> {
 
 impl Settings {
     pub fn to_rustzx_settings(&self, sound_sample_rate: usize) -> RustzxSettings {
-        let ay_enabled = (matches!(self.machine, ZXMachine::Sinclair128K) || self.force_enable_ay)
+        let ay_enabled = (matches!(
+            self.machine,
+            ZXMachine::Sinclair128K | ZXMachine::Pentagon128
+        ) || self.force_enable_ay)
             && (!self.force_disable_ay);
 
         RustzxSettings {
             machine: self.machine,
+            timings: if self.late_timings {
+                ZXTimings::Late
+            } else {
+                ZXTimings::Early
+            },
+            issue: if self.issue2 {
+                KeyboardIssue::Issue2
+            } else {
+                KeyboardIssue::Issue3
+            },
             emulation_mode: self.speed,
             tape_fastload_enabled: !self.disable_fastload,
+            tape_auto_control_enabled: self.tape_auto_control,
+            reset_rewinds_tape: true,
             kempston_enabled: !self.disable_kempston,
+            fuller_enabled: self.enable_fuller,
             mouse_enabled: self.enable_mouse,
+            ula_snow_enabled: self.enable_snow,
+            fast_boot: self.fast_boot,
+            border_size: self.border_size,
             ay_mode: self.ay_mode,
             ay_enabled,
+            turbosound_enabled: self.enable_turbosound,
             beeper_enabled: !self.disable_beeper,
+            specdrum_port: self.enable_specdrum.then_some(SPECDRUM_DEFAULT_PORT),
+            covox_port: self.enable_covox.then_some(COVOX_DEFAULT_PORT),
             sound_enabled: !self.disable_sound,
             sound_volume: 100,
             load_default_rom: self.rom.is_none(),
             sound_sample_rate,
+            sound_channels_enabled: false,
             autoload_enabled: !self.disable_autoload,
         }
     }
@@ -5,18 +5,18 @@ use frame_buffer::{FrameBufferContext, RgbaFrameBuffer};
 use rustzx_core::{
     host::{
         FrameBuffer, Host, HostContext, RomFormat, RomSet, Screen, Snapshot, StubDebugInterface,
-        StubIoExtender, Tape,
+        StubIoExtender, StubTapeListener, StubTraceSink, Tape,
     },
     zx::machine::ZXMachine,
 };
 use rustzx_utils::{
-    io::{DynamicAsset, FileAsset, GzipAsset},
+    io::{open_asset, DynamicAsset},
     stopwatch::InstantStopwatch,
 };
 use std::{collections::VecDeque, fs::File, path::Path};
 
 const SUPPORTED_SNAPSHOT_FORMATS: [&str; 1] = ["sna"];
-const SUPPORTED_TAPE_FORMATS: [&str; 1] = ["tap"];
+const SUPPORTED_TAPE_FORMATS: [&str; 3] = ["tap", "pzx", "csw"];
 const SUPPORTED_SCREEN_FORMATS: [&str; 1] = ["scr"];
 
 pub struct AppHost;
@@ -28,6 +28,8 @@ impl Host for AppHost {
     type FrameBuffer = RgbaFrameBuffer;
     type IoExtender = StubIoExtender;
     type TapeAsset = DynamicAsset;
+    type TapeListener = StubTapeListener;
+    type TraceSink = StubTraceSink;
 }
 
 pub struct AppHostContext;
@@ -63,20 +65,13 @@ pub enum DetectedFileKind {
 pub enum DetectedContainerKind {
     None,
     Gzip,
+    Zip,
 }
 
 pub fn load_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
-    let container_kind = detect_container(path);
-
-    let file = File::open(path).with_context(|| "Failed to open tape file")?;
-
-    match container_kind {
-        DetectedContainerKind::None => Ok(FileAsset::from(file).into()),
-        DetectedContainerKind::Gzip => {
-            let gzip = GzipAsset::new(file)?;
-            Ok(gzip.into())
-        }
-    }
+    let file = File::open(path).with_context(|| "Failed to open asset file")?;
+    // Compressed containers are detected by content, file extension is not relevant here
+    open_asset(file).with_context(|| "Failed to read asset file")
 }
 
 pub fn load_tape(path: &Path) -> anyhow::Result<Tape<DynamicAsset>> {
@@ -88,9 +83,15 @@ pub fn load_tape(path: &Path) -> anyhow::Result<Tape<DynamicAsset>> {
         bail!("Provided tape file does not exist");
     }
 
-    load_asset(path)
-        .map(Tape::Tap)
-        .with_context(|| "Failed to load tape file")
+    let asset = load_asset(path);
+    if file_extension_matches_one_of(path, &["pzx"]) {
+        asset.map(Tape::Pzx)
+    } else if file_extension_matches_one_of(path, &["csw"]) {
+        asset.map(Tape::Csw)
+    } else {
+        asset.map(Tape::Tap)
+    }
+    .with_context(|| "Failed to load tape file")
 }
 
 pub fn load_snapshot(path: &Path) -> anyhow::Result<Snapshot<DynamicAsset>> {
@@ -138,7 +139,7 @@ pub fn load_rom(path: &Path, machine: ZXMachine) -> anyhow::Result<FileRomSet> {
                 ]),
             })
         }
-        ZXMachine::Sinclair128K => {
+        ZXMachine::Sinclair128K | ZXMachine::Pentagon128 => {
             let rom0_path = path;
             if !file_extension_matches(rom0_path, "0") {
                 bail!("128K ROM filename should end with '.0' extension");
@@ -197,6 +198,7 @@ fn detect_container(path: &Path) -> DetectedContainerKind {
 
     match ext.as_str() {
         "gz" => DetectedContainerKind::Gzip,
+        "zip" => DetectedContainerKind::Zip,
         _ => DetectedContainerKind::None,
     }
 }
@@ -237,6 +239,7 @@ mod tests {
         assert!(file_extension_matches(&Path::new("test.tAp"), "tap"));
         assert!(file_extension_matches(&Path::new("test.tap.gz"), "tap"));
         assert!(file_extension_matches(&Path::new("test.tap.gZ"), "tap"));
+        assert!(file_extension_matches(&Path::new("test.tap.zip"), "tap"));
     }
 
     #[test]